@@ -56,7 +56,7 @@ impl IGame for TestGame {
            let view_port = world.fetch::<ViewPortSize>();
            (view_port.width() as f32,view_port.height() as f32)
         };
-        let entity = world.create_entity().with(camera_transform).with(Camera::standard_2d(w, h)).build();
+        let entity = world.create_entity().with(camera_transform).with(Camera::standard_2d(w, h).with_follow_view_size(true)).build();
         world.insert(ActiveCamera {entity : Some(entity) });
         world.fetch::<S2DLoader>().env().set_fs_root("./res/");
         self.test.start(world);
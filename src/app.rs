@@ -5,6 +5,7 @@ use crate::core::{
 use specs::{World,WorldExt,DispatcherBuilder,Dispatcher};
 use shrev::{ReaderId,EventChannel};
 use crate::core::{IGame};
+use crate::event::{EventReplayer};
 use rayon::{ThreadPoolBuilder};
 #[cfg(feature = "profiler")]
 use thread_profiler::{profile_scope, register_thread_with_profiler, write_profile};
@@ -73,7 +74,11 @@ impl<G,T> App<G,T> where T:IModuleBundle,G:IGame {
             
 
             self.update_limiter.wait();
-            let elapsed = world.write_resource::<Stopwatch>().elapsed();
+            let mut elapsed = world.write_resource::<Stopwatch>().elapsed();
+            //回放事件时使用固定的帧间隔,保证结果可以复现
+            if let Some(replayer) = world.try_fetch::<EventReplayer>() {
+                elapsed = replayer.delta();
+            }
             world.write_resource::<Time>().set_delta_time(elapsed);
             world.write_resource::<Time>().inc_frame_number();
             world.write_resource::<Stopwatch>().stop();
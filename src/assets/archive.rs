@@ -0,0 +1,245 @@
+use crate::assets::{Source,AssetLoadError,AssetErrorKind};
+use byteorder::{LittleEndian,ReadBytesExt,WriteBytesExt};
+use std::collections::HashMap;
+use std::fs::{File};
+use std::io::{self,Read,Seek,SeekFrom,Write};
+use std::path::{Path,PathBuf};
+use std::sync::{Mutex};
+
+const MAGIC:&[u8;4] = b"SPAK";
+const VERSION:u32 = 1;
+const FLAG_DEFLATE:u8 = 1;
+//索引项除path外的字节数
+const ENTRY_FIXED_SIZE:u64 = 2 + 8 * 3 + 1 + 4;
+
+//包文件格式(小端):
+//magic "SPAK" | version u32 | count u32 | count个索引项 | 数据区
+//索引项: path_len u16 | path | offset u64 | size u64 | raw_size u64 | flags u8 | crc32 u32
+//offset为数据区内偏移,crc32为解压后数据的校验
+#[derive(Debug,Clone)]
+struct PackEntry {
+    offset:u64,
+    size:u64,
+    raw_size:u64,
+    flags:u8,
+    crc:u32
+}
+
+#[derive(Default)]
+struct PackIndex {
+    data_start:u64,
+    entries:HashMap<String,PackEntry>
+}
+
+impl PackIndex {
+    //索引中的数量和长度都和文件大小比较,损坏的包文件不会导致大量分配
+    fn read<R:Read + Seek>(reader:&mut R) -> io::Result<PackIndex> {
+        let invalid = |msg:&str| io::Error::new(io::ErrorKind::InvalidData,msg);
+        let start = reader.stream_position()?;
+        let file_len = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(start))?;
+        let mut magic = [0u8;4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,"not a pack file"));
+        }
+        if reader.read_u32::<LittleEndian>()? != VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,"unsupported pack version"));
+        }
+        let count = reader.read_u32::<LittleEndian>()?;
+        if count as u64 * ENTRY_FIXED_SIZE > file_len.saturating_sub(reader.stream_position()?) {
+            return Err(invalid("pack entry count exceeds file size"));
+        }
+        let mut entries = HashMap::with_capacity(count as usize);
+        for _ in 0..count {
+            let path_len = reader.read_u16::<LittleEndian>()?;
+            if path_len as u64 > file_len.saturating_sub(reader.stream_position()?) {
+                return Err(invalid("pack entry path exceeds file size"));
+            }
+            let mut path = vec![0u8;path_len as usize];
+            reader.read_exact(&mut path)?;
+            let path = String::from_utf8(path).map_err(|e| io::Error::new(io::ErrorKind::InvalidData,e))?;
+            let entry = PackEntry {
+                offset:reader.read_u64::<LittleEndian>()?,
+                size:reader.read_u64::<LittleEndian>()?,
+                raw_size:reader.read_u64::<LittleEndian>()?,
+                flags:reader.read_u8()?,
+                crc:reader.read_u32::<LittleEndian>()?
+            };
+            entries.insert(path,entry);
+        }
+        let data_start = reader.stream_position()?;
+        for entry in entries.values() {
+            let end = data_start.checked_add(entry.offset).and_then(|v| v.checked_add(entry.size));
+            if end.is_none_or(|end| end > file_len) {
+                return Err(invalid("pack entry exceeds file size"));
+            }
+        }
+        Ok(PackIndex {data_start,entries })
+    }
+}
+
+//从单个包文件读取资源,路径使用'/'分隔
+pub struct ArchiveSource {
+    loc:PathBuf,
+    file:Mutex<Option<File>>,
+    index:PackIndex
+}
+
+impl ArchiveSource {
+    pub fn new<P>(loc:P) -> io::Result<Self> where P:Into<PathBuf> {
+        let loc = loc.into();
+        let (file,index) = ArchiveSource::open(&loc)?;
+        Ok(ArchiveSource {loc,file:Mutex::new(Some(file)),index })
+    }
+
+    fn open(loc:&Path) -> io::Result<(File,PackIndex)> {
+        let mut file = File::open(loc)?;
+        let index = PackIndex::read(&mut file)?;
+        Ok((file,index))
+    }
+    pub fn contains(&self,path:&str) -> bool {
+        self.index.entries.contains_key(path)
+    }
+
+    pub fn paths(&self) -> Vec<&String> {
+        self.index.entries.keys().collect()
+    }
+}
+
+impl Source for ArchiveSource {
+    fn load(&self,path:&str) -> Result<Vec<u8>,AssetLoadError> {
+        let load_err = || AssetLoadError::new(AssetErrorKind::LoadFileError);
+        let entry = self.index.entries.get(path).ok_or_else(|| load_err().with_detail("not found in archive"))?;
+        let mut data = vec![0u8;entry.size as usize];
+        {
+            let mut may_file = self.file.lock().unwrap();
+            let file = may_file.as_mut().ok_or_else(|| load_err().with_detail("archive not opened"))?;
+            file.seek(SeekFrom::Start(self.index.data_start + entry.offset)).map_err(|e| load_err().with_cause(e))?;
+            file.read_exact(&mut data).map_err(|e| load_err().with_cause(e))?;
+        }
+        if entry.flags & FLAG_DEFLATE != 0 {
+            data = miniz_oxide::inflate::decompress_to_vec_with_limit(&data,entry.raw_size as usize).map_err(|e| AssetLoadError::format(format!("inflate failed: {:?}",e.status)))?;
+        }
+        if data.len() as u64 != entry.raw_size || crc32fast::hash(&data) != entry.crc {
+            return Err(AssetLoadError::format("archive entry checksum mismatch"));
+        }
+        Ok(data)
+    }
+
+    //打开失败时清空索引,之后的load返回archive not opened
+    fn set_loc(&mut self,path:&str) {
+        self.loc = PathBuf::from(path);
+        let (file,index) = match ArchiveSource::open(&self.loc) {
+            Ok((file,index)) => (Some(file),index),
+            Err(_) => (None,PackIndex::default())
+        };
+        self.file = Mutex::new(file);
+        self.index = index;
+    }
+
+    fn loc(&self) -> String {
+        String::from(self.loc.to_str().unwrap_or_default())
+    }
+}
+
+fn collect_files(root:&Path,dir:&Path,out:&mut Vec<(String,PathBuf)>) -> io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(root,&path,out)?;
+        } else if let Ok(rel) = path.strip_prefix(root) {
+            let name = rel.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+            out.push((name,path.clone()));
+        }
+    }
+    Ok(())
+}
+
+//将dir下所有文件打包到out,compress为true时仅在压缩后更小时才压缩
+pub fn build_archive<P:AsRef<Path>,O:AsRef<Path>>(dir:P,out:O,compress:bool) -> io::Result<usize> {
+    let root = dir.as_ref();
+    let mut files = Vec::new();
+    collect_files(root,root,&mut files)?;
+    files.sort_by(|a,b| a.0.cmp(&b.0));
+
+    let mut index:Vec<(String,PackEntry)> = Vec::with_capacity(files.len());
+    let mut data:Vec<u8> = Vec::new();
+    for (name,path) in files.iter() {
+        if name.len() > u16::MAX as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,"path too long"));
+        }
+        let raw = std::fs::read(path)?;
+        let crc = crc32fast::hash(&raw);
+        let packed = if compress { Some(miniz_oxide::deflate::compress_to_vec(&raw,6)) } else { None };
+        let (bytes,flags) = match packed {
+            Some(packed) if packed.len() < raw.len() => (packed,FLAG_DEFLATE),
+            _ => (raw.clone(),0)
+        };
+        index.push((name.clone(),PackEntry {
+            offset:data.len() as u64,
+            size:bytes.len() as u64,
+            raw_size:raw.len() as u64,
+            flags,
+            crc
+        }));
+        data.extend_from_slice(&bytes);
+    }
+
+    let mut file = io::BufWriter::new(File::create(out)?);
+    file.write_all(MAGIC)?;
+    file.write_u32::<LittleEndian>(VERSION)?;
+    file.write_u32::<LittleEndian>(index.len() as u32)?;
+    for (name,entry) in index.iter() {
+        file.write_u16::<LittleEndian>(name.len() as u16)?;
+        file.write_all(name.as_bytes())?;
+        file.write_u64::<LittleEndian>(entry.offset)?;
+        file.write_u64::<LittleEndian>(entry.size)?;
+        file.write_u64::<LittleEndian>(entry.raw_size)?;
+        file.write_u8(entry.flags)?;
+        file.write_u32::<LittleEndian>(entry.crc)?;
+    }
+    file.write_all(&data)?;
+    file.flush()?;
+    Ok(index.len())
+}
+
+#[test]
+fn test_archive_round_trip() {
+    let dir = std::env::temp_dir().join(format!("seija_pack_{}",std::process::id()));
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    std::fs::write(dir.join("a.txt"),b"hello").unwrap();
+    std::fs::write(dir.join("sub").join("b.json"),vec![b'x';1024]).unwrap();
+    let out = dir.with_extension("pak");
+    assert_eq!(build_archive(&dir,&out,true).unwrap(),2);
+
+    let source = ArchiveSource::new(&out).unwrap();
+    assert_eq!(source.load("a.txt").unwrap(),b"hello");
+    assert_eq!(source.load("sub/b.json").unwrap(),vec![b'x';1024]);
+    assert!(source.load("missing").is_err());
+    assert_eq!(ArchiveSource::new(dir.join("missing.pak")).err().map(|e| e.kind()),Some(io::ErrorKind::NotFound));
+    assert_eq!(ArchiveSource::new(dir.join("a.txt")).err().map(|e| e.kind()),Some(io::ErrorKind::InvalidData));
+
+
+    //索引中的数量,路径长度和数据范围超过文件大小
+    let header = |count:u32| [&MAGIC[..],&VERSION.to_le_bytes(),&count.to_le_bytes()].concat();
+    let corrupt = dir.join("corrupt.pak");
+    let check = |bytes:Vec<u8>| {
+        std::fs::write(&corrupt,bytes).unwrap();
+        assert_eq!(ArchiveSource::new(&corrupt).err().map(|e| e.kind()),Some(io::ErrorKind::InvalidData));
+    };
+    check(header(u32::MAX));
+    check([header(1),u16::MAX.to_le_bytes().to_vec(),vec![0u8;ENTRY_FIXED_SIZE as usize]].concat());
+    let entry = |size:u64| [&1u16.to_le_bytes()[..],b"a",&0u64.to_le_bytes(),&size.to_le_bytes(),&size.to_le_bytes(),&[0u8],&0u32.to_le_bytes()].concat();
+    check([header(1),entry(u64::MAX)].concat());
+    check([header(1),entry(4)].concat());
+
+    //解压后超过raw_size
+    let packed = miniz_oxide::deflate::compress_to_vec(&vec![b'x';4096],6);
+    let entry = [&1u16.to_le_bytes()[..],b"a",&0u64.to_le_bytes(),&(packed.len() as u64).to_le_bytes(),&16u64.to_le_bytes(),&[FLAG_DEFLATE],&0u32.to_le_bytes()].concat();
+    std::fs::write(&corrupt,[header(1),entry,packed].concat()).unwrap();
+    assert!(ArchiveSource::new(&corrupt).unwrap().load("a").is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+    std::fs::remove_file(&out).unwrap();
+}
@@ -0,0 +1,191 @@
+use crate::assets::{IAssetLoaderInfo,LoaderEnv,AssetLoadError,AssetErrorKind,StorageCenter,Handle,TextuteLoaderInfo};
+use crate::common::rect::{Rect};
+use crate::render::components::{SpriteSheet,Sprite,TextureCoordinate};
+use crate::render::types::{Texture,Backend};
+use rendy::texture::{TextureBuilder,MipLevels};
+use rendy::factory::{Factory};
+use rendy::command::{QueueId};
+use rendy::hal::format::{Format};
+use rendy::hal::image::{Kind,ViewKind,SamplerDesc,Filter,WrapMode};
+use fnv::FnvHashMap;
+use specs::{World};
+use std::num::NonZeroU8;
+
+//运行时打包的图集不来自任何Source
+const ATLAS_SOURCE:&str = "atlas";
+
+struct AtlasImage {
+    name:String,
+    width:u32,
+    height:u32,
+    pixels:Vec<u8>
+}
+
+//把零散图片在CPU上打包成一张或多张图集页,每页最终上传为一张Texture
+pub struct AtlasBuilder {
+    page_width:u32,
+    page_height:u32,
+    padding:u32,
+    images:Vec<AtlasImage>
+}
+
+pub struct AtlasPage {
+    pub width:u32,
+    pub height:u32,
+    //RGBA8
+    pub pixels:Vec<u8>,
+    pub sprites:Vec<Sprite>
+}
+
+impl AtlasBuilder {
+    pub fn new(page_width:u32,page_height:u32) -> Self {
+        AtlasBuilder {page_width,page_height,padding:1,images:Vec::new() }
+    }
+
+    pub fn with_padding(mut self,padding:u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn add_rgba(&mut self,name:&str,width:u32,height:u32,pixels:Vec<u8>) -> Result<(),AssetLoadError> {
+        if pixels.len() != (width * height * 4) as usize {
+            return Err(AssetLoadError::format("rgba data size mismatch").with_path(name));
+        }
+        if width + self.padding * 2 > self.page_width || height + self.padding * 2 > self.page_height {
+            return Err(AssetLoadError::format(format!("{}x{} image does not fit in {}x{} page",width,height,self.page_width,self.page_height)).with_path(name));
+        }
+        self.images.push(AtlasImage {name:String::from(name),width,height,pixels });
+        Ok(())
+    }
+
+    pub fn add_encoded(&mut self,name:&str,bytes:&[u8]) -> Result<(),AssetLoadError> {
+        let img = image::load_from_memory(bytes)
+                        .map_err(|e| AssetLoadError::new(AssetErrorKind::LoadImageError).with_cause(e).with_path(name))?
+                        .to_rgba();
+        let (width,height) = img.dimensions();
+        self.add_rgba(name,width,height,img.into_raw())
+    }
+
+    pub fn add_from_source(&mut self,env:&LoaderEnv,source:&str,path:&str) -> Result<(),AssetLoadError> {
+        let bytes = env.load_by_source(source,path)?;
+        self.add_encoded(path,&bytes)
+    }
+
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+
+    //按高度从大到小逐行(shelf)摆放,放不下时开新页
+    pub fn pack(&self) -> Vec<AtlasPage> {
+        let mut order:Vec<usize> = (0..self.images.len()).collect();
+        order.sort_by(|a,b| self.images[*b].height.cmp(&self.images[*a].height).then(self.images[*b].width.cmp(&self.images[*a].width)));
+        let pad = self.padding;
+        let mut pages:Vec<Vec<(usize,u32,u32)>> = vec![Vec::new()];
+        let (mut x,mut y,mut shelf_h) = (pad,pad,0);
+        for idx in order {
+            let img = &self.images[idx];
+            if x + img.width + pad > self.page_width {
+                x = pad;
+                y += shelf_h + pad;
+                shelf_h = 0;
+            }
+            if y + img.height + pad > self.page_height {
+                pages.push(Vec::new());
+                x = pad;
+                y = pad;
+                shelf_h = 0;
+            }
+            pages.last_mut().unwrap().push((idx,x,y));
+            x += img.width + pad;
+            shelf_h = shelf_h.max(img.height);
+        }
+        pages.into_iter().filter(|p| !p.is_empty()).map(|placed| self.blit_page(&placed)).collect()
+    }
+
+    fn blit_page(&self,placed:&[(usize,u32,u32)]) -> AtlasPage {
+        let width = placed.iter().map(|(i,x,_)| x + self.images[*i].width + self.padding).max().unwrap_or(0);
+        let height = placed.iter().map(|(i,_,y)| y + self.images[*i].height + self.padding).max().unwrap_or(0);
+        let mut pixels = vec![0u8;(width * height * 4) as usize];
+        let mut sprites = Vec::with_capacity(placed.len());
+        for (idx,x,y) in placed.iter().cloned() {
+            let img = &self.images[idx];
+            let row_len = (img.width * 4) as usize;
+            for row in 0..img.height {
+                let src = (row * img.width * 4) as usize;
+                let dst = (((y + row) * width + x) * 4) as usize;
+                pixels[dst..dst + row_len].copy_from_slice(&img.pixels[src..src + row_len]);
+            }
+            let (w,h) = (img.width as i32,img.height as i32);
+            sprites.push(Sprite {
+                name:img.name.clone(),
+                rect:Rect {x:x as i32,y:y as i32,width:w,height:h},
+                coord:TextureCoordinate {
+                    left: x as f32 / width as f32,  right:(x + img.width) as f32 / width as f32,
+                    top: y as f32 / height as f32,  bottom:(y + img.height) as f32 / height as f32
+                },
+                rotated:false,
+                source_size:(img.width,img.height),
+                trim_offset:(0,0),
+                pivot:(0.5f32,0.5f32),
+                duration:None
+            });
+        }
+        AtlasPage {width,height,pixels,sprites }
+    }
+
+    //打包并上传,每页的texture和sheet分别以"{name}#{i}.png"和"{name}#{i}"注册到StorageCenter的"atlas"下
+    pub fn build<B:Backend>(&self,name:&str,world:&World) -> Result<Vec<Handle<SpriteSheet>>,AssetLoadError> {
+        let center = StorageCenter::clone(&world.fetch::<StorageCenter>());
+        let qid = world.try_fetch::<QueueId>().map(|qid| *qid).ok_or_else(|| AssetLoadError::new(AssetErrorKind::NoFactory))?;
+        let mut sheets = Vec::new();
+        for (i,page) in self.pack().into_iter().enumerate() {
+            let sheet = {
+                let mut factory = world.try_fetch_mut::<Factory<B>>().ok_or_else(|| AssetLoadError::new(AssetErrorKind::NoFactory))?;
+                page.into_sheet(&format!("{}#{}.png",name,i),&mut factory,qid,&center,world)?
+            };
+            sheets.push(center.insert_asset(sheet,ATLAS_SOURCE,&format!("{}#{}",name,i),world));
+        }
+        Ok(sheets)
+    }
+}
+
+impl AtlasPage {
+    pub fn texture_builder(&self) -> TextureBuilder<'static> {
+        TextureBuilder::new()
+            .with_raw_data(self.pixels.clone(),Format::Rgba8Srgb)
+            .with_data_width(self.width)
+            .with_data_height(self.height)
+            .with_mip_levels(MipLevels::Levels(NonZeroU8::new(1).unwrap()))
+            .with_kind(Kind::D2(self.width,self.height,1,1))
+            .with_view_kind(ViewKind::D2)
+            .with_sampler_info(SamplerDesc::new(Filter::Linear,WrapMode::Clamp))
+    }
+
+    pub fn into_sheet<B:Backend>(self,texture_path:&str,factory:&mut Factory<B>,qid:QueueId,center:&StorageCenter,world:&World) -> Result<SpriteSheet,AssetLoadError> {
+        let texture = TextuteLoaderInfo::load(self.texture_builder(),factory,qid,center,world)?;
+        let texture = center.insert_asset::<Texture>(texture,ATLAS_SOURCE,texture_path,world);
+        let name_dic:FnvHashMap<String,u32> = self.sprites.iter().enumerate().map(|(i,s)| (s.name.clone(),i as u32)).collect();
+        Ok(SpriteSheet::new(self.width,self.height,texture,self.sprites,name_dic,FnvHashMap::default(),Vec::new()))
+    }
+}
+
+#[test]
+fn test_atlas_pack() {
+    let mut builder = AtlasBuilder::new(16,16).with_padding(1);
+    builder.add_rgba("a",6,6,vec![1u8;6 * 6 * 4]).unwrap();
+    builder.add_rgba("b",6,4,vec![2u8;6 * 4 * 4]).unwrap();
+    builder.add_rgba("c",10,10,vec![3u8;10 * 10 * 4]).unwrap();
+    assert!(builder.add_rgba("big",20,2,vec![0u8;20 * 2 * 4]).is_err());
+
+    let pages = builder.pack();
+    assert_eq!(pages.len(),2);
+    let sprite_b = pages[1].sprites.iter().find(|s| s.name == "b").unwrap();
+    let page = &pages[1];
+    let (x,y) = (sprite_b.rect.x as u32,sprite_b.rect.y as u32);
+    assert_eq!(page.pixels[((y * page.width + x) * 4) as usize],2);
+    assert_eq!(sprite_b.coord.left,x as f32 / page.width as f32);
+}
@@ -1,39 +1,170 @@
-use crate::assets::{Asset,Handle,AssetStorage};
-use std::any::{TypeId};
+use crate::assets::{Asset,Handle,AssetStorage,IAssetLoaderInfo};
+use std::any::{TypeId,Any};
 use std::sync::{Arc,RwLock};
-use std::collections::{HashMap};
+use std::collections::{HashMap,HashSet};
 use specs::{World};
 
 
 pub type AssetID = (TypeId,u32);
+
+//同一路径可以从不同source加载,按(source,path)区分
+type AssetKey = (String,String);
+
+//dependencies: 资源 -> 它引用的资源, dependents: 资源 -> 引用它的资源
+#[derive(Default)]
+struct DepGraph {
+    dependencies:HashMap<AssetID,HashSet<AssetID>>,
+    dependents:HashMap<AssetID,HashSet<AssetID>>
+}
+
+impl DepGraph {
+    fn set(&mut self,asset_id:AssetID,deps:Vec<AssetID>) {
+        self.clear_dependencies(&asset_id);
+        if deps.is_empty() {
+            return;
+        }
+        for dep in deps.iter() {
+            self.dependents.entry(*dep).or_default().insert(asset_id);
+        }
+        self.dependencies.insert(asset_id,deps.into_iter().collect());
+    }
+
+    fn clear_dependencies(&mut self,asset_id:&AssetID) {
+        if let Some(old_deps) = self.dependencies.remove(asset_id) {
+            for dep in old_deps.iter() {
+                if let Some(set) = self.dependents.get_mut(dep) {
+                    set.remove(asset_id);
+                    if set.is_empty() {
+                        self.dependents.remove(dep);
+                    }
+                }
+            }
+        }
+    }
+}
+
+//加载资源时使用的LoaderInfo,热更新时原样重新执行
+struct LoadRecord {
+    path:String,
+    source:String,
+    info:Arc<dyn Any + Send + Sync>
+}
+
+#[derive(Clone)]
 pub struct StorageCenter {
-    assets:Arc<RwLock<HashMap<String,AssetID>>>,
+    assets:Arc<RwLock<HashMap<AssetKey,AssetID>>>,
+    deps:Arc<RwLock<DepGraph>>,
+    records:Arc<RwLock<HashMap<AssetID,LoadRecord>>>
 }
 
 impl Default for StorageCenter {
     fn default() -> Self {
-        Self {assets:Arc::new(RwLock::new(HashMap::new())) }
+        Self {
+            assets:Arc::new(RwLock::new(HashMap::new())),
+            deps:Arc::new(RwLock::new(DepGraph::default())),
+            records:Arc::new(RwLock::new(HashMap::new()))
+        }
     }
 }
 
 impl StorageCenter {
-    pub fn contains(&self,path:&String) -> bool {
+    fn key(source:&str,path:&str) -> AssetKey {
+        (String::from(source),String::from(path))
+    }
+
+    pub fn contains(&self,source:&str,path:&str) -> bool {
         let read = self.assets.read().unwrap();
-        read.contains_key(path)
+        read.contains_key(&StorageCenter::key(source,path))
     }
 
-    pub fn get_asset_id(&self,path:&String) -> Option<AssetID> {
+    pub fn get_asset_id(&self,source:&str,path:&str) -> Option<AssetID> {
         let read = self.assets.read().unwrap();
-        read.get(path).map(|id| id.clone())
+        read.get(&StorageCenter::key(source,path)).copied()
+    }
+
+    pub fn get_handle<A:Asset>(&self,source:&str,path:&str,world:&World) -> Option<Handle<A>> {
+        let (_,asset_id) = self.get_asset_id(source,path)?;
+        world.fetch::<AssetStorage<A>>().get_handle(asset_id)
+    }
+
+    pub fn paths(&self) -> Vec<(String,String,AssetID)> {
+        let read = self.assets.read().unwrap();
+        read.iter().map(|((source,path),id)| (source.clone(),path.clone(),*id)).collect()
+    }
+
+    pub fn remove_asset_id(&self,asset_id:&AssetID) {
+        let mut write = self.assets.write().unwrap();
+        write.retain(|_,id| id != asset_id);
+        let mut deps = self.deps.write().unwrap();
+        deps.clear_dependencies(asset_id);
+        deps.dependents.remove(asset_id);
+        self.records.write().unwrap().remove(asset_id);
+    }
+
+    pub fn set_load_info<AL>(&self,asset_id:AssetID,info:AL) where AL:IAssetLoaderInfo + Send + Sync + 'static {
+        let record = LoadRecord {path:info.path().clone(),source:String::from(info.source()),info:Arc::new(info) };
+        self.records.write().unwrap().insert(asset_id,record);
+    }
+
+    pub fn load_info<AL>(&self,asset_id:&AssetID) -> Option<AL> where AL:IAssetLoaderInfo + Clone + 'static {
+        let records = self.records.read().unwrap();
+        records.get(asset_id).and_then(|r| r.info.downcast_ref::<AL>()).cloned()
     }
 
-    pub fn insert_asset<A:Asset>(&self,asset:A,path:&String,world:&World) -> Handle<A> {
-       if let Some((_,asset_id)) = self.get_asset_id(path) {
-           return  Handle::new(asset_id);
+    //从source加载的资源路径
+    pub fn source_paths(&self,source:&str) -> Vec<(String,AssetID)> {
+        let records = self.records.read().unwrap();
+        records.iter().filter(|(_,r)| r.source == source).map(|(id,r)| (r.path.clone(),*id)).collect()
+    }
+
+    pub fn set_dependencies(&self,asset_id:AssetID,deps:Vec<AssetID>) {
+        self.deps.write().unwrap().set(asset_id,deps);
+    }
+
+    pub fn dependencies_of(&self,asset_id:&AssetID) -> Vec<AssetID> {
+        let deps = self.deps.read().unwrap();
+        deps.dependencies.get(asset_id).map(|set| set.iter().cloned().collect()).unwrap_or_default()
+    }
+
+    pub fn dependents_of(&self,asset_id:&AssetID) -> Vec<AssetID> {
+        let deps = self.deps.read().unwrap();
+        deps.dependents.get(asset_id).map(|set| set.iter().cloned().collect()).unwrap_or_default()
+    }
+
+    pub fn insert_asset<A:Asset>(&self,asset:A,source:&str,path:&str,world:&World) -> Handle<A> {
+       if let Some(handle) = self.get_handle::<A>(source,path,world) {
+           return handle;
        }
+       let deps = asset.dependencies();
        let hid = world.fetch_mut::<AssetStorage<A>>().insert(asset);
+       self.set_dependencies((TypeId::of::<A>(),hid.id()),deps);
        let mut write = self.assets.write().unwrap();
-       write.insert(path.clone(), (TypeId::of::<A>(),hid.id()));
+       write.insert(StorageCenter::key(source,path), (TypeId::of::<A>(),hid.id()));
        hid
     }
-}
\ No newline at end of file
+
+    pub fn insert_asset_with_handle<A:Asset>(&self,asset:A,source:&str,path:&str,handle:&Handle<A>,world:&World) {
+        self.set_dependencies((TypeId::of::<A>(),handle.id()),asset.dependencies());
+        world.fetch_mut::<AssetStorage<A>>().insert_with_handle(handle,asset);
+        let mut write = self.assets.write().unwrap();
+        write.entry(StorageCenter::key(source,path)).or_insert((TypeId::of::<A>(),handle.id()));
+    }
+}
+
+#[test]
+fn test_dependency_graph() {
+    let center = StorageCenter::default();
+    let tex = (TypeId::of::<u8>(),0);
+    let sheet_a = (TypeId::of::<u16>(),0);
+    let sheet_b = (TypeId::of::<u16>(),1);
+    center.set_dependencies(sheet_a,vec![tex]);
+    center.set_dependencies(sheet_b,vec![tex]);
+    assert_eq!(center.dependencies_of(&sheet_a),vec![tex]);
+    assert_eq!(center.dependents_of(&tex).len(),2);
+
+    center.remove_asset_id(&sheet_a);
+    assert!(center.dependencies_of(&sheet_a).is_empty());
+    assert_eq!(center.dependents_of(&tex),vec![sheet_b]);
+    center.set_dependencies(sheet_b,vec![]);
+    assert!(center.dependents_of(&tex).is_empty());
+}
@@ -1,8 +1,9 @@
 use std::path::{PathBuf};
 use std::fs::{File};
-use crate::assets::{AssetLoadError};
+use crate::assets::{AssetLoadError,AssetErrorKind};
 use std::collections::HashMap;
-use std::sync::{RwLock};
+use std::sync::{Arc,RwLock};
+use std::borrow::Cow;
 pub trait Source: Send + Sync + 'static {
     fn load(&self, path: &str) -> Result<Vec<u8>, AssetLoadError>;
     fn set_loc(&mut self,path:&str);
@@ -31,8 +32,9 @@ impl Source for LocalFS {
         use std::io::Read;
         let path = self.path(path);
         let mut v = Vec::new();
-        let mut file = File::open(&path).map_err(|_| AssetLoadError::LoadFileError)?;
-        file.read_to_end(&mut v).map_err(|_| AssetLoadError::LoadFileError)?;
+        let load_err = |e| AssetLoadError::new(AssetErrorKind::LoadFileError).with_cause(e);
+        let mut file = File::open(&path).map_err(load_err)?;
+        file.read_to_end(&mut v).map_err(load_err)?;
         Ok(v)
     }
 
@@ -47,35 +49,89 @@ impl Source for LocalFS {
 }
 
 
+//内存中的path->bytes表,clone后共享同一份数据,注册到LoaderEnv后仍可继续插入
+#[derive(Clone,Default)]
+pub struct MemorySource {
+    files:Arc<RwLock<HashMap<String,Cow<'static,[u8]>>>>
+}
+
+impl MemorySource {
+    pub fn new() -> Self {
+        MemorySource::default()
+    }
+
+    //用于include_bytes!生成的静态表
+    pub fn from_static(files:&[(&str,&'static [u8])]) -> Self {
+        let source = MemorySource::default();
+        for (path,bytes) in files {
+            source.insert_static(path,bytes);
+        }
+        source
+    }
+
+    pub fn insert(&self,path:&str,bytes:Vec<u8>) {
+        self.files.write().unwrap().insert(String::from(path),Cow::Owned(bytes));
+    }
+
+    pub fn insert_static(&self,path:&str,bytes:&'static [u8]) {
+        self.files.write().unwrap().insert(String::from(path),Cow::Borrowed(bytes));
+    }
+
+    pub fn remove(&self,path:&str) -> bool {
+        self.files.write().unwrap().remove(path).is_some()
+    }
+
+    pub fn contains(&self,path:&str) -> bool {
+        self.files.read().unwrap().contains_key(path)
+    }
+}
+
+impl Source for MemorySource {
+    fn load(&self, path: &str) -> Result<Vec<u8>, AssetLoadError> {
+        let files = self.files.read().unwrap();
+        files.get(path).map(|bytes| bytes.to_vec()).ok_or_else(|| AssetLoadError::new(AssetErrorKind::LoadFileError).with_detail("not found in memory source"))
+    }
+
+    fn set_loc(&mut self,_:&str) {}
+
+    fn loc(&self) -> String {
+        String::default()
+    }
+}
 
 pub struct LoaderEnv {
-    sources:HashMap<String,RwLock<Box<dyn Source>>>
+    sources:RwLock<HashMap<String,Box<dyn Source>>>
 }
 
 impl Default for LoaderEnv {
     fn default() -> LoaderEnv {
-        let mut env = LoaderEnv {
-            sources: HashMap::default()
+        let env = LoaderEnv {
+            sources: RwLock::new(HashMap::default())
         };
-        env.sources.insert(String::from("fs"),RwLock::new(Box::new(LocalFS::new(""))));
+        env.add_source("fs",Box::new(LocalFS::new("")));
         env
     }
 }
 
 impl LoaderEnv {
+    pub fn add_source(&self,name:&str,source:Box<dyn Source>) {
+        self.sources.write().unwrap().insert(String::from(name),source);
+    }
+
+    pub fn has_source(&self,source_type:&str) -> bool {
+        self.sources.read().unwrap().contains_key(source_type)
+    }
+
     pub fn set_source_root(&self,source_type:&str,path:&str) -> bool {
-        if let Some(box_source) = self.sources.get(&String::from(source_type)) {
-            box_source.write().unwrap().set_loc(path);
+        if let Some(box_source) = self.sources.write().unwrap().get_mut(source_type) {
+            box_source.set_loc(path);
             return true;
         }
         false
     }
 
     pub fn get_source_root(&self,source_type:&str) -> Option<String> {
-        if let Some(box_source) = self.sources.get(&String::from(source_type)) {
-            return Some(box_source.read().unwrap().loc());
-        }
-        None
+        self.sources.read().unwrap().get(source_type).map(|s| s.loc())
     }
 
     
@@ -88,8 +144,10 @@ impl LoaderEnv {
     }
 
     pub fn load_by_source(&self,source_type:&str,path:&str) -> Result<Vec<u8>, AssetLoadError> {
-        let box_source = self.sources.get(&String::from(source_type)).ok_or(AssetLoadError::LoadFileError)?;
-        box_source.read().unwrap().load(path)
+        let sources = self.sources.read().unwrap();
+        let box_source = sources.get(source_type)
+                                .ok_or_else(|| AssetLoadError::new(AssetErrorKind::NotFoundSource).with_path(path).with_source(source_type))?;
+        box_source.load(path).map_err(|e| e.with_path(path).with_source(source_type))
     }
 
     pub fn load_fs_source(&self,path:&str) -> Result<Vec<u8>, AssetLoadError> {
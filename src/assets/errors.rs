@@ -1,9 +1,123 @@
-#[derive(Debug)]
-pub enum AssetLoadError {
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum AssetErrorKind {
     LoadFileError,
+    NotFoundSource,
     FormatError,
     LoadImageError,
     UploadImageError,
     NotFoundLoader,
-    FindDepAssetError
-}
\ No newline at end of file
+    FindDepAssetError,
+    NoFactory
+}
+
+impl fmt::Display for AssetErrorKind {
+    fn fmt(&self,f:&mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            AssetErrorKind::LoadFileError => "load file error",
+            AssetErrorKind::NotFoundSource => "source not found",
+            AssetErrorKind::FormatError => "format error",
+            AssetErrorKind::LoadImageError => "load image error",
+            AssetErrorKind::UploadImageError => "upload image error",
+            AssetErrorKind::NotFoundLoader => "loader not found",
+            AssetErrorKind::FindDepAssetError => "dependent asset not found",
+            AssetErrorKind::NoFactory => "render factory not available",
+        };
+        f.write_str(s)
+    }
+}
+
+//cause用Arc包装,LoadState需要Clone
+#[derive(Debug,Clone)]
+pub struct AssetLoadError {
+    kind:AssetErrorKind,
+    path:Option<String>,
+    source_name:Option<String>,
+    detail:Option<String>,
+    cause:Option<Arc<dyn Error + Send + Sync>>
+}
+
+impl AssetLoadError {
+    pub fn new(kind:AssetErrorKind) -> Self {
+        AssetLoadError {kind,path:None,source_name:None,detail:None,cause:None }
+    }
+
+    pub fn format(detail:impl Into<String>) -> Self {
+        AssetLoadError::new(AssetErrorKind::FormatError).with_detail(detail)
+    }
+
+    //已有path/source时不覆盖,保留最内层出错的资源
+    pub fn with_path(mut self,path:&str) -> Self {
+        if self.path.is_none() {
+            self.path = Some(String::from(path));
+        }
+        self
+    }
+
+    pub fn with_source(mut self,source_name:&str) -> Self {
+        if self.source_name.is_none() {
+            self.source_name = Some(String::from(source_name));
+        }
+        self
+    }
+
+    pub fn with_detail(mut self,detail:impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn with_cause<E>(mut self,cause:E) -> Self where E:Error + Send + Sync + 'static {
+        self.cause = Some(Arc::new(cause));
+        self
+    }
+
+    pub fn kind(&self) -> AssetErrorKind {
+        self.kind
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    pub fn source_name(&self) -> Option<&str> {
+        self.source_name.as_deref()
+    }
+
+    pub fn detail(&self) -> Option<&str> {
+        self.detail.as_deref()
+    }
+}
+
+impl From<AssetErrorKind> for AssetLoadError {
+    fn from(kind:AssetErrorKind) -> Self {
+        AssetLoadError::new(kind)
+    }
+}
+
+impl fmt::Display for AssetLoadError {
+    fn fmt(&self,f:&mut fmt::Formatter) -> fmt::Result {
+        write!(f,"{}",self.kind)?;
+        if let Some(path) = self.path.as_ref() {
+            write!(f," '{}'",path)?;
+        }
+        if let Some(source_name) = self.source_name.as_ref() {
+            write!(f," in source '{}'",source_name)?;
+        }
+        if let Some(detail) = self.detail.as_ref() {
+            write!(f,": {}",detail)?;
+        }
+        if let Some(cause) = self.cause.as_ref() {
+            write!(f,": {}",cause)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for AssetLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.cause.as_ref().map(|e| e.as_ref() as &(dyn Error + 'static))
+    }
+}
@@ -1,28 +1,42 @@
 use rendy::texture::{image::{ImageTextureConfig,load_from_image},TextureBuilder};
 use crate::render::types::{Texture,Backend};
-use crate::assets::{IAssetLoaderInfo,LoaderEnv,AssetLoadError,StorageCenter,Handle,Asset};
+use crate::assets::{IAssetLoaderInfo,LoaderEnv,AssetLoadError,AssetErrorKind,StorageCenter,Asset,AssetID};
+use std::any::{TypeId};
 use rendy::factory::{Factory,ImageState};
 use rendy::command::{QueueId};
 use crate::common::rect::{Rect};
 use std::path::{Path};
-use crate::render::components::{SpriteSheet,Sprite,TextureCoordinate};
+use crate::render::components::{SpriteSheet,Sprite,TextureCoordinate,FrameTag};
+use crate::assets::sheet_format::{SheetFormat,parse_atlas};
 use fnv::FnvHashMap;
 use crate::render::{FontAsset};
+use crate::event::{InputBindings};
 use specs::{World};
 use glyph_brush::ab_glyph::{FontArc};
 
+const DEFAULT_SOURCE:&str = "fs";
+
+type Border = (f32,f32,f32,f32);
+
+#[derive(Clone)]
 pub struct TextuteLoaderInfo {
     path:String,
+    source:String,
     config:ImageTextureConfig
 }
 
 impl TextuteLoaderInfo {
     pub fn new(path:&str,config:ImageTextureConfig) -> TextuteLoaderInfo {
-        TextuteLoaderInfo {path : String::from(path),config }
+        TextuteLoaderInfo {path : String::from(path),source:String::from(DEFAULT_SOURCE),config }
     }
 
     pub fn new_only_path(path:&str) -> TextuteLoaderInfo {
-        TextuteLoaderInfo {path :String::from(path),config :ImageTextureConfig::default() }
+        TextuteLoaderInfo {path :String::from(path),source:String::from(DEFAULT_SOURCE),config :ImageTextureConfig::default() }
+    }
+
+    pub fn with_source(mut self,source:&str) -> Self {
+        self.source = String::from(source);
+        self
     }
 }
 
@@ -38,10 +52,14 @@ impl IAssetLoaderInfo for TextuteLoaderInfo {
         &self.path
     }
 
+    fn source(&self) -> &str {
+        &self.source
+    }
+
     fn load_data(&self,_:&StorageCenter,source:&LoaderEnv) -> Result<Result<Self::CData,Self::Asset>,AssetLoadError> {
-        let bytes = source.load_fs_source(self.path.as_str())?;
+        let bytes = source.load_by_source(&self.source,self.path.as_str())?;
         let builder:TextureBuilder = load_from_image(std::io::Cursor::new(bytes), self.config.clone())
-                                     .map_err(|_| AssetLoadError::LoadImageError)?;
+                                     .map_err(|e| AssetLoadError::new(AssetErrorKind::LoadImageError).with_detail(format!("{:?}",e)).with_path(&self.path))?;
         Ok(Ok(builder))
     }
 
@@ -50,52 +68,67 @@ impl IAssetLoaderInfo for TextuteLoaderInfo {
             stage: rendy::hal::pso::PipelineStage::FRAGMENT_SHADER,
             access: rendy::hal::image::Access::SHADER_READ,
             layout: rendy::hal::image::Layout::ShaderReadOnlyOptimal
-        }, factory).map(B::wrap_texture).map_err(|_|AssetLoadError::UploadImageError)
+        }, factory).map(B::wrap_texture).map_err(|e| AssetLoadError::new(AssetErrorKind::UploadImageError).with_detail(format!("{:?}",e)))
     }
 }
 
+#[derive(Clone)]
 pub struct SpriteSheetLoaderInfo {
     path:String,
+    source:String,
     config:ImageTextureConfig
 }
 
 impl SpriteSheetLoaderInfo {
-    pub fn new(path: &str, config: ImageTextureConfig) -> Self { Self { path:String::from(path), config } }
-    pub fn new_only_path(path: &str) -> Self { Self { path:String::from(path), config:ImageTextureConfig::default() } }
+    pub fn new(path: &str, config: ImageTextureConfig) -> Self { Self { path:String::from(path),source:String::from(DEFAULT_SOURCE), config } }
+    pub fn new_only_path(path: &str) -> Self { Self { path:String::from(path),source:String::from(DEFAULT_SOURCE), config:ImageTextureConfig::default() } }
+    //贴图与json从同一个source读取
+    pub fn with_source(mut self,source:&str) -> Self {
+        self.source = String::from(source);
+        self
+    }
 }
 
 
 impl Asset for SpriteSheet {
     type LoaderInfo = SpriteSheetLoaderInfo;
+    fn dependencies(&self) -> Vec<AssetID> {
+        vec![(TypeId::of::<Texture>(),self.texture.id())]
+    }
 }
 pub struct SpriteSheetCData {
     tex_path:String,
+    tex_info:TextuteLoaderInfo,
     pub width: u32,
     pub height: u32,
-    pub texture: TextureBuilder<'static>,
+    pub texture: Option<TextureBuilder<'static>>,
     sprites: Vec<Sprite>,
     name_dic: FnvHashMap<String, u32>,
     borders: FnvHashMap<String, Vec<(f32, f32, f32, f32)>>,
+    tags: Vec<FrameTag>,
 }
 
 impl SpriteSheetCData {
-    pub fn get_meta_data(val:&serde_json::Value) -> Option<(String,u32,u32)> {
-        let meta_object = val.as_object().and_then(|o| o.get("meta"))?;
-        let texture_name = meta_object.get("texture").and_then(|s| s.as_str())?;
-        let w = meta_object.get("width").and_then(|s| s.as_i64())?;
-        let h = meta_object.get("height").and_then(|s| s.as_i64())?;
-        Some((String::from(texture_name),w as u32,h as u32))
+    pub fn get_meta_data(val:&serde_json::Value) -> Result<(String,u32,u32),AssetLoadError> {
+        let meta_object = val.as_object().and_then(|o| o.get("meta")).ok_or_else(|| AssetLoadError::format("missing 'meta'"))?;
+        let texture_name = meta_object.get("texture").and_then(|s| s.as_str()).ok_or_else(|| AssetLoadError::format("'meta.texture' must be a string"))?;
+        let w = meta_object.get("width").and_then(|s| s.as_i64()).ok_or_else(|| AssetLoadError::format("'meta.width' must be an integer"))?;
+        let h = meta_object.get("height").and_then(|s| s.as_i64()).ok_or_else(|| AssetLoadError::format("'meta.height' must be an integer"))?;
+        Ok((String::from(texture_name),w as u32,h as u32))
     }
 
-    pub fn parse_sprites(val:&Vec<serde_json::Value>,s_w:f32,s_h:f32) -> Option<Vec<Sprite>> {
+    pub fn parse_sprites(val:&[serde_json::Value],s_w:f32,s_h:f32) -> Result<Vec<Sprite>,AssetLoadError> {
         let mut ret_list = Vec::new();
-        for item in val {
+        for (index,item) in val.iter().enumerate() {
             if let Some(sprite_map) = item.as_object() {
-                let x = sprite_map.get("x").and_then(|x| x.as_i64())?;
-                let y = sprite_map.get("y").and_then(|y| y.as_i64())?;
-                let width = sprite_map.get("width").and_then(|w| w.as_i64())?;
-                let height = sprite_map.get("height").and_then(|h| h.as_i64())?;
-                let name = sprite_map.get("name").and_then(|h| h.as_str()).map(String::from)?;
+                let get_int = |key:&str| sprite_map.get(key).and_then(|v| v.as_i64())
+                                                .ok_or_else(|| AssetLoadError::format(format!("'sprites[{}].{}' must be an integer",index,key)));
+                let x = get_int("x")?;
+                let y = get_int("y")?;
+                let width = get_int("width")?;
+                let height = get_int("height")?;
+                let name = sprite_map.get("name").and_then(|h| h.as_str()).map(String::from)
+                                     .ok_or_else(|| AssetLoadError::format(format!("'sprites[{}].name' must be a string",index)))?;
                 ret_list.push(Sprite {
                     name, 
                     rect : Rect {
@@ -104,38 +137,40 @@ impl SpriteSheetCData {
                     coord:TextureCoordinate {
                         left: x as f32 / s_w,  right:(x + width) as f32 / s_w,
                         top : y as f32 / s_h,  bottom: (y + height) as f32 / s_h
-                    }
+                    },
+                    rotated:false,
+                    source_size:(width as u32,height as u32),
+                    trim_offset:(0,0),
+                    pivot:(0.5f32,0.5f32),
+                    duration:None
                 });
             }
         }
-        Some(ret_list)
+        Ok(ret_list)
     }
 
-    pub fn parse_border(val:&Vec<serde_json::Value>) -> FnvHashMap<String,Vec<(f32,f32,f32,f32)>> {
+    pub fn parse_border(val:&[serde_json::Value]) -> Result<FnvHashMap<String,Vec<Border>>,AssetLoadError> {
         let mut ret_map = FnvHashMap::default();
-        for item in val {
-            if let Some(_border_map) = item.as_object() {
-               if let Some((sprite_name,border_list)) = SpriteSheetCData::parse_border_item(&item) {
-                   ret_map.insert(sprite_name.clone(),border_list);
-               }
-            }
+        for (index,item) in val.iter().enumerate() {
+            let (sprite_name,border_list) = SpriteSheetCData::parse_border_item(index,item)?;
+            ret_map.insert(sprite_name,border_list);
         }
-        ret_map
-    }
-
-    fn parse_border_item(val:&serde_json::Value) -> Option<(String,Vec<(f32,f32,f32,f32)>)> {
-        let _target = val.get("target")?.as_str()?;
-        let border_arr = val.get("border")?.as_array()?;
-        let mut border_list:Vec<(f32,f32,f32,f32)> = Vec::new();
-        for border in border_arr.iter() {
-            let num_arr = border.as_array()?;
-            let _0 = num_arr[0].as_f64()? as f32;
-            let _1 = num_arr[1].as_f64()? as f32;
-            let _2 = num_arr[2].as_f64()? as f32;
-            let _3 = num_arr[3].as_f64()? as f32;
-            border_list.push((_0,_1,_2,_3));
+        Ok(ret_map)
+    }
+
+    fn parse_border_item(index:usize,val:&serde_json::Value) -> Result<(String,Vec<Border>),AssetLoadError> {
+        let target = val.get("target").and_then(|t| t.as_str())
+                        .ok_or_else(|| AssetLoadError::format(format!("'sliceBorder[{}].target' must be a string",index)))?;
+        let border_arr = val.get("border").and_then(|b| b.as_array())
+                            .ok_or_else(|| AssetLoadError::format(format!("'sliceBorder[{}].border' must be an array",index)))?;
+        let mut border_list:Vec<Border> = Vec::new();
+        for (j,border) in border_arr.iter().enumerate() {
+            let num_arr = border.as_array();
+            let get_num = |i:usize| num_arr.and_then(|arr| arr.get(i)).and_then(|n| n.as_f64()).map(|n| n as f32)
+                                            .ok_or_else(|| AssetLoadError::format(format!("'sliceBorder[{}].border[{}]' must be an array of 4 numbers",index,j)));
+            border_list.push((get_num(0)?,get_num(1)?,get_num(2)?,get_num(3)?));
         }
-        Some((String::from(_target),border_list))
+        Ok((String::from(target),border_list))
     }
 }
 
@@ -145,16 +180,31 @@ impl IAssetLoaderInfo for SpriteSheetLoaderInfo {
     fn path(&self) -> &String {
         &self.path
     }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
     fn load_data(&self, center:&StorageCenter, source:&LoaderEnv) -> Result<Result<Self::CData,Self::Asset>,AssetLoadError> {
-        let json_bytes = source.load_fs_source(self.path.as_str())?;
-        let sheet_json = serde_json::from_slice(&json_bytes).map_err(|_|AssetLoadError::FormatError)?;
-        let (mut texture_path,width,height) = SpriteSheetCData::get_meta_data(&sheet_json).ok_or(AssetLoadError::FormatError)?;
-        let sprite_vec = sheet_json.get("sprites").and_then(|s| s.as_array()).ok_or(AssetLoadError::FormatError)?;
-        let sprites = SpriteSheetCData::parse_sprites(sprite_vec,width as f32,height as f32).ok_or(AssetLoadError::FormatError)?;
-        let mut borders:FnvHashMap<String,Vec<(f32,f32,f32,f32)>> = FnvHashMap::default();
-        if let Some(border_arr) = sheet_json.get("sliceBorder").and_then(|s| s.as_array()) {
-           borders = SpriteSheetCData::parse_border(border_arr);
-        }
+        let json_bytes = source.load_by_source(&self.source,self.path.as_str())?;
+        let with_path = |e:AssetLoadError| e.with_path(&self.path).with_source(&self.source);
+        let sheet_json:serde_json::Value = serde_json::from_slice(&json_bytes).map_err(|e| with_path(AssetLoadError::new(AssetErrorKind::FormatError).with_cause(e)))?;
+        let format = SheetFormat::detect(&sheet_json).ok_or_else(|| with_path(AssetLoadError::format("unknown sprite sheet format")))?;
+        let (mut texture_path,width,height,sprites,borders,tags) = match format {
+            SheetFormat::Seija => {
+                let (texture_path,width,height) = SpriteSheetCData::get_meta_data(&sheet_json).map_err(with_path)?;
+                let sprite_vec = sheet_json.get("sprites").and_then(|s| s.as_array()).ok_or_else(|| with_path(AssetLoadError::format("'sprites' must be an array")))?;
+                let sprites = SpriteSheetCData::parse_sprites(sprite_vec,width as f32,height as f32).map_err(with_path)?;
+                let mut borders:FnvHashMap<String,Vec<(f32,f32,f32,f32)>> = FnvHashMap::default();
+                if let Some(border_arr) = sheet_json.get("sliceBorder").and_then(|s| s.as_array()) {
+                    borders = SpriteSheetCData::parse_border(border_arr).map_err(with_path)?;
+                }
+                (texture_path,width,height,sprites,borders,Vec::new())
+            },
+            SheetFormat::TexturePacker | SheetFormat::Aseprite => {
+                let atlas = parse_atlas(&sheet_json).map_err(with_path)?;
+                (atlas.texture,atlas.width,atlas.height,atlas.sprites,FnvHashMap::default(),atlas.tags)
+            }
+        };
         let mut name_dic = FnvHashMap::default();
         let mut i = 0;
         for sprite in sprites.iter() {
@@ -165,39 +215,57 @@ impl IAssetLoaderInfo for SpriteSheetLoaderInfo {
             let jpath = parent.join(&texture_path);
             texture_path = String::from(jpath.to_owned().to_str().unwrap());
         }
-        if let Some((_,asset_id)) = center.get_asset_id(&texture_path) {
-            let sprite_sheet = SpriteSheet::new(width,height,Handle::new(asset_id),sprites,name_dic,borders);
-            return  Ok(Err(sprite_sheet));
+        let mut tex_builder = None;
+        let tex_load_info = TextuteLoaderInfo::new(texture_path.as_str(), self.config.clone()).with_source(&self.source);
+        if !center.contains(&self.source,&texture_path) {
+            //只有找不到贴图时才算依赖缺失,贴图格式等错误原样返回
+            tex_builder = tex_load_info.load_data(center, source).map_err(|e| match e.kind() {
+                AssetErrorKind::LoadFileError | AssetErrorKind::NotFoundSource => AssetLoadError::new(AssetErrorKind::FindDepAssetError).with_path(&texture_path).with_cause(e),
+                _ => e
+            })?.ok();
         }
-
-        let tex_load_info = TextuteLoaderInfo::new(texture_path.as_str(), self.config.clone());
-        let tex_builder = tex_load_info.load_data(center, source)?.unwrap();
         Ok(Ok(SpriteSheetCData {
             tex_path:texture_path,
+            tex_info:tex_load_info,
             width,
             height,
             texture:tex_builder,
             sprites,
             name_dic,
-            borders
+            borders,
+            tags
         }))
     }
 
 
     fn load<B:Backend>(cdata:Self::CData, factory:&mut Factory<B>, qid:QueueId,center:&StorageCenter,world:&World) -> Result<Self::Asset,AssetLoadError> {
-        let texture = TextuteLoaderInfo::load(cdata.texture, factory, qid,center,world)?;
-        let hid = center.insert_asset::<Texture>(texture,&cdata.tex_path,world);
-        let sheet = SpriteSheet::new(cdata.width,cdata.height,hid,cdata.sprites,cdata.name_dic,cdata.borders);
+        let hid = match cdata.texture {
+            Some(builder) => {
+                let texture = TextuteLoaderInfo::load(builder, factory, qid,center,world)?;
+                let hid = center.insert_asset::<Texture>(texture,&cdata.tex_info.source,&cdata.tex_path,world);
+                center.set_load_info((TypeId::of::<Texture>(),hid.id()),cdata.tex_info);
+                hid
+            },
+            None => center.get_handle::<Texture>(&cdata.tex_info.source,&cdata.tex_path,world)
+                           .ok_or_else(|| AssetLoadError::new(AssetErrorKind::FindDepAssetError).with_path(&cdata.tex_path))?
+        };
+        let sheet = SpriteSheet::new(cdata.width,cdata.height,hid,cdata.sprites,cdata.name_dic,cdata.borders,cdata.tags);
         Ok(sheet)
     }
 }
 
+#[derive(Clone)]
 pub struct FontAssetLoaderInfo {
-    path:String
+    path:String,
+    source:String
 }
 
 impl FontAssetLoaderInfo {
-    pub fn new(path: &str) -> Self { Self { path :String::from(path) } }
+    pub fn new(path: &str) -> Self { Self { path :String::from(path),source:String::from(DEFAULT_SOURCE) } }
+    pub fn with_source(mut self,source:&str) -> Self {
+        self.source = String::from(source);
+        self
+    }
 }
 
 
@@ -213,11 +281,146 @@ impl IAssetLoaderInfo for FontAssetLoaderInfo {
         &self.path
     }
 
+    fn source(&self) -> &str {
+        &self.source
+    }
+
     fn load_data(&self, _:&StorageCenter, source:&LoaderEnv) -> Result<Result<Self::CData,Self::Asset>,AssetLoadError> {
-        let bytes = source.load_fs_source(self.path.as_str())?;
-        let font_asset = FontArc::try_from_vec(bytes).map(|font| FontAsset {font} ).map_err(|_| AssetLoadError::FormatError)?;
+        let bytes = source.load_by_source(&self.source,self.path.as_str())?;
+        let font_asset = FontArc::try_from_vec(bytes).map(|font| FontAsset {font} )
+                                    .map_err(|e| AssetLoadError::new(AssetErrorKind::FormatError).with_cause(e).with_path(&self.path).with_source(&self.source))?;
         Ok(Err(font_asset))
     }
 
    
-}
\ No newline at end of file
+}
+
+#[derive(Clone)]
+pub struct InputBindingsLoaderInfo {
+    path:String,
+    source:String
+}
+
+impl InputBindingsLoaderInfo {
+    pub fn new(path: &str) -> Self { Self { path :String::from(path),source:String::from(DEFAULT_SOURCE) } }
+    pub fn with_source(mut self,source:&str) -> Self {
+        self.source = String::from(source);
+        self
+    }
+}
+
+impl Asset for InputBindings {
+    type LoaderInfo = InputBindingsLoaderInfo;
+}
+
+impl IAssetLoaderInfo for InputBindingsLoaderInfo {
+    type CData = ();
+    type Asset = InputBindings;
+
+    fn path(&self) -> &String {
+        &self.path
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn load_data(&self, _:&StorageCenter, source:&LoaderEnv) -> Result<Result<Self::CData,Self::Asset>,AssetLoadError> {
+        let bytes = source.load_by_source(&self.source,self.path.as_str())?;
+        let bindings = InputBindings::from_json(&bytes).map_err(|e| e.with_path(&self.path).with_source(&self.source))?;
+        Ok(Err(bindings))
+    }
+}
+
+#[test]
+fn test_sprite_sheet_from_memory() {
+    use crate::assets::{MemorySource,PNG_1X1};
+    let json = r#"{"meta":{"texture":"a.png","width":16,"height":8},
+                   "sprites":[{"name":"a","x":0,"y":0,"width":8,"height":8},{"name":"b","x":8,"y":0,"width":8,"height":8}],
+                   "sliceBorder":[{"target":"b","border":[[1,2,3,4]]}]}"#;
+    let memory = MemorySource::from_static(&[("ui/a.png",PNG_1X1)]);
+    memory.insert("ui/sheet.json",json.as_bytes().to_vec());
+    let env = LoaderEnv::default();
+    env.add_source("mem",Box::new(memory.clone()));
+
+    let center = StorageCenter::default();
+    let info = SpriteSheetLoaderInfo::new_only_path("ui/sheet.json").with_source("mem");
+    let cdata = info.load_data(&center,&env).ok().and_then(|r| r.ok()).unwrap();
+    assert_eq!((cdata.width,cdata.height),(16,8));
+    assert_eq!(cdata.tex_path,"ui/a.png");
+    assert!(cdata.texture.is_some());
+    assert_eq!(cdata.sprites.len(),2);
+    assert_eq!(cdata.name_dic.get("b"),Some(&1));
+    assert_eq!(cdata.borders.get("b"),Some(&vec![(1f32,2f32,3f32,4f32)]));
+
+    let err = SpriteSheetLoaderInfo::new_only_path("ui/sheet.json").load_data(&center,&env).err().unwrap();
+    assert_eq!(err.kind(),AssetErrorKind::LoadFileError);
+    assert_eq!((err.path(),err.source_name()),(Some("ui/sheet.json"),Some("fs")));
+
+    memory.insert("ui/bad.json",br#"{"meta":{"texture":"a.png","width":16,"height":8},"sprites":[{"name":"a","x":0,"y":0,"width":8,"height":8},{"name":"b","x":8}]}"#.to_vec());
+    let err = SpriteSheetLoaderInfo::new_only_path("ui/bad.json").with_source("mem").load_data(&center,&env).err().unwrap();
+    assert_eq!(err.kind(),AssetErrorKind::FormatError);
+    assert_eq!(err.detail(),Some("'sprites[1].y' must be an integer"));
+
+    memory.insert("ui/bad_border.json",br#"{"meta":{"texture":"a.png","width":16,"height":8},"sprites":[],
+                                            "sliceBorder":[{"target":"a","border":[[1,2,3,4]]},{"target":"b","border":[[1,2,3]]}]}"#.to_vec());
+    let err = SpriteSheetLoaderInfo::new_only_path("ui/bad_border.json").with_source("mem").load_data(&center,&env).err().unwrap();
+    assert_eq!(err.kind(),AssetErrorKind::FormatError);
+    assert_eq!(err.detail(),Some("'sliceBorder[1].border[0]' must be an array of 4 numbers"));
+
+    memory.insert("ui/nodep.json",br#"{"meta":{"texture":"missing.png","width":1,"height":1},"sprites":[]}"#.to_vec());
+    let err = SpriteSheetLoaderInfo::new_only_path("ui/nodep.json").with_source("mem").load_data(&center,&env).err().unwrap();
+    assert_eq!(err.kind(),AssetErrorKind::FindDepAssetError);
+    assert_eq!(err.path(),Some("ui/missing.png"));
+
+    memory.insert("ui/broken.png",b"not a png".to_vec());
+    memory.insert("ui/baddep.json",br#"{"meta":{"texture":"broken.png","width":1,"height":1},"sprites":[]}"#.to_vec());
+    let err = SpriteSheetLoaderInfo::new_only_path("ui/baddep.json").with_source("mem").load_data(&center,&env).err().unwrap();
+    assert_eq!(err.kind(),AssetErrorKind::LoadImageError);
+    assert_eq!(err.path(),Some("ui/broken.png"));
+}
+
+#[test]
+fn test_texture_from_memory() {
+    use crate::assets::{MemorySource,PNG_1X1};
+    let env = LoaderEnv::default();
+    env.add_source("mem",Box::new(MemorySource::from_static(&[("a.png",PNG_1X1)])));
+    let center = StorageCenter::default();
+    let info = TextuteLoaderInfo::new_only_path("a.png").with_source("mem");
+    assert_eq!(info.source(),"mem");
+    assert!(matches!(info.load_data(&center,&env),Ok(Ok(_))));
+    let err = TextuteLoaderInfo::new_only_path("a.png").load_data(&center,&env).err().unwrap();
+    assert_eq!(err.kind(),AssetErrorKind::LoadFileError);
+    let err = TextuteLoaderInfo::new_only_path("a.png").with_source("pak").load_data(&center,&env).err().unwrap();
+    assert_eq!(err.kind(),AssetErrorKind::NotFoundSource);
+}
+
+#[test]
+fn test_font_from_memory() {
+    use crate::assets::{MemorySource};
+    use glyph_brush::ab_glyph::{Font};
+    //只有head,hhea,maxp三个必需表的最小字体
+    let table = |tag:&[u8],offset:u32,len:u32| [tag,&0u32.to_be_bytes(),&offset.to_be_bytes(),&len.to_be_bytes()].concat();
+    let mut head = vec![0u8;54];
+    head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+    let hhea = vec![0u8;36];
+    let maxp = [&0x0000_5000u32.to_be_bytes()[..],&1u16.to_be_bytes()].concat();
+    let font = [&0x0001_0000u32.to_be_bytes()[..],&3u16.to_be_bytes(),&[0u8;6],
+                &table(b"head",60,54),&table(b"hhea",114,36),&table(b"maxp",150,6),&head,&hhea,&maxp].concat();
+
+    let env = LoaderEnv::default();
+    let memory = MemorySource::from_static(&[("bad.ttf",b"not a font")]);
+    memory.insert("font.ttf",font);
+    env.add_source("mem",Box::new(memory));
+    let center = StorageCenter::default();
+    let asset = match FontAssetLoaderInfo::new("font.ttf").with_source("mem").load_data(&center,&env) {
+        Ok(Err(asset)) => asset,
+        _ => panic!("font should load from memory")
+    };
+    assert_eq!(asset.font.units_per_em(),Some(1000f32));
+    let err = FontAssetLoaderInfo::new("bad.ttf").with_source("mem").load_data(&center,&env).err().unwrap();
+    assert_eq!(err.kind(),AssetErrorKind::FormatError);
+    assert_eq!((err.path(),err.source_name()),(Some("bad.ttf"),Some("mem")));
+    let err = FontAssetLoaderInfo::new("font.ttf").load_data(&center,&env).err().unwrap();
+    assert_eq!(err.kind(),AssetErrorKind::LoadFileError);
+}
@@ -1,18 +1,45 @@
-use crate::assets::{IAssetLoaderInfo,StorageCenter,AssetPack,LoaderEnv,Handle,AssetLoadError};
-use specs::{World};
+use crate::assets::{IAssetLoaderInfo,StorageCenter,AssetPack,LoaderEnv,Handle,AssetLoadError,AssetErrorKind,AssetStorage,AssetID,Asset};
+use specs::{World,RunNow,ReaderId};
+use shrev::{EventChannel};
 use crate::render::types::{Backend};
 use std::marker::PhantomData;
+use std::any::{TypeId,Any};
+use std::collections::{HashMap};
+use std::sync::{Arc,Mutex};
 use rendy::factory::{Factory};
 use rendy::command::{QueueId};
+use crossbeam_queue::SegQueue;
+use rayon::{ThreadPool};
+
+#[derive(Debug,Clone)]
+pub enum LoadState {
+    Pending,
+    Loaded,
+    Failed(AssetLoadError)
+}
+
+type LoadFinish = Box<dyn FnOnce(&World) + Send>;
+
+#[derive(Default)]
+struct LoadProgress {
+    states:HashMap<AssetID,LoadState>,
+    pending:HashMap<(String,String),Box<dyn Any + Send>>
+}
+
 pub struct Loader<T:AssetPack> {
-    env:LoaderEnv,
+    env:Arc<LoaderEnv>,
+    progress:Arc<Mutex<LoadProgress>>,
+    finished:Arc<SegQueue<LoadFinish>>,
     m:PhantomData<T>
 }
 
 impl<T> Default for Loader<T> where T:AssetPack {
     fn default() -> Self {
         Loader {
-            env:LoaderEnv::default(), m: PhantomData
+            env:Arc::new(LoaderEnv::default()),
+            progress:Arc::new(Mutex::new(LoadProgress::default())),
+            finished:Arc::new(SegQueue::new()),
+            m: PhantomData
         }
     }
 }
@@ -21,20 +48,214 @@ impl<T> Loader<T> where T:AssetPack {
     pub fn env(&self) -> &LoaderEnv {
         &self.env
     }
-    pub fn load_sync<AL:IAssetLoaderInfo,B:Backend>(&self,info:AL,world:&World) -> Result<Handle<AL::Asset>,AssetLoadError>  {
+    pub fn load_sync<AL,B>(&self,info:AL,world:&World) -> Result<Handle<AL::Asset>,AssetLoadError>
+        where AL:IAssetLoaderInfo + Send + Sync + 'static,B:Backend {
         let center_ref = world.fetch::<StorageCenter>();
         let ret = info.load_data(&center_ref, &self.env)?;
-        match ret {
-            Ok(cdata) => {
-                let qid = *world.fetch::<QueueId>();
-                let load_asset = AL::load(cdata,&mut world.fetch_mut::<Factory<B>>(),qid,&center_ref,world)?;
-                let hid = center_ref.insert_asset::<AL::Asset>(load_asset,info.path(),world);
-                Ok(hid)
-            },
-            Err(asset) => {
-               let hid = center_ref.insert_asset::<AL::Asset>(asset,info.path(),world);
-               Ok(hid)
+        let asset = match ret {
+            Ok(cdata) => load_with_factory::<AL,B>(cdata,&center_ref,world).map_err(|e| e.with_path(info.path()).with_source(info.source()))?,
+            Err(asset) => asset
+        };
+        let hid = center_ref.insert_asset::<AL::Asset>(asset,info.source(),info.path(),world);
+        center_ref.set_load_info((TypeId::of::<AL::Asset>(),hid.id()),info);
+        Ok(hid)
+    }
+
+    //load_data在线程池中执行,load在主线程的AssetLoadSystem中执行
+    pub fn load_async<AL,B>(&self,info:AL,world:&World) -> Handle<AL::Asset>
+        where AL:IAssetLoaderInfo + Send + Sync + 'static,AL::CData:Send,B:Backend {
+        let center = StorageCenter::clone(&world.fetch::<StorageCenter>());
+        if let Some(handle) = center.get_handle::<AL::Asset>(info.source(),info.path(),world) {
+            return handle;
+        }
+        let key = (String::from(info.source()),info.path().clone());
+        let handle = {
+            let mut progress = self.progress.lock().unwrap();
+            if let Some(handle) = progress.pending.get(&key).and_then(|h| h.downcast_ref::<Handle<AL::Asset>>()) {
+                return handle.clone();
+            }
+            let handle = world.fetch::<AssetStorage<AL::Asset>>().allocate();
+            let asset_id = (TypeId::of::<AL::Asset>(),handle.id());
+            progress.pending.insert(key.clone(),Box::new(handle.clone()));
+            progress.states.insert(asset_id,LoadState::Pending);
+            handle
+        };
+        let env = self.env.clone();
+        let progress = self.progress.clone();
+        let finished = self.finished.clone();
+        let task_handle = handle.clone();
+        let task = move || {
+            let asset_id = (TypeId::of::<AL::Asset>(),task_handle.id());
+            match info.load_data(&center,&env) {
+                Ok(ret) => {
+                    let finish:LoadFinish = Box::new(move |world:&World| {
+                        let load_asset = match ret {
+                            Ok(cdata) => load_with_factory::<AL,B>(cdata,&center,world).map_err(|e| e.with_path(&key.1).with_source(&key.0)),
+                            Err(asset) => Ok(asset)
+                        };
+                        let state = match load_asset {
+                            Ok(asset) => {
+                                center.insert_asset_with_handle(asset,&key.0,&key.1,&task_handle,world);
+                                center.set_load_info(asset_id,info);
+                                LoadState::Loaded
+                            },
+                            Err(err) => {
+                                world.fetch_mut::<AssetStorage<AL::Asset>>().release(task_handle);
+                                LoadState::Failed(err)
+                            }
+                        };
+                        let mut progress = progress.lock().unwrap();
+                        progress.pending.remove(&key);
+                        progress.states.insert(asset_id,state);
+                    });
+                    finished.push(finish);
+                },
+                Err(err) => {
+                    {
+                        let mut progress = progress.lock().unwrap();
+                        progress.pending.remove(&key);
+                        progress.states.insert(asset_id,LoadState::Failed(err));
+                    }
+                    //线程池中拿不到World,回收handle放到主线程做
+                    finished.push(Box::new(move |world:&World| {
+                        world.fetch_mut::<AssetStorage<AL::Asset>>().release(task_handle);
+                    }));
+                }
+            }
+        };
+        match world.try_fetch::<Arc<ThreadPool>>() {
+            Some(pool) => pool.spawn(task),
+            None => rayon::spawn(task)
+        }
+        handle
+    }
+
+    pub fn load_state<A:Asset>(&self,handle:&Handle<A>) -> Option<LoadState> {
+        let progress = self.progress.lock().unwrap();
+        progress.states.get(&(TypeId::of::<A>(),handle.id())).cloned()
+    }
+
+    //id回收后会分配给新的handle,不能再返回之前的状态;Pending的状态只属于新的handle
+    fn remove_states<'a>(&self,ids:impl Iterator<Item = &'a AssetID>) {
+        let mut progress = self.progress.lock().unwrap();
+        for asset_id in ids {
+            if let Some(LoadState::Pending) = progress.states.get(asset_id) {
+                continue;
+            }
+            progress.states.remove(asset_id);
+        }
+    }
+
+    pub fn process_finished(&self,world:&World) {
+        while let Some(finish) = self.finished.pop() {
+            finish(world);
+        }
+    }
+}
+
+//headless模式下没有Factory,需要上传到GPU的资源返回NoFactory错误
+pub(crate) fn load_with_factory<AL:IAssetLoaderInfo,B:Backend>(cdata:AL::CData,center:&StorageCenter,world:&World) -> Result<AL::Asset,AssetLoadError> {
+    let qid = world.try_fetch::<QueueId>().map(|qid| *qid);
+    match (qid,world.try_fetch_mut::<Factory<B>>()) {
+        (Some(qid),Some(mut factory)) => AL::load(cdata,&mut factory,qid,center,world),
+        _ => Err(AssetLoadError::new(AssetErrorKind::NoFactory))
+    }
+}
+
+pub struct AssetLoadSystem<T:AssetPack> {
+    freed:Option<ReaderId<AssetID>>,
+    m:PhantomData<T>
+}
+
+impl<T> Default for AssetLoadSystem<T> where T:AssetPack {
+    fn default() -> Self {
+        AssetLoadSystem {freed:None, m:PhantomData }
+    }
+}
+
+impl<'a,T> RunNow<'a> for AssetLoadSystem<T> where T:AssetPack {
+    fn run_now(&mut self,world:&'a World) {
+        if let Some(loader) = world.try_fetch::<Loader<T>>() {
+            if let Some(channel) = world.try_fetch::<EventChannel<AssetID>>() {
+                if let Some(reader) = self.freed.as_mut() {
+                    loader.remove_states(channel.read(reader));
+                }
+            }
+            if self.freed.is_none() {
+                self.freed = world.try_fetch_mut::<EventChannel<AssetID>>().map(|mut channel| channel.register_reader());
             }
-        } 
+            loader.process_finished(world);
+        }
     }
-}
\ No newline at end of file
+
+    fn setup(&mut self,_:&mut World) {}
+}
+
+#[test]
+fn test_same_path_from_sources() {
+    use crate::assets::{S2DAssetPack,MemorySource,InputBindingsLoaderInfo};
+    use crate::event::{InputBindings};
+    use crate::s2d::{DefaultBackend};
+    use specs::{WorldExt};
+    let dir = std::env::temp_dir().join(format!("seija_source_{}",std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("keys.json"),r#"{"actions":{"jump":["Space"]}}"#).unwrap();
+
+    let mut world = World::new();
+    world.insert(StorageCenter::default());
+    world.insert(AssetStorage::<InputBindings>::new());
+    let loader = Loader::<S2DAssetPack>::default();
+    loader.env().set_fs_root(dir.to_str().unwrap());
+    loader.env().add_source("mem",Box::new(MemorySource::from_static(&[("keys.json",br#"{"actions":{"fire":["Mouse:Left"]}}"#)])));
+    let fs = loader.load_sync::<_,DefaultBackend>(InputBindingsLoaderInfo::new("keys.json"),&world).unwrap();
+    let mem = loader.load_sync::<_,DefaultBackend>(InputBindingsLoaderInfo::new("keys.json").with_source("mem"),&world).unwrap();
+    assert_ne!(fs.id(),mem.id());
+    {
+        let storage = world.fetch::<AssetStorage<InputBindings>>();
+        assert!(storage.get(&fs).unwrap().actions.contains_key("jump"));
+        assert!(storage.get(&mem).unwrap().actions.contains_key("fire"));
+    }
+    let center = StorageCenter::clone(&world.fetch::<StorageCenter>());
+    assert_eq!(center.get_handle::<InputBindings>("mem","keys.json",&world).map(|h| h.id()),Some(mem.id()));
+    assert_eq!(center.get_handle::<InputBindings>("fs","keys.json",&world).map(|h| h.id()),Some(fs.id()));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_load_async_state() {
+    use crate::assets::{S2DAssetPack,MemorySource,InputBindingsLoaderInfo,AssetMaintainSystem};
+    use crate::event::{InputBindings};
+    use crate::s2d::{DefaultBackend};
+    use specs::{WorldExt};
+    let mut world = World::new();
+    world.insert(StorageCenter::default());
+    world.insert(AssetStorage::<InputBindings>::new());
+    world.insert(EventChannel::<AssetID>::new());
+    let loader = Loader::<S2DAssetPack>::default();
+    loader.env().add_source("mem",Box::new(MemorySource::from_static(&[("keys.json",br#"{"actions":{"jump":["Space"]}}"#)])));
+    world.insert(loader);
+    let mut load_system = AssetLoadSystem::<S2DAssetPack>::default();
+    load_system.run_now(&world);
+
+    let handle = world.fetch::<Loader<S2DAssetPack>>().load_async::<_,DefaultBackend>(InputBindingsLoaderInfo::new("keys.json").with_source("mem"),&world);
+    let start = std::time::Instant::now();
+    loop {
+        load_system.run_now(&world);
+        match world.fetch::<Loader<S2DAssetPack>>().load_state(&handle) {
+            Some(LoadState::Loaded) => break,
+            Some(LoadState::Pending) => assert!(start.elapsed().as_secs() < 10),
+            state => panic!("unexpected state {:?}",state)
+        }
+        std::thread::yield_now();
+    }
+    assert!(world.fetch::<AssetStorage<InputBindings>>().get(&handle).unwrap().actions.contains_key("jump"));
+
+    //id被回收后新的handle没有加载状态
+    let id = handle.id();
+    drop(handle);
+    AssetMaintainSystem::<InputBindings>::default().run_now(&world);
+    load_system.run_now(&world);
+    let reused = world.fetch::<AssetStorage<InputBindings>>().allocate();
+    assert_eq!(reused.id(),id);
+    assert!(world.fetch::<Loader<S2DAssetPack>>().load_state(&reused).is_none());
+}
@@ -5,24 +5,35 @@ mod env;
 mod errors;
 mod storage;
 mod loader;
-pub use env::{Source,LocalFS,LoaderEnv};
-pub use errors::{AssetLoadError};
+mod reload;
+mod archive;
+mod sheet_format;
+mod atlas;
+pub use env::{Source,LocalFS,MemorySource,LoaderEnv};
+pub use errors::{AssetLoadError,AssetErrorKind};
 pub use center::{StorageCenter,AssetID};
-pub use storage::{AssetStorage,Handle};
+pub use storage::{AssetStorage,Handle,AssetMaintainSystem};
 pub use pack::{S2DAssetPack};
-pub use loader::{Loader};
+pub use loader::{Loader,LoadState,AssetLoadSystem};
+pub use reload::{HotReload,HotReloadSystem};
+pub use archive::{ArchiveSource,build_archive};
+pub use sheet_format::{SheetFormat};
+pub use atlas::{AtlasBuilder,AtlasPage};
 
 use crate::render::types::{Backend};
 use rendy::factory::{Factory};
 use rendy::command::{QueueId};
 use specs::{World};
-pub use impls::{TextuteLoaderInfo,SpriteSheetLoaderInfo,FontAssetLoaderInfo};
+pub use impls::{TextuteLoaderInfo,SpriteSheetLoaderInfo,FontAssetLoaderInfo,InputBindingsLoaderInfo};
 
 
 pub trait IAssetLoaderInfo {
     type CData;
     type Asset:Asset;
     fn path(&self) -> &String;
+    fn source(&self) -> &str {
+        "fs"
+    }
     fn load_data(&self,center:&center::StorageCenter,source:&LoaderEnv) -> Result<Result<Self::CData,Self::Asset>,AssetLoadError>;
     fn load<B:Backend>(_:Self::CData,_:&mut Factory<B>,_:QueueId,_:&center::StorageCenter,_:&World) -> Result<Self::Asset,AssetLoadError> {
         unimplemented!()
@@ -31,8 +42,19 @@ pub trait IAssetLoaderInfo {
 
 pub trait Asset : Send + Sync + 'static {
     type LoaderInfo:IAssetLoaderInfo;
+    //该资源持有Handle的其他资源,插入StorageCenter时记录为依赖
+    fn dependencies(&self) -> Vec<AssetID> {
+        Vec::new()
+    }
 }
 
 pub trait AssetPack : Send + Sync + 'static {
 
-}
\ No newline at end of file
+}
+//测试用的1x1 RGBA png
+#[cfg(test)]
+pub(crate) const PNG_1X1:&[u8] = &[0x89,0x50,0x4E,0x47,0x0D,0x0A,0x1A,0x0A,0x00,0x00,0x00,0x0D,0x49,0x48,0x44,0x52,
+                                  0x00,0x00,0x00,0x01,0x00,0x00,0x00,0x01,0x08,0x06,0x00,0x00,0x00,0x1F,0x15,0xC4,
+                                  0x89,0x00,0x00,0x00,0x0B,0x49,0x44,0x41,0x54,0x78,0x9C,0x63,0xF8,0x0F,0x04,0x00,
+                                  0x09,0xFB,0x03,0xFD,0xFB,0x5E,0x6B,0x2B,0x00,0x00,0x00,0x00,0x49,0x45,0x4E,0x44,
+                                  0xAE,0x42,0x60,0x82];
@@ -1,8 +1,11 @@
-use crate::assets::{AssetPack,AssetStorage};
-use specs::{World};
-use crate::render::types::{Texture};
+use crate::assets::{AssetPack,AssetStorage,AssetMaintainSystem,AssetID,HotReload,
+                    TextuteLoaderInfo,SpriteSheetLoaderInfo,FontAssetLoaderInfo,InputBindingsLoaderInfo};
+use specs::{World,DispatcherBuilder};
+use shrev::{EventChannel};
+use crate::render::types::{Texture,Backend};
 use crate::render::components::{SpriteSheet};
 use crate::render::{FontAsset};
+use crate::event::{InputBindings};
 pub enum S2DAssetPack {
 
 }
@@ -17,5 +20,23 @@ impl S2DAssetPack {
         world.insert(AssetStorage::<Texture>::new());
         world.insert(AssetStorage::<SpriteSheet>::new());
         world.insert(AssetStorage::<FontAsset>::new());
+        world.insert(AssetStorage::<InputBindings>::new());
+        world.insert(EventChannel::<AssetID>::new());
+    }
+
+    pub fn register_all_system(builder:&mut DispatcherBuilder<'static,'static>) {
+        builder.add(AssetMaintainSystem::<SpriteSheet>::default(), "sprite_sheet_maintain", &[]);
+        builder.add(AssetMaintainSystem::<Texture>::default(), "texture_maintain", &["sprite_sheet_maintain"]);
+        builder.add(AssetMaintainSystem::<FontAsset>::default(), "font_maintain", &[]);
+        builder.add(AssetMaintainSystem::<InputBindings>::default(), "input_bindings_maintain", &[]);
+    }
+
+    pub fn hot_reload<B:Backend>(interval:f32) -> HotReload {
+        let mut hot_reload = HotReload::new(interval);
+        hot_reload.register::<TextuteLoaderInfo,B>();
+        hot_reload.register::<SpriteSheetLoaderInfo,B>();
+        hot_reload.register::<FontAssetLoaderInfo,B>();
+        hot_reload.register::<InputBindingsLoaderInfo,B>();
+        hot_reload
     }
 }
\ No newline at end of file
@@ -0,0 +1,174 @@
+use crate::assets::{Asset,IAssetLoaderInfo,StorageCenter,AssetPack,LoaderEnv,Loader,AssetLoadError,AssetStorage,AssetID};
+use crate::render::types::{Backend};
+use crate::core::{Time};
+use crate::assets::loader::{load_with_factory};
+use specs::{World,RunNow};
+use std::any::{TypeId};
+use std::collections::{HashMap,HashSet,VecDeque};
+use std::marker::PhantomData;
+use std::path::{PathBuf};
+use std::time::{SystemTime};
+
+type Reloader = Box<dyn Fn(AssetID,&StorageCenter,&LoaderEnv,&World) -> Result<(),AssetLoadError> + Send + Sync>;
+
+//只监视从fs加载的资源,archive和内存中的资源不会变化
+const WATCH_SOURCE:&str = "fs";
+
+//轮询fs根目录下已加载资源的修改时间,变化后用加载时的LoaderInfo重新加载并replace
+pub struct HotReload {
+    interval:f32,
+    elapsed:f32,
+    modified:HashMap<String,SystemTime>,
+    reloaders:HashMap<TypeId,Reloader>
+}
+
+impl HotReload {
+    pub fn new(interval:f32) -> Self {
+        HotReload {
+            interval,
+            elapsed:0f32,
+            modified:HashMap::new(),
+            reloaders:HashMap::new()
+        }
+    }
+
+    pub fn register<AL,B>(&mut self) where AL:IAssetLoaderInfo + Clone + 'static,B:Backend {
+        let reloader:Reloader = Box::new(move |asset_id,center,env,world| {
+            let info = match center.load_info::<AL>(&asset_id) {
+                Some(info) => info,
+                None => return Ok(())
+            };
+            let asset = match info.load_data(center,env)? {
+                Ok(cdata) => load_with_factory::<AL,B>(cdata,center,world)?,
+                Err(asset) => asset
+            };
+            let deps = asset.dependencies();
+            let mut storage = world.fetch_mut::<AssetStorage<AL::Asset>>();
+            if let Some(handle) = storage.get_handle(asset_id.1) {
+                storage.replace(&handle,asset);
+                center.set_dependencies(asset_id,deps);
+            }
+            Ok(())
+        });
+        self.reloaders.insert(TypeId::of::<AL::Asset>(),reloader);
+    }
+
+    pub fn update(&mut self,dt:f32,center:&StorageCenter,env:&LoaderEnv,world:&World) {
+        self.elapsed += dt;
+        if self.elapsed < self.interval {
+            return;
+        }
+        self.elapsed = 0f32;
+        let root = PathBuf::from(env.fs_root());
+        let paths = center.source_paths(WATCH_SOURCE);
+        //资源释放后不再监视,移除对应的修改时间
+        let watching:HashSet<&String> = paths.iter().map(|(path,_)| path).collect();
+        self.modified.retain(|path,_| watching.contains(path));
+        let mut changed_list:Vec<AssetID> = Vec::new();
+        for (path,asset_id) in paths.iter() {
+            let may_time = std::fs::metadata(root.join(path)).and_then(|m| m.modified());
+            if let Ok(time) = may_time {
+                let changed = self.modified.insert(path.clone(),time).map(|old| old != time).unwrap_or(false);
+                if changed {
+                    changed_list.push(*asset_id);
+                }
+            }
+        }
+        if changed_list.is_empty() {
+            return;
+        }
+        //依赖先于引用它的资源重新加载,例如texture变化后sheet也会重新加载
+        let id_paths:HashMap<AssetID,&String> = paths.iter().map(|(path,id)| (*id,path)).collect();
+        let mut reloaded:HashSet<AssetID> = HashSet::new();
+        let mut queue:VecDeque<AssetID> = changed_list.into_iter().collect();
+        while let Some(asset_id) = queue.pop_front() {
+            if !reloaded.insert(asset_id) {
+                continue;
+            }
+            if let Some(path) = id_paths.get(&asset_id) {
+                self.reload(path,asset_id,center,env,world);
+            }
+            queue.extend(center.dependents_of(&asset_id));
+        }
+    }
+
+    fn reload(&self,path:&str,asset_id:AssetID,center:&StorageCenter,env:&LoaderEnv,world:&World) {
+        if let Some(reloader) = self.reloaders.get(&asset_id.0) {
+            if let Err(err) = reloader(asset_id,center,env,world) {
+                eprintln!("hot reload {} failed: {}",path,err);
+            }
+        }
+    }
+}
+
+pub struct HotReloadSystem<T:AssetPack> {
+    m:PhantomData<T>
+}
+
+impl<T> Default for HotReloadSystem<T> where T:AssetPack {
+    fn default() -> Self {
+        HotReloadSystem { m:PhantomData }
+    }
+}
+
+impl<'a,T> RunNow<'a> for HotReloadSystem<T> where T:AssetPack {
+    fn run_now(&mut self,world:&'a World) {
+        if let Some(mut hot_reload) = world.try_fetch_mut::<HotReload>() {
+            let dt = world.try_fetch::<Time>().map(|t| t.delta_seconds()).unwrap_or(0f32);
+            let center = StorageCenter::clone(&world.fetch::<StorageCenter>());
+            if let Some(loader) = world.try_fetch::<Loader<T>>() {
+                hot_reload.update(dt,&center,loader.env(),world);
+            }
+        }
+    }
+
+    fn setup(&mut self,_:&mut World) {}
+}
+
+#[test]
+fn test_reload_only_fs_source() {
+    use crate::assets::{S2DAssetPack,MemorySource,InputBindingsLoaderInfo};
+    use crate::event::{InputBindings};
+    use crate::s2d::{DefaultBackend};
+    use specs::{WorldExt};
+    use std::time::{Duration,UNIX_EPOCH};
+    let dir = std::env::temp_dir().join(format!("seija_reload_{}",std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let write = |name:&str,json:&str,secs:u64| {
+        let path = dir.join(name);
+        std::fs::write(&path,json).unwrap();
+        std::fs::File::options().write(true).open(&path).unwrap().set_modified(UNIX_EPOCH + Duration::from_secs(secs)).unwrap();
+    };
+    write("a.json",r#"{"actions":{"jump":["Space"]}}"#,100);
+    write("b.json",r#"{"actions":{"jump":["Space"]}}"#,100);
+
+    let mut world = World::new();
+    world.insert(StorageCenter::default());
+    world.insert(AssetStorage::<InputBindings>::new());
+    let loader = Loader::<S2DAssetPack>::default();
+    loader.env().set_fs_root(dir.to_str().unwrap());
+    let memory = MemorySource::new();
+    memory.insert("b.json",br#"{"actions":{"fire":["Mouse:Left"]}}"#.to_vec());
+    loader.env().add_source("mem",Box::new(memory));
+    let a = loader.load_sync::<_,DefaultBackend>(InputBindingsLoaderInfo::new("a.json"),&world).unwrap();
+    let b = loader.load_sync::<_,DefaultBackend>(InputBindingsLoaderInfo::new("b.json").with_source("mem"),&world).unwrap();
+
+    let center = StorageCenter::clone(&world.fetch::<StorageCenter>());
+    let mut hot_reload = S2DAssetPack::hot_reload::<DefaultBackend>(0f32);
+    hot_reload.update(1f32,&center,loader.env(),&world);
+    write("a.json",r#"{"actions":{"jump":["Space"],"fire":["Mouse:Left"]}}"#,200);
+    write("b.json",r#"{"actions":{}}"#,200);
+    hot_reload.update(1f32,&center,loader.env(),&world);
+
+    let storage = world.fetch::<AssetStorage<InputBindings>>();
+    assert!(storage.get(&a).unwrap().actions.contains_key("fire"));
+    assert_eq!(storage.get_version(&a),Some(1));
+    assert!(storage.get(&b).unwrap().actions.contains_key("fire"));
+    assert_eq!(storage.get_version(&b),Some(0));
+
+    let fs_paths = center.source_paths(WATCH_SOURCE);
+    center.remove_asset_id(&fs_paths[0].1);
+    hot_reload.update(1f32,&center,loader.env(),&world);
+    assert!(hot_reload.modified.is_empty());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
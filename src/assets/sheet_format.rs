@@ -0,0 +1,181 @@
+use crate::assets::{AssetLoadError};
+use crate::common::rect::{Rect};
+use crate::render::components::{Sprite,TextureCoordinate,FrameTag,TagDirection};
+use serde_json::{Value,Map};
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum SheetFormat {
+    //{meta:{texture,width,height},sprites:[..],sliceBorder:[..]}
+    Seija,
+    //TexturePacker "JSON (Hash)" 与 "JSON (Array)"
+    TexturePacker,
+    Aseprite
+}
+
+impl SheetFormat {
+    pub fn detect(val:&Value) -> Option<SheetFormat> {
+        if val.get("frames").is_some() {
+            let meta = val.get("meta");
+            let app_is_ase = meta.and_then(|m| m.get("app")).and_then(|a| a.as_str()).map(|a| a.contains("aseprite")).unwrap_or(false);
+            if app_is_ase || meta.and_then(|m| m.get("frameTags")).is_some() {
+                return Some(SheetFormat::Aseprite);
+            }
+            return Some(SheetFormat::TexturePacker);
+        }
+        if val.get("sprites").is_some() {
+            return Some(SheetFormat::Seija);
+        }
+        None
+    }
+}
+
+pub struct AtlasData {
+    pub texture:String,
+    pub width:u32,
+    pub height:u32,
+    pub sprites:Vec<Sprite>,
+    pub tags:Vec<FrameTag>
+}
+
+//TexturePacker和Aseprite共用的frames格式,Hash格式按json中的顺序(serde_json开启了preserve_order)
+pub fn parse_atlas(val:&Value) -> Result<AtlasData,AssetLoadError> {
+    let meta = val.get("meta").and_then(|m| m.as_object()).ok_or_else(|| AssetLoadError::format("missing 'meta'"))?;
+    let texture = meta.get("image").and_then(|s| s.as_str()).ok_or_else(|| AssetLoadError::format("'meta.image' must be a string"))?;
+    let size = meta.get("size").ok_or_else(|| AssetLoadError::format("missing 'meta.size'"))?;
+    let width = get_u32(size,"w","meta.size")?;
+    let height = get_u32(size,"h","meta.size")?;
+
+    let mut frames:Vec<(String,String,&Value)> = Vec::new();
+    match val.get("frames") {
+        Some(Value::Array(arr)) => {
+            for (index,item) in arr.iter().enumerate() {
+                let at = format!("frames[{}]",index);
+                let name = item.get("filename").and_then(|s| s.as_str()).ok_or_else(|| AssetLoadError::format(format!("'{}.filename' must be a string",at)))?;
+                frames.push((String::from(name),at,item));
+            }
+        },
+        Some(Value::Object(map)) => {
+            for (name,item) in map.iter() {
+                frames.push((name.clone(),format!("frames[\"{}\"]",name),item));
+            }
+        },
+        _ => return Err(AssetLoadError::format("'frames' must be an array or object"))
+    }
+
+    let mut sprites = Vec::with_capacity(frames.len());
+    for (name,at,item) in frames {
+        sprites.push(parse_frame(name,&at,item,width as f32,height as f32)?);
+    }
+    let tags = match meta.get("frameTags").and_then(|t| t.as_array()) {
+        Some(arr) => parse_tags(arr,sprites.len())?,
+        None => Vec::new()
+    };
+    Ok(AtlasData {texture:String::from(texture),width,height,sprites,tags })
+}
+
+fn parse_frame(name:String,at:&str,item:&Value,s_w:f32,s_h:f32) -> Result<Sprite,AssetLoadError> {
+    let frame = item.get("frame").ok_or_else(|| AssetLoadError::format(format!("missing '{}.frame'",at)))?;
+    let frame_at = format!("{}.frame",at);
+    let (x,y) = (get_u32(frame,"x",&frame_at)? as i32,get_u32(frame,"y",&frame_at)? as i32);
+    let (w,h) = (get_u32(frame,"w",&frame_at)? as i32,get_u32(frame,"h",&frame_at)? as i32);
+    let rotated = item.get("rotated").and_then(|r| r.as_bool()).unwrap_or(false);
+    //旋转后图集中实际区域宽高互换
+    let (region_w,region_h) = if rotated { (h,w) } else { (w,h) };
+
+    let source_size = match item.get("sourceSize") {
+        Some(size) => {
+            let size_at = format!("{}.sourceSize",at);
+            (get_u32(size,"w",&size_at)?,get_u32(size,"h",&size_at)?)
+        },
+        None => (w as u32,h as u32)
+    };
+    let trim_offset = match item.get("spriteSourceSize") {
+        Some(src) => {
+            let src_at = format!("{}.spriteSourceSize",at);
+            (get_u32(src,"x",&src_at)? as i32,get_u32(src,"y",&src_at)? as i32)
+        },
+        None => (0,0)
+    };
+    let pivot = item.get("pivot").map(|p| {
+        (p.get("x").and_then(|v| v.as_f64()).unwrap_or(0.5) as f32,p.get("y").and_then(|v| v.as_f64()).unwrap_or(0.5) as f32)
+    }).unwrap_or((0.5f32,0.5f32));
+    let duration = item.get("duration").and_then(|d| d.as_f64()).map(|ms| ms as f32 / 1000f32);
+    Ok(Sprite {
+        name,
+        rect:Rect {x,y,width:w,height:h},
+        coord:TextureCoordinate {
+            left: x as f32 / s_w, right:(x + region_w) as f32 / s_w,
+            top: y as f32 / s_h,  bottom:(y + region_h) as f32 / s_h
+        },
+        rotated,
+        source_size,
+        trim_offset,
+        pivot,
+        duration
+    })
+}
+
+fn parse_tags(arr:&[Value],frame_count:usize) -> Result<Vec<FrameTag>,AssetLoadError> {
+    let mut tags = Vec::with_capacity(arr.len());
+    for (index,item) in arr.iter().enumerate() {
+        let at = format!("meta.frameTags[{}]",index);
+        let name = item.get("name").and_then(|s| s.as_str()).ok_or_else(|| AssetLoadError::format(format!("'{}.name' must be a string",at)))?;
+        let from = get_u32(item,"from",&at)?;
+        let to = get_u32(item,"to",&at)?;
+        if from > to || to as usize >= frame_count {
+            return Err(AssetLoadError::format(format!("'{}' range {}..{} out of {} frames",at,from,to,frame_count)));
+        }
+        let direction = match item.get("direction").and_then(|s| s.as_str()).unwrap_or("forward") {
+            "reverse" => TagDirection::Reverse,
+            "pingpong" => TagDirection::PingPong,
+            _ => TagDirection::Forward
+        };
+        tags.push(FrameTag {name:String::from(name),from,to,direction });
+    }
+    Ok(tags)
+}
+
+fn get_u32(val:&Value,key:&str,at:&str) -> Result<u32,AssetLoadError> {
+    val.as_object().and_then(|o:&Map<String,Value>| o.get(key)).and_then(|v| v.as_u64()).map(|v| v as u32)
+       .ok_or_else(|| AssetLoadError::format(format!("'{}.{}' must be a non-negative integer",at,key)))
+}
+
+#[test]
+fn test_parse_aseprite_hash() {
+    let json = r#"{"frames":{
+        "hero 2.aseprite":{"frame":{"x":0,"y":0,"w":16,"h":16},"rotated":false,"trimmed":false,
+                           "spriteSourceSize":{"x":0,"y":0,"w":16,"h":16},"sourceSize":{"w":16,"h":16},"duration":100},
+        "hero 10.aseprite":{"frame":{"x":0,"y":16,"w":8,"h":16},"rotated":true,"trimmed":true,
+                            "spriteSourceSize":{"x":4,"y":0,"w":8,"h":16},"sourceSize":{"w":16,"h":16},"duration":50}},
+        "meta":{"app":"http://www.aseprite.org/","image":"hero.png","size":{"w":32,"h":32},
+                "frameTags":[{"name":"run","from":0,"to":1,"direction":"pingpong"}]}}"#;
+    let val:Value = serde_json::from_str(json).unwrap();
+    assert_eq!(SheetFormat::detect(&val),Some(SheetFormat::Aseprite));
+    let atlas = parse_atlas(&val).unwrap();
+    assert_eq!(atlas.sprites[0].name,"hero 2.aseprite");
+    let rotated = &atlas.sprites[1];
+    assert!(rotated.rotated && rotated.is_trimmed());
+    assert_eq!((rotated.coord.right,rotated.coord.bottom),(0.5f32,0.75f32));
+    assert_eq!((rotated.trim_offset,rotated.duration),((4,0),Some(0.05f32)));
+    assert_eq!(atlas.tags[0].direction,TagDirection::PingPong);
+}
+
+#[test]
+fn test_parse_texture_packer_array() {
+    let json = r#"{"frames":[
+        {"filename":"b.png","frame":{"x":0,"y":0,"w":10,"h":20},"rotated":false,"trimmed":false,
+         "spriteSourceSize":{"x":0,"y":0,"w":10,"h":20},"sourceSize":{"w":10,"h":20}},
+        {"filename":"a.png","frame":{"x":10,"y":0,"w":6,"h":4},"rotated":true,"trimmed":true,
+         "spriteSourceSize":{"x":1,"y":2,"w":6,"h":4},"sourceSize":{"w":8,"h":8},"pivot":{"x":0.25,"y":1}}],
+        "meta":{"app":"https://www.codeandweb.com/texturepacker","image":"ui.png","size":{"w":32,"h":32}}}"#;
+    let val:Value = serde_json::from_str(json).unwrap();
+    assert_eq!(SheetFormat::detect(&val),Some(SheetFormat::TexturePacker));
+    let atlas = parse_atlas(&val).unwrap();
+    assert_eq!((atlas.texture.as_str(),atlas.width,atlas.height),("ui.png",32,32));
+    assert_eq!(atlas.sprites.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(),vec!["b.png","a.png"]);
+    let a = &atlas.sprites[1];
+    assert_eq!((a.rect.width,a.rect.height),(6,4));
+    assert_eq!((a.coord.left,a.coord.right,a.coord.bottom),(10f32 / 32f32,14f32 / 32f32,6f32 / 32f32));
+    assert_eq!((a.source_size,a.trim_offset,a.pivot),((8,8),(1,2),(0.25f32,1f32)));
+    assert!(atlas.tags.is_empty() && atlas.sprites[0].duration.is_none());
+}
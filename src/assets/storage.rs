@@ -1,5 +1,8 @@
-use crate::assets::{Asset};
+use crate::assets::{Asset,AssetID,StorageCenter};
 use specs::storage::{VecStorage,UnprotectedStorage};
+use specs::{System,Write,ReadExpect};
+use shrev::{EventChannel};
+use std::any::{TypeId};
 use hibitset::BitSet;
 use std::marker::PhantomData;
 use std::sync::Arc;
@@ -26,10 +29,9 @@ impl<A> Handle<A> {
         *self.id.as_ref()
     }
 
-    /*
     fn is_unique(&self) -> bool {
         Arc::strong_count(&self.id) == 1
-    }*/
+    }
 
     pub fn new(id:u32) -> Self {
         Handle {
@@ -82,6 +84,10 @@ impl<A: Asset> AssetStorage<A> {
         None
     }
 
+    pub fn get_handle(&self,id:u32) -> Option<Handle<A>> {
+        self.handles.iter().find(|h| h.id() == id).cloned()
+    }
+
     pub fn get(&self,handle:&Handle<A>) -> Option<&A> {
         if self.bitset.contains(handle.id()) {
             Some(unsafe { &self.assets.get(handle.id()).0 })
@@ -131,6 +137,20 @@ impl<A: Asset> AssetStorage<A> {
         handle
     }
 
+    pub fn insert_with_handle(&mut self,handle:&Handle<A>,asset:A) {
+        let id = handle.id();
+        if self.bitset.add(id) {
+            let data = unsafe { self.assets.get_mut(id) };
+            data.1 += 1;
+            data.0 = asset;
+        } else {
+            self.handles.push(handle.clone());
+            unsafe {
+                self.assets.insert(id, (asset,0))
+            }
+        }
+    }
+
     pub fn contains(&self,handle:&Handle<A>) -> bool {
         self.bitset.contains(handle.id())
     }
@@ -155,6 +175,35 @@ impl<A: Asset> AssetStorage<A> {
         }
     }
 
+    //加载失败的handle没有资源,交给Storage持有,外部引用都释放后由maintain回收id
+    pub fn release(&mut self,handle:Handle<A>) {
+        if !self.bitset.contains(handle.id()) && !self.handles.iter().any(|h| h.id() == handle.id()) {
+            self.handles.push(handle);
+        }
+    }
+
+    //释放只剩下Storage自己引用的资源,返回回收的id,包括加载失败后release的handle
+    pub fn maintain(&mut self) -> Vec<u32> {
+        let mut unloaded = Vec::new();
+        let bitset = &mut self.bitset;
+        let assets = &mut self.assets;
+        let unused_handles = &self.unused_handles;
+        self.handles.retain(|handle| {
+            if handle.is_unique() {
+                let id = handle.id();
+                if bitset.remove(id) {
+                    unsafe { assets.remove(id); }
+                }
+                unloaded.push(id);
+                unused_handles.push(handle.clone());
+                false
+            } else {
+                true
+            }
+        });
+        unloaded
+    }
+
     pub fn unload_all(&mut self) {
         unsafe {self.assets.clean(&self.bitset) }
         self.bitset.clear();
@@ -171,4 +220,98 @@ impl<A: Asset> Default for AssetStorage<A> {
             unused_handles: Default::default()
         }
     }
-}
\ No newline at end of file
+}
+
+pub struct AssetMaintainSystem<A:Asset> {
+    marker: PhantomData<A>
+}
+
+impl<A: Asset> Default for AssetMaintainSystem<A> {
+    fn default() -> Self {
+        AssetMaintainSystem { marker: PhantomData }
+    }
+}
+
+impl<'a,A: Asset> System<'a> for AssetMaintainSystem<A> {
+    type SystemData = (
+        Write<'a,AssetStorage<A>>,
+        ReadExpect<'a,StorageCenter>,
+        Write<'a,EventChannel<AssetID>>
+    );
+
+    fn run(&mut self,(mut storage,center,mut channel): Self::SystemData) {
+        for id in storage.maintain() {
+            let asset_id = (TypeId::of::<A>(),id);
+            center.remove_asset_id(&asset_id);
+            channel.single_write(asset_id);
+        }
+    }
+}
+
+#[cfg(test)]
+struct NumAsset(u32);
+#[cfg(test)]
+struct NumLoaderInfo(String);
+
+#[cfg(test)]
+impl Asset for NumAsset {
+    type LoaderInfo = NumLoaderInfo;
+}
+
+#[cfg(test)]
+impl crate::assets::IAssetLoaderInfo for NumLoaderInfo {
+    type CData = ();
+    type Asset = NumAsset;
+    fn path(&self) -> &String { &self.0 }
+    fn load_data(&self,_:&StorageCenter,_:&crate::assets::LoaderEnv) -> Result<Result<(),NumAsset>,crate::assets::AssetLoadError> {
+        Ok(Err(NumAsset(0)))
+    }
+}
+
+#[test]
+fn test_maintain_recycle() {
+    let mut storage:AssetStorage<NumAsset> = AssetStorage::new();
+    let a = storage.insert(NumAsset(1));
+    let b = storage.insert(NumAsset(2));
+    assert!(storage.maintain().is_empty());
+    let b_id = b.id();
+    drop(b);
+    assert_eq!(storage.maintain(),vec![b_id]);
+    assert!(!storage.contains_id(b_id));
+    assert_eq!(storage.get(&a).map(|n| n.0),Some(1));
+    let c = storage.insert(NumAsset(3));
+    assert_eq!(c.id(),b_id);
+    assert_eq!(storage.get(&c).map(|n| n.0),Some(3));
+
+    let failed = storage.allocate();
+    let failed_id = failed.id();
+    storage.release(failed.clone());
+    assert!(storage.maintain().is_empty());
+    drop(failed);
+    assert_eq!(storage.maintain(),vec![failed_id]);
+    assert_eq!(storage.allocate().id(),failed_id);
+}
+
+#[test]
+fn test_maintain_remove_path() {
+    use specs::{World,WorldExt,RunNow};
+    let mut world = World::new();
+    world.insert(AssetStorage::<NumAsset>::new());
+    world.insert(StorageCenter::default());
+    world.insert(EventChannel::<AssetID>::new());
+    let mut reader = world.fetch_mut::<EventChannel<AssetID>>().register_reader();
+    let path = String::from("num.txt");
+    let center = StorageCenter::clone(&world.fetch::<StorageCenter>());
+
+    let handle = center.insert_asset(NumAsset(1),"fs",&path,&world);
+    let asset_id = (TypeId::of::<NumAsset>(),handle.id());
+    assert_eq!(center.get_handle::<NumAsset>("fs",&path,&world).map(|h| h.id()),Some(handle.id()));
+    drop(handle);
+    AssetMaintainSystem::<NumAsset>::default().run_now(&world);
+    assert_eq!(world.fetch_mut::<EventChannel<AssetID>>().read(&mut reader).cloned().collect::<Vec<_>>(),vec![asset_id]);
+    assert!(!center.contains("fs",&path));
+    assert!(center.get_handle::<NumAsset>("fs",&path,&world).is_none());
+
+    let handle = center.insert_asset(NumAsset(2),"fs",&path,&world);
+    assert_eq!(world.fetch::<AssetStorage<NumAsset>>().get(&handle).map(|n| n.0),Some(2));
+}
@@ -2,7 +2,9 @@ pub mod transform;
 pub mod rect;
 pub mod tree;
 mod update;
-pub use update::{Update,UpdateDesc,UpdateSystem,UpdateType,UpdateCallBack};
+mod tween;
+pub use update::{Update,UpdateDesc,UpdateSystem,UpdateType,UpdateCallBack,UpdateId};
+pub use tween::{Tween,Tweens,TweenValue,TweenTargets,TweenSystem,TweenCallbackSystem,TweenCompleted,Ease};
 pub use rect::{Rect2D};
 pub use transform::transform::{Transform};
 pub use transform::component::{TransformSystem};
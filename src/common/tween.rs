@@ -0,0 +1,408 @@
+use crate::common::{Transform,Rect2D};
+use crate::core::{Time};
+use crate::render::components::{ImageRender,SpriteRender,TextRender,Mesh2D};
+use nalgebra::{Vector3,UnitQuaternion};
+use specs::{Component,DenseVecStorage,Entity,Entities,World,RunNow,System,Read,Write,WriteStorage,Join};
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+type TweenCallBack = Arc<dyn Fn(Entity,&World) + Send + Sync>;
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Ease {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    ExpoIn,
+    ExpoOut,
+    BackIn,
+    BackOut,
+    ElasticOut,
+    BounceOut
+}
+
+impl Ease {
+    pub fn apply(&self,t:f32) -> f32 {
+        let t = t.clamp(0f32,1f32);
+        match self {
+            Ease::Linear => t,
+            Ease::QuadIn => t * t,
+            Ease::QuadOut => t * (2f32 - t),
+            Ease::QuadInOut => if t < 0.5 { 2f32 * t * t } else { -1f32 + (4f32 - 2f32 * t) * t },
+            Ease::CubicIn => t * t * t,
+            Ease::CubicOut => { let f = t - 1f32; f * f * f + 1f32 },
+            Ease::CubicInOut => if t < 0.5 { 4f32 * t * t * t } else { let f = 2f32 * t - 2f32; 0.5 * f * f * f + 1f32 },
+            Ease::SineIn => 1f32 - (t * PI * 0.5).cos(),
+            Ease::SineOut => (t * PI * 0.5).sin(),
+            Ease::SineInOut => 0.5 * (1f32 - (t * PI).cos()),
+            Ease::ExpoIn => if t == 0f32 { 0f32 } else { 2f32.powf(10f32 * (t - 1f32)) },
+            Ease::ExpoOut => if t == 1f32 { 1f32 } else { 1f32 - 2f32.powf(-10f32 * t) },
+            Ease::BackIn => { let s = 1.70158f32; t * t * ((s + 1f32) * t - s) },
+            Ease::BackOut => { let s = 1.70158f32; let f = t - 1f32; f * f * ((s + 1f32) * f + s) + 1f32 },
+            Ease::ElasticOut => {
+                if t == 0f32 || t == 1f32 { t } else { 2f32.powf(-10f32 * t) * ((t - 0.075) * (2f32 * PI) / 0.3).sin() + 1f32 }
+            },
+            Ease::BounceOut => {
+                if t < 1f32 / 2.75 {
+                    7.5625 * t * t
+                } else if t < 2f32 / 2.75 {
+                    let f = t - 1.5 / 2.75; 7.5625 * f * f + 0.75
+                } else if t < 2.5 / 2.75 {
+                    let f = t - 2.25 / 2.75; 7.5625 * f * f + 0.9375
+                } else {
+                    let f = t - 2.625 / 2.75; 7.5625 * f * f + 0.984375
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum TweenValue {
+    Position(Vector3<f32>),
+    Scale(Vector3<f32>),
+    Rotation(UnitQuaternion<f32>),
+    Size(f32,f32),
+    //作用于ImageRender/SpriteRender/TextRender中存在的那个
+    Color([f32;4])
+}
+
+impl TweenValue {
+    fn lerp(&self,to:&TweenValue,t:f32) -> TweenValue {
+        match (self,to) {
+            (TweenValue::Position(a),TweenValue::Position(b)) => TweenValue::Position(a.lerp(b,t)),
+            (TweenValue::Scale(a),TweenValue::Scale(b)) => TweenValue::Scale(a.lerp(b,t)),
+            (TweenValue::Rotation(a),TweenValue::Rotation(b)) => TweenValue::Rotation(a.slerp(b,t)),
+            (TweenValue::Size(aw,ah),TweenValue::Size(bw,bh)) => TweenValue::Size(aw + (bw - aw) * t,ah + (bh - ah) * t),
+            (TweenValue::Color(a),TweenValue::Color(b)) => {
+                let mut c = [0f32;4];
+                for i in 0..4 {
+                    c[i] = a[i] + (b[i] - a[i]) * t;
+                }
+                TweenValue::Color(c)
+            },
+            _ => *to
+        }
+    }
+}
+
+//一个实体上被tween修改的组件
+pub struct TweenTargets<'a> {
+    pub transform:Option<&'a mut Transform>,
+    pub rect:Option<&'a mut Rect2D>,
+    pub image:Option<&'a mut ImageRender>,
+    pub sprite:Option<&'a mut SpriteRender>,
+    pub text:Option<&'a mut TextRender>,
+    pub mesh:Option<&'a mut Mesh2D>
+}
+
+impl<'a> TweenTargets<'a> {
+    fn read(&self,like:&TweenValue) -> Option<TweenValue> {
+        match like {
+            TweenValue::Position(_) => self.transform.as_ref().map(|t| TweenValue::Position(*t.position())),
+            TweenValue::Scale(_) => self.transform.as_ref().map(|t| TweenValue::Scale(*t.scale())),
+            TweenValue::Rotation(_) => self.transform.as_ref().map(|t| TweenValue::Rotation(*t.rotation())),
+            TweenValue::Size(_,_) => self.rect.as_ref().map(|r| TweenValue::Size(r.width(),r.height())),
+            TweenValue::Color(_) => {
+                let color = self.image.as_ref().map(|i| *i.get_color())
+                                .or_else(|| self.sprite.as_ref().map(|s| *s.get_color()))
+                                .or_else(|| self.text.as_ref().map(|t| t.color));
+                color.map(TweenValue::Color)
+            }
+        }
+    }
+
+    fn write(&mut self,value:TweenValue) {
+        match value {
+            TweenValue::Position(v) => { if let Some(t) = self.transform.as_mut() { t.set_position(v); } },
+            TweenValue::Scale(v) => { if let Some(t) = self.transform.as_mut() { t.set_scale(v) } },
+            TweenValue::Rotation(q) => { if let Some(t) = self.transform.as_mut() { t.set_rotation(q) } },
+            TweenValue::Size(w,h) => {
+                if let Some(r) = self.rect.as_mut() {
+                    r.set_width(w);
+                    r.set_height(h);
+                }
+            },
+            TweenValue::Color(c) => {
+                if let Some(i) = self.image.as_mut() { i.set_color(c[0],c[1],c[2],c[3]) }
+                if let Some(s) = self.sprite.as_mut() { s.set_color(c[0],c[1],c[2],c[3]) }
+                if let Some(t) = self.text.as_mut() { t.set_color(c[0],c[1],c[2],c[3]) }
+                if let Some(m) = self.mesh.as_mut() { m.is_dirty = true }
+            }
+        }
+    }
+}
+
+enum TweenKind {
+    //from为None时在开始时读取当前值
+    Prop {from:Option<TweenValue>,to:TweenValue,duration:f32,elapsed:f32},
+    Delay {duration:f32,elapsed:f32},
+    Sequence {items:Vec<Tween>,index:usize},
+    Parallel {items:Vec<Tween>}
+}
+
+pub struct Tween {
+    kind:TweenKind,
+    ease:Ease,
+    on_complete:Option<TweenCallBack>
+}
+
+impl Tween {
+    fn new(kind:TweenKind) -> Self {
+        Tween {kind,ease:Ease::Linear,on_complete:None }
+    }
+
+    pub fn to(value:TweenValue,duration:f32) -> Self {
+        Tween::new(TweenKind::Prop {from:None,to:value,duration,elapsed:0f32 })
+    }
+
+    pub fn from_to(from:TweenValue,to:TweenValue,duration:f32) -> Self {
+        Tween::new(TweenKind::Prop {from:Some(from),to,duration,elapsed:0f32 })
+    }
+
+    pub fn move_to(pos:Vector3<f32>,duration:f32) -> Self { Tween::to(TweenValue::Position(pos),duration) }
+    pub fn scale_to(scale:Vector3<f32>,duration:f32) -> Self { Tween::to(TweenValue::Scale(scale),duration) }
+    pub fn rotate_to(rotation:UnitQuaternion<f32>,duration:f32) -> Self { Tween::to(TweenValue::Rotation(rotation),duration) }
+    pub fn size_to(width:f32,height:f32,duration:f32) -> Self { Tween::to(TweenValue::Size(width,height),duration) }
+    pub fn color_to(color:[f32;4],duration:f32) -> Self { Tween::to(TweenValue::Color(color),duration) }
+
+    pub fn delay(duration:f32) -> Self {
+        Tween::new(TweenKind::Delay {duration,elapsed:0f32 })
+    }
+
+    pub fn sequence(items:Vec<Tween>) -> Self {
+        Tween::new(TweenKind::Sequence {items,index:0 })
+    }
+
+    pub fn parallel(items:Vec<Tween>) -> Self {
+        Tween::new(TweenKind::Parallel {items })
+    }
+
+    pub fn ease(mut self,ease:Ease) -> Self {
+        self.ease = ease;
+        self
+    }
+
+    pub fn on_complete<F>(mut self,f:F) -> Self where F:Fn(Entity,&World) + Send + Sync + 'static {
+        self.on_complete = Some(Arc::new(f));
+        self
+    }
+
+    //返回未用完的时间,None表示尚未结束
+    fn update(&mut self,dt:f32,targets:&mut TweenTargets,done:&mut Vec<TweenCallBack>) -> Option<f32> {
+        let ease = self.ease;
+        let left = match &mut self.kind {
+            TweenKind::Prop {from,to,duration,elapsed} => {
+                if from.is_none() {
+                    *from = targets.read(to);
+                }
+                *elapsed += dt;
+                let t = if *duration <= 0f32 { 1f32 } else { (*elapsed / *duration).min(1f32) };
+                if let Some(from) = from.as_ref() {
+                    targets.write(from.lerp(to,ease.apply(t)));
+                }
+                if *elapsed >= *duration { Some(*elapsed - duration.max(0f32)) } else { None }
+            },
+            TweenKind::Delay {duration,elapsed} => {
+                *elapsed += dt;
+                if *elapsed >= *duration { Some(*elapsed - *duration) } else { None }
+            },
+            TweenKind::Sequence {items,index} => {
+                let mut dt = dt;
+                loop {
+                    match items.get_mut(*index) {
+                        None => break Some(dt),
+                        Some(item) => match item.update(dt,targets,done) {
+                            Some(left) => {
+                                *index += 1;
+                                dt = left;
+                            },
+                            None => break None
+                        }
+                    }
+                }
+            },
+            TweenKind::Parallel {items} => {
+                let mut min_left:Option<f32> = Some(dt);
+                for item in items.iter_mut() {
+                    match item.update(dt,targets,done) {
+                        Some(left) => min_left = min_left.map(|m| m.min(left)),
+                        None => min_left = None
+                    }
+                }
+                items.retain(|item| !item.is_finished());
+                if items.is_empty() { min_left.or(Some(0f32)) } else { None }
+            }
+        };
+        if left.is_some() {
+            if let Some(f) = self.on_complete.take() {
+                done.push(f);
+            }
+        }
+        left
+    }
+
+    fn is_finished(&self) -> bool {
+        match &self.kind {
+            TweenKind::Prop {elapsed,duration,..} | TweenKind::Delay {elapsed,duration} => *elapsed >= *duration,
+            TweenKind::Sequence {items,index} => *index >= items.len(),
+            TweenKind::Parallel {items} => items.is_empty()
+        }
+    }
+}
+
+//实体上正在运行的tween,结束后自动移除
+#[derive(Default)]
+pub struct Tweens {
+    running:Vec<Tween>
+}
+
+impl Tweens {
+    pub fn add(&mut self,tween:Tween) {
+        self.running.push(tween);
+    }
+
+    pub fn clear(&mut self) {
+        self.running.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.running.is_empty()
+    }
+}
+
+impl Component for Tweens {
+    type Storage = DenseVecStorage<Self>;
+}
+
+//已完成的tween回调,由TweenSystem收集,TweenCallbackSystem调用
+#[derive(Default)]
+pub struct TweenCompleted {
+    done:Vec<(Entity,TweenCallBack)>
+}
+
+//作为普通System加在transform_system之前,tween的值当帧即可进入矩阵和剔除
+#[derive(Default)]
+pub struct TweenSystem;
+
+impl<'a> System<'a> for TweenSystem {
+    type SystemData = (
+        Entities<'a>,
+        Option<Read<'a,Time>>,
+        WriteStorage<'a,Tweens>,
+        WriteStorage<'a,Transform>,
+        WriteStorage<'a,Rect2D>,
+        WriteStorage<'a,ImageRender>,
+        WriteStorage<'a,SpriteRender>,
+        WriteStorage<'a,TextRender>,
+        WriteStorage<'a,Mesh2D>,
+        Write<'a,TweenCompleted>
+    );
+
+    fn run(&mut self,(entities,time,mut tweens,mut transforms,mut rects,mut images,mut sprites,mut texts,mut meshes,mut completed):Self::SystemData) {
+        let dt = time.map(|t| t.delta_seconds()).unwrap_or(0f32);
+        let mut done = Vec::new();
+        for (entity,tween) in (&entities,&mut tweens).join() {
+            let mut targets = TweenTargets {
+                transform:transforms.get_mut(entity),
+                rect:rects.get_mut(entity),
+                image:images.get_mut(entity),
+                sprite:sprites.get_mut(entity),
+                text:texts.get_mut(entity),
+                mesh:meshes.get_mut(entity)
+            };
+            for item in tween.running.iter_mut() {
+                item.update(dt,&mut targets,&mut done);
+            }
+            tween.running.retain(|t| !t.is_finished());
+            completed.done.extend(done.drain(..).map(|f| (entity,f)));
+        }
+    }
+}
+
+//完成回调需要&World,所以作为thread_local在dispatch结束后调用
+#[derive(Default)]
+pub struct TweenCallbackSystem;
+
+impl<'a> RunNow<'a> for TweenCallbackSystem {
+    fn run_now(&mut self,world:&'a World) {
+        let done = match world.try_fetch_mut::<TweenCompleted>() {
+            Some(mut completed) => std::mem::take(&mut completed.done),
+            None => return
+        };
+        for (entity,f) in done {
+            f(entity,world);
+        }
+    }
+
+    fn setup(&mut self,world:&mut World) {
+        world.entry::<TweenCompleted>().or_insert_with(TweenCompleted::default);
+    }
+}
+
+#[test]
+fn test_tween_sequence() {
+    let mut rect = Rect2D::new(0f32,0f32,[0.5f32,0.5f32]);
+    let finished = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let counter = finished.clone();
+    let mut tween = Tween::sequence(vec![
+        Tween::delay(1f32),
+        Tween::size_to(10f32,20f32,2f32),
+        Tween::parallel(vec![Tween::size_to(0f32,0f32,1f32),Tween::delay(2f32)])
+    ]).on_complete(move |_,_| { counter.fetch_add(1,std::sync::atomic::Ordering::SeqCst); });
+    let mut done = Vec::new();
+    fn step(tween:&mut Tween,rect:&mut Rect2D,dt:f32,done:&mut Vec<TweenCallBack>) -> Option<f32> {
+        let mut targets = TweenTargets {transform:None,rect:Some(rect),image:None,sprite:None,text:None,mesh:None };
+        tween.update(dt,&mut targets,done)
+    }
+    assert!(step(&mut tween,&mut rect,2f32,&mut done).is_none());
+    assert_eq!((rect.width(),rect.height()),(5f32,10f32));
+    assert!(step(&mut tween,&mut rect,1.5f32,&mut done).is_none());
+    assert_eq!(rect.width(),5f32);
+    assert_eq!(step(&mut tween,&mut rect,2f32,&mut done),Some(0.5f32));
+    assert_eq!(rect.width(),0f32);
+    assert_eq!(done.len(),1);
+    assert!(tween.is_finished());
+    assert_eq!(Ease::QuadInOut.apply(0.5),0.5);
+}
+
+#[test]
+fn test_tween_before_transform() {
+    use specs::{Builder,DispatcherBuilder,WorldExt};
+    use crate::common::{Tree,transform::build_transform_module};
+    let mut world = World::new();
+    world.register::<Tweens>();
+    world.register::<ImageRender>();
+    world.register::<SpriteRender>();
+    world.register::<TextRender>();
+    world.register::<Mesh2D>();
+    world.register::<Rect2D>();
+    world.insert(Tree::default());
+    world.insert(TweenCompleted::default());
+    let mut time = Time::default();
+    time.set_delta_time(std::time::Duration::from_secs(1));
+    world.insert(time);
+    let mut builder = DispatcherBuilder::new();
+    builder.add(TweenSystem, "tween_system", &[]);
+    build_transform_module(&mut world,&mut builder);
+    builder.add_thread_local(TweenCallbackSystem);
+    let mut dispatcher = builder.build();
+
+    let finished = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let flag = finished.clone();
+    let mut tweens = Tweens::default();
+    tweens.add(Tween::move_to(Vector3::new(10f32,0f32,0f32),1f32).on_complete(move |_,_| flag.store(true,std::sync::atomic::Ordering::SeqCst)));
+    let e = world.create_entity().with(Transform::default()).with(tweens).build();
+    dispatcher.dispatch(&world);
+    let transforms = world.read_storage::<Transform>();
+    assert_eq!(transforms.get(e).unwrap().global_matrix()[(0,3)],10f32);
+    assert!(finished.load(std::sync::atomic::Ordering::SeqCst));
+    assert!(world.read_storage::<Tweens>().get(e).unwrap().is_empty());
+}
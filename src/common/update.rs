@@ -1,76 +1,186 @@
-use specs::{Component,VecStorage,World,WorldExt,Join};
+use specs::{Component,VecStorage,World,WorldExt,Join,Entity};
 use std::sync::{Arc,Mutex};
+use std::sync::atomic::{AtomicU64,Ordering};
+
+#[derive(Debug,Clone,Copy,PartialEq)]
 pub enum UpdateType {
     Frame(u32),
-    Time(f32)
+    Time(f32),
+    //经过指定秒数后触发一次,然后自动移除
+    Once(f32)
 }
+
+//dt为距离上次触发累计的时间
 pub trait UpdateCallBack :Send + Sync {
-    fn run(&self);
+    fn run(&self,entity:Entity,dt:f32,world:&mut World);
+}
+
+impl<F> UpdateCallBack for F where F:Fn(Entity,f32,&mut World) + Send + Sync {
+    fn run(&self,entity:Entity,dt:f32,world:&mut World) {
+        self(entity,dt,world)
+    }
 }
 
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub struct UpdateId(u64);
+
+static NEXT_UPDATE_ID:AtomicU64 = AtomicU64::new(1);
+
 #[derive(Default)]
 pub struct Update {
     updates:Vec<Arc<UpdateDesc>>
 }
 
 impl Update {
-    pub fn update(&self,dt:f32) {
-        for d in self.updates.iter() {
-            d.update(dt);
+    pub fn insert(&mut self,desc:UpdateDesc) -> UpdateId {
+        let id = desc.id;
+        self.updates.push(Arc::new(desc));
+        id
+    }
+
+    //同一帧中已经排队但还未执行的回调也不会再触发
+    pub fn remove(&mut self,id:UpdateId) -> bool {
+        match self.updates.iter().position(|d| d.id == id) {
+            Some(idx) => {
+                self.updates.remove(idx).cancel();
+                true
+            },
+            None => false
         }
     }
 
-    pub fn insert(&mut self,desc:UpdateDesc) {
-        self.updates.push(Arc::new(desc));
+    pub fn set_paused(&self,id:UpdateId,paused:bool) -> bool {
+        match self.get(id) {
+            Some(desc) => {
+                desc.state.lock().unwrap().paused = paused;
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn is_paused(&self,id:UpdateId) -> Option<bool> {
+        self.get(id).map(|d| d.state.lock().unwrap().paused)
     }
+
+    pub fn contains(&self,id:UpdateId) -> bool {
+        self.get(id).is_some()
+    }
+
+    pub fn clear(&mut self) {
+        for desc in self.updates.drain(..) {
+            desc.cancel();
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.updates.is_empty()
+    }
+
+    fn get(&self,id:UpdateId) -> Option<&Arc<UpdateDesc>> {
+        self.updates.iter().find(|d| d.id == id)
+    }
+}
+
+#[derive(Default)]
+struct UpdateState {
+    frames:u32,
+    elapsed:f32,
+    paused:bool,
+    finished:bool,
+    cancelled:bool
 }
 
 pub struct UpdateDesc {
+    id:UpdateId,
     typ:UpdateType,
     call:Option<Box<dyn UpdateCallBack>>,
-    add_frame:Mutex<u32>,
-    add_time:Mutex<f32>
+    state:Mutex<UpdateState>
 }
 
 impl UpdateDesc {
-    pub fn update(&self,dt:f32) {
-        match self.typ {
-            UpdateType::Frame(frame) => {
-                let mut mutframe = self.add_frame.lock().unwrap();
-                if *mutframe >= frame {
-                    if let Some(f) = self.call.as_ref() {
-                        f.run();
-                    }
-                    *mutframe = 1;
-                } else {
-                    *mutframe += 1;
-                }
-            },
-            UpdateType::Time(time) => {
-                let mut muttime = self.add_time.lock().unwrap();
-                if *muttime >= time {
-                    if let Some(f) = self.call.as_ref() {
-                        f.run();
-                    }
-                    *muttime = dt;
-                } else {
-                    *muttime += dt;
-                }
-            },
+    pub fn new(typ:UpdateType) -> Self {
+        UpdateDesc {
+            id:UpdateId(NEXT_UPDATE_ID.fetch_add(1,Ordering::Relaxed)),
+            typ,
+            call:None,
+            state:Mutex::new(UpdateState::default())
         }
     }
 
     pub fn from_frame(num:u32) -> Self {
-        UpdateDesc {typ:UpdateType::Frame(num),call:None,add_frame:Mutex::new(0),add_time:Mutex::new(0f32) }
+        UpdateDesc::new(UpdateType::Frame(num))
     }
 
     pub fn from_time(num:f32) -> Self {
-        UpdateDesc {typ:UpdateType::Time(num),call:None ,add_frame:Mutex::new(0),add_time:Mutex::new(0f32)}
+        UpdateDesc::new(UpdateType::Time(num))
+    }
+
+    pub fn once(secs:f32) -> Self {
+        UpdateDesc::new(UpdateType::Once(secs))
     }
 
     pub fn set_call(&mut self,call:Box<dyn UpdateCallBack>) {
         self.call = Some(call);
     }
+
+    pub fn with_call<F>(mut self,f:F) -> Self where F:Fn(Entity,f32,&mut World) + Send + Sync + 'static {
+        self.call = Some(Box::new(f));
+        self
+    }
+
+    pub fn id(&self) -> UpdateId {
+        self.id
+    }
+
+    pub fn typ(&self) -> UpdateType {
+        self.typ
+    }
+
+    pub fn cancel(&self) {
+        self.state.lock().unwrap().cancelled = true;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.finished || state.cancelled
+    }
+
+    //返回Some(累计时间)表示本帧需要触发
+    fn tick(&self,dt:f32) -> Option<f32> {
+        let mut state = self.state.lock().unwrap();
+        if state.paused || state.finished || state.cancelled {
+            return None;
+        }
+        state.frames += 1;
+        state.elapsed += dt;
+        let fire = match self.typ {
+            UpdateType::Frame(frame) => state.frames >= frame,
+            UpdateType::Time(time) => state.elapsed >= time,
+            UpdateType::Once(time) => {
+                let fire = state.elapsed >= time;
+                state.finished = fire;
+                fire
+            }
+        };
+        if fire {
+            let elapsed = state.elapsed;
+            state.frames = 0;
+            state.elapsed = 0f32;
+            Some(elapsed)
+        } else {
+            None
+        }
+    }
+
+    fn run(&self,entity:Entity,dt:f32,world:&mut World) {
+        if self.state.lock().unwrap().cancelled {
+            return;
+        }
+        if let Some(f) = self.call.as_ref() {
+            f.run(entity,dt,world);
+        }
+    }
 }
 
 impl Component for Update {
@@ -79,7 +189,7 @@ impl Component for Update {
 
 
 pub struct UpdateSystem {
-    update_desc:Vec<Arc<UpdateDesc>>
+    update_desc:Vec<(Entity,Arc<UpdateDesc>,f32)>
 }
 
 impl Default for UpdateSystem {
@@ -94,17 +204,63 @@ impl UpdateSystem {
     pub fn update(&mut self,dt:f32,world:&mut World) {
        self.update_desc.clear();
        {
-           let mut storage = world.write_storage::<Update>();
-           let joined = (&mut storage).join();
-           for update in joined {
+           let entities = world.entities();
+           let storage = world.read_storage::<Update>();
+           for (entity,update) in (&entities,&storage).join() {
                for desc in update.updates.iter() {
-                   self.update_desc.push(desc.clone());
-               } 
-             
+                   if let Some(elapsed) = desc.tick(dt) {
+                       self.update_desc.push((entity,desc.clone(),elapsed));
+                   }
+               }
            }
-        };
-        for desc in self.update_desc.iter() {
-            desc.update(dt);
+       };
+       let mut has_finished = false;
+       for (entity,desc,elapsed) in self.update_desc.drain(..) {
+           desc.run(entity,elapsed,world);
+           has_finished |= desc.is_finished();
+       }
+       if has_finished {
+           let mut storage = world.write_storage::<Update>();
+           for update in (&mut storage).join() {
+               update.updates.retain(|d| !d.is_finished());
+           }
+       }
+    }
+}
+
+#[test]
+fn test_update_once_pause_cancel() {
+    use std::sync::atomic::AtomicU32;
+    use specs::Builder;
+    let mut world = World::new();
+    world.register::<Update>();
+    let counter = Arc::new(AtomicU32::new(0));
+    let (c0,c1) = (counter.clone(),counter.clone());
+    let mut update = Update::default();
+    let once_id = update.insert(UpdateDesc::once(0.25).with_call(move |_,dt,_:&mut World| {
+        assert!(dt >= 0.25);
+        c0.fetch_add(1,Ordering::SeqCst);
+    }));
+    let frame_id = update.insert(UpdateDesc::from_frame(2).with_call(move |e,_,world:&mut World| {
+        c1.fetch_add(100,Ordering::SeqCst);
+        //回调中可以修改自身所在实体的组件
+        if let Some(u) = world.write_storage::<Update>().get_mut(e) {
+            u.set_paused(once_id,false);
         }
+    }));
+    let entity = world.create_entity().with(update).build();
+    let mut system = UpdateSystem::default();
+    for _ in 0..4 {
+        system.update(0.1,&mut world);
     }
-}
\ No newline at end of file
+    assert_eq!(counter.load(Ordering::SeqCst),201);
+    assert!(!world.read_storage::<Update>().get(entity).unwrap().contains(once_id));
+
+    world.write_storage::<Update>().get_mut(entity).unwrap().set_paused(frame_id,true);
+    system.update(0.1,&mut world);
+    system.update(0.1,&mut world);
+    assert_eq!(counter.load(Ordering::SeqCst),201);
+
+    assert!(world.write_storage::<Update>().get_mut(entity).unwrap().remove(frame_id));
+    assert!(world.read_storage::<Update>().get(entity).unwrap().is_empty());
+}
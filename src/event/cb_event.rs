@@ -1,13 +1,138 @@
 use specs::{Component,DenseVecStorage,World,WorldExt,Join,Entity,ReadStorage,WriteStorage};
 use crate::common::{Rect2D,Transform,Hidden,HiddenPropagate,TreeNode};
-use crate::event::{GameEvent,EventNode,NodeEvent,EventNodeState};
+use crate::event::{GameEvent,GameEventType,EventNode,NodeEvent,EventNodeState,FocusManager,CurrentEvent,DragTracker,PointerId,RecognizerSettings};
+use crate::event::recognizer::{PointerRecognizer};
+use crate::core::{Time};
 use std::sync::{Arc};
 
 #[derive(Default)]
-pub struct CABEventHandle;
+pub struct CABEventHandle {
+    recognizer:PointerRecognizer
+}
+
+fn now_seconds(world:&World) -> f64 {
+    world.try_fetch::<Time>().map(|t| t.absolute_real_time_seconds()).unwrap_or(0f64)
+}
 
 impl CABEventHandle {
     pub fn process(&mut self,ev:&GameEvent,world:&mut World) {
+       if let GameEvent::TouchStart(pointer,_) = ev {
+           if let Some(mut tracker) = world.try_fetch_mut::<DragTracker>() {
+               if tracker.pointer().is_none() || tracker.pointer() == Some(*pointer) {
+                   tracker.clear();
+               }
+           }
+       }
+       self.process_recognizer(ev,world);
+       let events = self.collect(ev,world);
+       //TouchEnd在同一位置派发Click(以及DoubleClick),都在执行回调前收集,拖拽结束或长按后不派发Click
+       let mut follow_events = Vec::new();
+       if let GameEvent::TouchEnd(pointer,pos) = ev {
+           let is_dragging = world.try_fetch::<DragTracker>().map(|t| t.is_dragging() && t.pointer() == Some(*pointer)).unwrap_or(false);
+           let is_long_pressed = self.recognizer.release(*pointer);
+           if !is_dragging && !is_long_pressed {
+               let click = GameEvent::Click(*pointer,*pos);
+               let click_events = self.collect(&click,world);
+               follow_events.push((click,click_events));
+               let is_double = {
+                   let settings = world.fetch::<RecognizerSettings>();
+                   self.recognizer.click(*pos,now_seconds(world),&settings)
+               };
+               if is_double {
+                   let double = GameEvent::DoubleClick(*pointer,*pos);
+                   let double_events = self.collect(&double,world);
+                   follow_events.push((double,double_events));
+               }
+           }
+           self.release_pointer(*pointer,world);
+       }
+       CurrentEvent::dispatch(world,ev,events);
+       for (follow,follow_events) in follow_events {
+           CurrentEvent::dispatch(world,&follow,follow_events);
+       }
+       self.process_drag(ev,world);
+    }
+
+    //驱动LongPress和HoverStay,每帧调用一次
+    pub fn update(&mut self,world:&mut World) {
+        if !world.has_value::<RecognizerSettings>() {
+            return;
+        }
+        let timed_evs = {
+            let settings = world.fetch::<RecognizerSettings>();
+            self.recognizer.update(now_seconds(world),&settings)
+        };
+        for ev in timed_evs {
+            let events = self.collect(&ev,world);
+            if let (GameEvent::LongPress(pointer,_),false) = (&ev,events.is_empty()) {
+                self.recognizer.set_long_press_handled(*pointer);
+            }
+            CurrentEvent::dispatch(world,&ev,events);
+        }
+    }
+
+    fn process_recognizer(&mut self,ev:&GameEvent,world:&World) {
+        if !world.has_value::<RecognizerSettings>() {
+            return;
+        }
+        let now = now_seconds(world);
+        match ev {
+            GameEvent::TouchStart(pointer,pos) => self.recognizer.press(*pointer,*pos,now),
+            GameEvent::Move(pointer,pos) => self.recognizer.moved(*pointer,*pos,now,&world.fetch::<RecognizerSettings>()),
+            _ => {}
+        }
+    }
+
+    //指针被系统取消(例如触摸被打断),不派发Click和Drop
+    pub fn cancel_pointer(&mut self,pointer:PointerId,world:&mut World) {
+        self.recognizer.cancel(pointer);
+        self.release_pointer(pointer,world);
+        if let Some(mut tracker) = world.try_fetch_mut::<DragTracker>() {
+            if tracker.pointer() == Some(pointer) {
+                tracker.clear();
+            }
+        }
+    }
+
+    fn release_pointer(&mut self,pointer:PointerId,world:&World) {
+        for ev_node in (&mut world.write_storage::<EventNode>()).join() {
+            ev_node.release(pointer);
+        }
+    }
+
+    fn process_drag(&mut self,ev:&GameEvent,world:&mut World) {
+        if !world.has_value::<DragTracker>() {
+            return;
+        }
+        match ev {
+            GameEvent::TouchStart(pointer,pos) => world.write_resource::<DragTracker>().press(*pointer,*pos),
+            GameEvent::Move(pointer,pos) => {
+                let drag_evs = world.write_resource::<DragTracker>().moved(*pointer,*pos);
+                for drag_ev in drag_evs {
+                    DragTracker::dispatch_to_source(world,&drag_ev);
+                }
+            },
+            GameEvent::TouchEnd(pointer,pos) => {
+                let (source,is_dragging) = {
+                    let tracker = world.read_resource::<DragTracker>();
+                    if tracker.pointer() != Some(*pointer) {
+                        return;
+                    }
+                    (tracker.source(),tracker.is_dragging())
+                };
+                if let (Some(source),true) = (source,is_dragging) {
+                    let drop = GameEvent::Drop(*pos,source);
+                    let drop_events = self.collect(&drop,world);
+                    CurrentEvent::dispatch(world,&drop,drop_events);
+                    DragTracker::dispatch_to_source(world,&GameEvent::DragEnd(*pos));
+                }
+                world.write_resource::<DragTracker>().clear();
+            },
+            _ => {}
+        }
+    }
+
+    fn collect(&mut self,ev:&GameEvent,world:&World) -> Vec<(Arc<NodeEvent>,Entity)> {
        let mut events:Vec<(Arc<NodeEvent>,Entity)> = Vec::new();
        {
             let mut roots = world.write_storage::<CABEventRoot>();
@@ -22,9 +147,7 @@ impl CABEventHandle {
                 root.process(world,e,ev,t,rect,&tree_nodes,&trans,&rects,&mut ev_node,&mut events);   
             }
        };
-       for (ev,eid) in events {
-           ev(eid,world);
-       }
+       events
     }
     
     pub fn process_no_hit(&mut self,ev:&GameEvent,world:&mut World) {
@@ -41,7 +164,7 @@ impl CABEventHandle {
                 if rect.test(t, pos) == false {
                   if ev_node.node_state & EventNodeState::MouseIn.value() > 0 {
                      ev_node.node_state &= !(EventNodeState::MouseIn.value());
-                     let ev_list = ev_node.get_dispatch_event(false,ev.to_type());
+                     let ev_list = ev_node.get_dispatch_event(false,ev);
                      for ev in ev_list {
                         events.push((ev,e));
                      }
@@ -49,9 +172,7 @@ impl CABEventHandle {
                 }
             }
        };
-       for (ev,eid) in events {
-         ev(eid,world);
-       }
+       CurrentEvent::dispatch(world,ev,events);
     }
 }
 
@@ -71,13 +192,6 @@ impl CABEventRoot {
                    r_storage:&ReadStorage<Rect2D>,ev_storage:&mut WriteStorage<EventNode>,events:&mut Vec<(Arc<NodeEvent>,Entity)>) {
         
         self.process_node(world,e, ev,trans,rect,tree_nodes,t_storage,r_storage,ev_storage,events);
-        
-        if let GameEvent::TouchEnd(pos) = ev {
-            self.process_node(world,e, &GameEvent::Click(*pos),trans,rect,tree_nodes,t_storage,r_storage,ev_storage,events);
-            for ev_node in ev_storage.join() {
-                ev_node.node_state &= !(EventNodeState::MouseDown.value());
-            }
-        }
     }
 
   
@@ -104,10 +218,19 @@ impl CABEventRoot {
         if let Some(ev_node) = may_ev_node {
             is_through = ev_node.is_through;
             if !is_hide && !ev_node.is_through {
-                let evlist:Vec<Arc<NodeEvent>> = ev_node.get_dispatch_event(true,ev.to_type());
+                let evlist:Vec<Arc<NodeEvent>> = ev_node.get_dispatch_event(true,ev);
                 for ev in evlist {
                     events.push((ev,e));
                 }
+                //先序遍历,最深的可获得焦点节点最后请求,最终获得焦点
+                if ev_node.is_focusable() && ev.to_type() == GameEventType::TouchStart {
+                    let request:NodeEvent = Box::new(|e,w| w.write_resource::<FocusManager>().request_focus(Some(e)));
+                    events.push((Arc::new(request),e));
+                }
+                if ev_node.is_draggable() && ev.to_type() == GameEventType::TouchStart {
+                    let candidate:NodeEvent = Box::new(|e,w| w.write_resource::<DragTracker>().set_candidate(e));
+                    events.push((Arc::new(candidate),e));
+                }
                 if ev_node.is_stop_capture {
                     return true;
                 }
@@ -147,7 +270,7 @@ impl CABEventRoot {
         let mut ev_join = ev_storage.join();
         if let Some(ev_node) = ev_join.get_unchecked(e.id()) {
             if is_hide == false {
-                let evlist = ev_node.get_dispatch_event(false,ev.to_type());
+                let evlist = ev_node.get_dispatch_event(false,ev);
                 for ev in evlist {
                     events.push((ev,e));
                 }
@@ -160,7 +283,7 @@ impl CABEventRoot {
         while let Some(parent) = may_parent {
             if let Some(ev_node) = ev_join.get_unchecked(parent.id()) {
                 if is_hide == false {
-                    let evlist = ev_node.get_dispatch_event(false,ev.to_type());
+                    let evlist = ev_node.get_dispatch_event(false,ev);
                     for ev in evlist {
                         events.push((ev,parent));
                     }
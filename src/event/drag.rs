@@ -0,0 +1,124 @@
+use specs::{World,WorldExt,Entity};
+use crate::event::{GameEvent,EventNode,CurrentEvent,PointerId};
+
+const DEFAULT_DRAG_THRESHOLD:f64 = 4f64;
+
+//按下时命中的可拖拽节点,移动超过threshold后开始拖拽,之后Drag一直派发给该节点直到松开
+pub struct DragTracker {
+    pub threshold:f64,
+    candidate:Option<Entity>,
+    source:Option<Entity>,
+    //同一时间只跟踪一个拖拽指针,其他手指不会打断它
+    pointer:Option<PointerId>,
+    start:(f64,f64),
+    last:(f64,f64),
+    dragging:bool
+}
+
+impl Default for DragTracker {
+    fn default() -> Self {
+        DragTracker {
+            threshold:DEFAULT_DRAG_THRESHOLD,
+            candidate:None,
+            source:None,
+            pointer:None,
+            start:(0f64,0f64),
+            last:(0f64,0f64),
+            dragging:false
+        }
+    }
+}
+
+impl DragTracker {
+    pub fn is_dragging(&self) -> bool {
+        self.dragging
+    }
+
+    pub fn source(&self) -> Option<Entity> {
+        self.source
+    }
+
+    pub fn pointer(&self) -> Option<PointerId> {
+        self.pointer
+    }
+
+    //相对按下位置的总位移
+    pub fn offset(&self) -> (f64,f64) {
+        (self.last.0 - self.start.0,self.last.1 - self.start.1)
+    }
+
+    pub(crate) fn set_candidate(&mut self,e:Entity) {
+        self.candidate = Some(e);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.candidate = None;
+        self.source = None;
+        self.pointer = None;
+        self.dragging = false;
+    }
+
+    pub(crate) fn press(&mut self,pointer:PointerId,pos:(f64,f64)) {
+        if self.pointer.is_some() {
+            return;
+        }
+        self.source = self.candidate.take();
+        self.pointer = self.source.map(|_| pointer);
+        self.start = pos;
+        self.last = pos;
+        self.dragging = false;
+    }
+
+    //返回需要派发给拖拽源的事件
+    pub(crate) fn moved(&mut self,pointer:PointerId,pos:(f64,f64)) -> Vec<GameEvent> {
+        let mut evs = Vec::new();
+        if self.source.is_none() || self.pointer != Some(pointer) {
+            return evs;
+        }
+        if !self.dragging {
+            let (dx,dy) = (pos.0 - self.start.0,pos.1 - self.start.1);
+            if dx * dx + dy * dy < self.threshold * self.threshold {
+                return evs;
+            }
+            self.dragging = true;
+            evs.push(GameEvent::DragStart(self.start));
+        }
+        evs.push(GameEvent::Drag(pos,(pos.0 - self.last.0,pos.1 - self.last.1)));
+        self.last = pos;
+        evs
+    }
+
+    pub(crate) fn dispatch_to_source(world:&mut World,ev:&GameEvent) {
+        let source = match world.try_fetch::<DragTracker>().and_then(|t| t.source) {
+            Some(e) if world.is_alive(e) => e,
+            _ => return
+        };
+        let events = world.write_storage::<EventNode>().get_mut(source)
+                          .map(|n| n.get_self_events(ev).into_iter().map(|call| (call,source)).collect())
+                          .unwrap_or_default();
+        CurrentEvent::dispatch(world,ev,events);
+    }
+}
+
+#[test]
+fn test_drag_threshold() {
+    use specs::Builder;
+    let mut world = World::new();
+    let e = world.create_entity().build();
+    let mut tracker = DragTracker::default();
+    tracker.set_candidate(e);
+    tracker.press(PointerId::Mouse,(0f64,0f64));
+    assert!(tracker.moved(PointerId::Mouse,(2f64,2f64)).is_empty());
+    assert!(!tracker.is_dragging());
+    let evs = tracker.moved(PointerId::Mouse,(5f64,0f64));
+    assert!(matches!(evs[0],GameEvent::DragStart((x,_)) if x == 0f64));
+    assert!(matches!(evs[1],GameEvent::Drag(_,(dx,_)) if dx == 5f64));
+    assert!(matches!(tracker.moved(PointerId::Mouse,(6f64,1f64))[0],GameEvent::Drag(_,(dx,dy)) if dx == 1f64 && dy == 1f64));
+    assert_eq!(tracker.offset(),(6f64,1f64));
+    assert_eq!(tracker.source(),Some(e));
+
+    assert!(tracker.moved(PointerId::Touch(1),(50f64,0f64)).is_empty());
+    tracker.clear();
+    tracker.press(PointerId::Mouse,(0f64,0f64));
+    assert!(tracker.moved(PointerId::Mouse,(50f64,0f64)).is_empty());
+}
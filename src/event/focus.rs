@@ -0,0 +1,175 @@
+use specs::{World,WorldExt,Entity};
+use crate::common::{Tree,TreeNode,Hidden,HiddenPropagate};
+use crate::event::{GameEvent,EventNode,NodeEvent,CurrentEvent};
+use std::sync::{Arc};
+
+//记录当前获得键盘焦点的实体,KeyBoard/RecvChar会先派发给焦点节点再沿TreeNode向上冒泡
+#[derive(Default)]
+pub struct FocusManager {
+    focused:Option<Entity>,
+    //在只能拿到&World的事件回调中请求切换焦点,由GameEventHandle在本轮事件结束后处理
+    request:Option<Option<Entity>>
+}
+
+impl FocusManager {
+    pub fn focused(&self) -> Option<Entity> {
+        self.focused
+    }
+
+    pub fn is_focused(&self,e:Entity) -> bool {
+        self.focused == Some(e)
+    }
+
+    pub fn request_focus(&mut self,e:Option<Entity>) {
+        self.request = Some(e);
+    }
+
+    pub fn take_request(&mut self) -> Option<Option<Entity>> {
+        self.request.take()
+    }
+
+    //返回焦点是否发生变化,旧焦点收到FocusOut,新焦点收到FocusIn
+    pub fn set_focus(world:&mut World,e:Option<Entity>) -> bool {
+        let e = e.filter(|e| world.is_alive(*e));
+        let old = {
+            let mut focus = world.write_resource::<FocusManager>();
+            if focus.focused == e {
+                return false;
+            }
+            std::mem::replace(&mut focus.focused,e)
+        };
+        let targets = old.filter(|o| world.is_alive(*o)).map(|o| (o,GameEvent::FocusOut)).into_iter()
+                         .chain(e.map(|n| (n,GameEvent::FocusIn)));
+        for (entity,ev) in targets {
+            let mut events:Vec<(Arc<NodeEvent>,Entity)> = Vec::new();
+            if let Some(ev_node) = world.write_storage::<EventNode>().get_mut(entity) {
+                events.extend(ev_node.get_self_events(&ev).into_iter().map(|call| (call,entity)));
+            }
+            CurrentEvent::dispatch(world,&ev,events);
+        }
+        true
+    }
+
+    //按tab_index从小到大(0排在所有正数之后),相同时按树的先序遍历顺序
+    pub fn tab_order(world:&World) -> Vec<Entity> {
+        let tree = match world.try_fetch::<Tree>() {
+            Some(tree) => tree,
+            None => return Vec::new()
+        };
+        let tree_nodes = world.read_storage::<TreeNode>();
+        let ev_nodes = world.read_storage::<EventNode>();
+        let hiddens = world.read_storage::<Hidden>();
+        let hide_props = world.read_storage::<HiddenPropagate>();
+        let mut order:Vec<(Entity,i32)> = Vec::new();
+        let mut stack:Vec<Entity> = tree.roots().iter().rev().cloned().collect();
+        while let Some(e) = stack.pop() {
+            if hide_props.contains(e) {
+                continue;
+            }
+            if let Some(tab_index) = ev_nodes.get(e).and_then(|n| n.tab_index) {
+                if tab_index >= 0 && !hiddens.contains(e) {
+                    order.push((e,tab_index));
+                }
+            }
+            if let Some(node) = tree_nodes.get(e) {
+                stack.extend(node.children.iter().rev());
+            }
+        }
+        order.sort_by_key(|(_,idx)| if *idx == 0 { i32::MAX } else { *idx });
+        order.into_iter().map(|(e,_)| e).collect()
+    }
+
+    //Tab/Shift-Tab,到达末尾后回绕
+    pub fn focus_next(world:&mut World,forward:bool) -> Option<Entity> {
+        let order = FocusManager::tab_order(world);
+        if order.is_empty() {
+            return None;
+        }
+        let cur = world.read_resource::<FocusManager>().focused.and_then(|f| order.iter().position(|e| *e == f));
+        let len = order.len();
+        let next = match (cur,forward) {
+            (Some(idx),true) => (idx + 1) % len,
+            (Some(idx),false) => (idx + len - 1) % len,
+            (None,true) => 0,
+            (None,false) => len - 1
+        };
+        FocusManager::set_focus(world,Some(order[next]));
+        Some(order[next])
+    }
+
+    //派发给焦点节点,再沿父节点冒泡,返回是否有焦点节点
+    pub fn route_event(world:&mut World,ev:&GameEvent) -> bool {
+        let focused = match world.read_resource::<FocusManager>().focused {
+            Some(e) if world.is_alive(e) => e,
+            _ => return false
+        };
+        let mut events:Vec<(Arc<NodeEvent>,Entity)> = Vec::new();
+        {
+            let tree_nodes = world.read_storage::<TreeNode>();
+            let hiddens = world.read_storage::<Hidden>();
+            let mut ev_nodes = world.write_storage::<EventNode>();
+            let mut may_node = Some(focused);
+            while let Some(e) = may_node {
+                if let Some(ev_node) = ev_nodes.get_mut(e) {
+                    if !hiddens.contains(e) {
+                        for call in ev_node.get_dispatch_event(false,ev) {
+                            events.push((call,e));
+                        }
+                    }
+                    if ev_node.is_stop_bubble {
+                        break;
+                    }
+                }
+                may_node = tree_nodes.get(e).and_then(|t| t.parent);
+            }
+        };
+        CurrentEvent::dispatch(world,ev,events);
+        true
+    }
+}
+
+#[test]
+fn test_focus_tab_and_route() {
+    use specs::Builder;
+    use crate::event::{Modifiers,GameEventType,KeyCode};
+    use std::sync::atomic::{AtomicU32,Ordering};
+    let mut world = World::new();
+    world.register::<TreeNode>();
+    world.register::<EventNode>();
+    world.register::<Hidden>();
+    world.register::<HiddenPropagate>();
+    world.insert(Tree::default());
+    world.insert(FocusManager::default());
+    world.insert(CurrentEvent::default());
+    let root = world.create_entity().build();
+    Tree::add(&mut world,root,None);
+    let mut ids = Vec::new();
+    for tab_index in [Some(0),None,Some(1),Some(0)].iter() {
+        let e = world.create_entity().with(EventNode {tab_index:*tab_index,..Default::default() }).build();
+        Tree::add(&mut world,e,Some(root));
+        ids.push(e);
+    }
+    assert_eq!(FocusManager::tab_order(&world),vec![ids[2],ids[0],ids[3]]);
+
+    let keys = Arc::new(AtomicU32::new(0));
+    let focus_outs = Arc::new(AtomicU32::new(0));
+    let (k0,f0) = (keys.clone(),focus_outs.clone());
+    let mut root_node = EventNode::default();
+    root_node.register(false,GameEventType::KeyBoard,move |_,w| {
+        if let Some(GameEvent::KeyBoard(KeyCode::A,_,_)) = w.read_resource::<CurrentEvent>().get() {
+            k0.fetch_add(1,Ordering::SeqCst);
+        }
+    });
+    world.write_storage::<EventNode>().insert(root,root_node).unwrap();
+    world.write_storage::<EventNode>().get_mut(ids[2]).unwrap().register(false,GameEventType::FocusOut,move |_,_| {
+        f0.fetch_add(1,Ordering::SeqCst);
+    });
+
+    assert!(!FocusManager::route_event(&mut world,&GameEvent::KeyBoard(KeyCode::A,true,Modifiers::CTRL)));
+    assert_eq!(FocusManager::focus_next(&mut world,true),Some(ids[2]));
+    assert!(FocusManager::route_event(&mut world,&GameEvent::KeyBoard(KeyCode::A,true,Modifiers::CTRL)));
+    assert_eq!(keys.load(Ordering::SeqCst),1);
+    assert_eq!(FocusManager::focus_next(&mut world,false),Some(ids[3]));
+    assert_eq!(focus_outs.load(Ordering::SeqCst),1);
+    assert!(world.read_resource::<CurrentEvent>().get().is_none());
+}
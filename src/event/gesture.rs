@@ -0,0 +1,52 @@
+use crate::event::{GameEvent};
+
+//跟踪当前按下的手指,恰好两指时把移动识别为Pinch和Pan
+#[derive(Default)]
+pub struct TouchGesture {
+    touches:Vec<(u64,(f64,f64))>
+}
+
+impl TouchGesture {
+    pub fn touch_count(&self) -> usize {
+        self.touches.len()
+    }
+
+    pub fn touch_start(&mut self,id:u64,pos:(f64,f64)) {
+        self.touch_end(id);
+        self.touches.push((id,pos));
+    }
+
+    pub fn touch_end(&mut self,id:u64) {
+        self.touches.retain(|(tid,_)| *tid != id);
+    }
+
+    pub fn touch_move(&mut self,id:u64,pos:(f64,f64)) -> Vec<GameEvent> {
+        let mut evs = Vec::new();
+        let idx = match self.touches.iter().position(|(tid,_)| *tid == id) {
+            Some(idx) => idx,
+            None => return evs
+        };
+        if self.touches.len() != 2 {
+            self.touches[idx].1 = pos;
+            return evs;
+        }
+        let (old_center,old_dist) = self.center_and_distance();
+        self.touches[idx].1 = pos;
+        let (center,dist) = self.center_and_distance();
+        if old_dist > 0f64 && dist != old_dist {
+            evs.push(GameEvent::Pinch(center,dist / old_dist));
+        }
+        let delta = (center.0 - old_center.0,center.1 - old_center.1);
+        if delta != (0f64,0f64) {
+            evs.push(GameEvent::Pan(center,delta));
+        }
+        evs
+    }
+
+    fn center_and_distance(&self) -> ((f64,f64),f64) {
+        let (a,b) = (self.touches[0].1,self.touches[1].1);
+        let center = ((a.0 + b.0) * 0.5f64,(a.1 + b.1) * 0.5f64);
+        let (dx,dy) = (b.0 - a.0,b.1 - a.1);
+        (center,(dx * dx + dy * dy).sqrt())
+    }
+}
@@ -0,0 +1,286 @@
+use crate::event::{KeyCode,MouseButton,Modifiers};
+use crate::assets::{AssetLoadError,AssetStorage,Handle};
+use std::collections::{HashMap,HashSet};
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum InputSource {
+    Key(KeyCode),
+    Mouse(MouseButton)
+}
+
+//按键加上需要同时按住的修饰键,字符串形式为"Ctrl+Shift+S","Mouse:Right"
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub struct Binding {
+    pub source:InputSource,
+    pub modifiers:Modifiers
+}
+
+impl Binding {
+    pub fn key(code:KeyCode) -> Self {
+        Binding {source:InputSource::Key(code),modifiers:Modifiers::empty() }
+    }
+
+    pub fn mouse(button:MouseButton) -> Self {
+        Binding {source:InputSource::Mouse(button),modifiers:Modifiers::empty() }
+    }
+
+    pub fn with_modifiers(mut self,modifiers:Modifiers) -> Self {
+        self.modifiers = modifiers;
+        self
+    }
+
+    pub fn parse(s:&str) -> Option<Binding> {
+        let mut parts:Vec<&str> = s.split('+').map(|p| p.trim()).collect();
+        let last = parts.pop()?;
+        let mut modifiers = Modifiers::empty();
+        for part in parts {
+            modifiers |= match part {
+                "Shift" => Modifiers::SHIFT,
+                "Ctrl" => Modifiers::CTRL,
+                "Alt" => Modifiers::ALT,
+                "Logo" => Modifiers::LOGO,
+                _ => return None
+            };
+        }
+        let source = match last.strip_prefix("Mouse:") {
+            Some("Left") => InputSource::Mouse(MouseButton::Left),
+            Some("Right") => InputSource::Mouse(MouseButton::Right),
+            Some("Middle") => InputSource::Mouse(MouseButton::Middle),
+            Some(n) => InputSource::Mouse(MouseButton::Other(n.parse().ok()?)),
+            None => InputSource::Key(KeyCode::from_name(last)?)
+        };
+        Some(Binding {source,modifiers })
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct AxisBinding {
+    pub positive:Binding,
+    pub negative:Binding
+}
+
+//InputMap的配置,可以通过Loader从json加载:
+//{"actions":{"jump":["Space","Mouse:Left"]},"axes":{"move_x":[{"positive":"D","negative":"A"}]}}
+#[derive(Debug,Clone,Default)]
+pub struct InputBindings {
+    pub actions:HashMap<String,Vec<Binding>>,
+    pub axes:HashMap<String,Vec<AxisBinding>>
+}
+
+fn parse_binding_field(val:&serde_json::Value,field:&str) -> Result<Binding,AssetLoadError> {
+    let s = val.as_str().ok_or_else(|| AssetLoadError::format(format!("'{}' must be a string",field)))?;
+    Binding::parse(s).ok_or_else(|| AssetLoadError::format(format!("'{}' unknown binding '{}'",field,s)))
+}
+
+impl InputBindings {
+    pub fn from_json(bytes:&[u8]) -> Result<InputBindings,AssetLoadError> {
+        let json:serde_json::Value = serde_json::from_slice(bytes).map_err(|e| AssetLoadError::format(e.to_string()))?;
+        let mut bindings = InputBindings::default();
+        if let Some(actions) = json.get("actions") {
+            let actions = actions.as_object().ok_or_else(|| AssetLoadError::format("'actions' must be an object"))?;
+            for (name,list) in actions {
+                let list = list.as_array().ok_or_else(|| AssetLoadError::format(format!("'actions.{}' must be an array",name)))?;
+                let mut action = Vec::with_capacity(list.len());
+                for (i,val) in list.iter().enumerate() {
+                    action.push(parse_binding_field(val,&format!("actions.{}[{}]",name,i))?);
+                }
+                bindings.actions.insert(name.clone(),action);
+            }
+        }
+        if let Some(axes) = json.get("axes") {
+            let axes = axes.as_object().ok_or_else(|| AssetLoadError::format("'axes' must be an object"))?;
+            for (name,list) in axes {
+                let list = list.as_array().ok_or_else(|| AssetLoadError::format(format!("'axes.{}' must be an array",name)))?;
+                let mut axis = Vec::with_capacity(list.len());
+                for (i,val) in list.iter().enumerate() {
+                    let field = format!("axes.{}[{}]",name,i);
+                    axis.push(AxisBinding {
+                        positive:parse_binding_field(&val["positive"],&format!("{}.positive",field))?,
+                        negative:parse_binding_field(&val["negative"],&format!("{}.negative",field))?
+                    });
+                }
+                bindings.axes.insert(name.clone(),axis);
+            }
+        }
+        Ok(bindings)
+    }
+}
+
+//把按键状态映射为命名的动作和轴,由GameEventHandle::fire_event每帧更新
+#[derive(Default)]
+pub struct InputMap {
+    bindings:InputBindings,
+    asset:Option<(Handle<InputBindings>,Option<u32>)>,
+    keys:HashSet<KeyCode>,
+    buttons:HashSet<MouseButton>,
+    modifiers:Modifiers,
+    down:HashSet<String>,
+    just_pressed:HashSet<String>,
+    just_released:HashSet<String>
+}
+
+impl InputMap {
+    pub fn bindings(&self) -> &InputBindings {
+        &self.bindings
+    }
+
+    pub fn set_bindings(&mut self,bindings:InputBindings) {
+        self.bindings = bindings;
+        self.refresh();
+    }
+
+    //使用加载的配置,资源热重载后会重新应用并覆盖运行时的修改
+    pub fn use_asset(&mut self,handle:Handle<InputBindings>) {
+        self.asset = Some((handle,None));
+    }
+
+    pub fn bind_action(&mut self,name:&str,binding:Binding) {
+        self.bindings.actions.entry(String::from(name)).or_default().push(binding);
+        self.refresh();
+    }
+
+    pub fn set_action(&mut self,name:&str,bindings:Vec<Binding>) {
+        self.bindings.actions.insert(String::from(name),bindings);
+        self.refresh();
+    }
+
+    pub fn unbind_action(&mut self,name:&str) -> Option<Vec<Binding>> {
+        let old = self.bindings.actions.remove(name);
+        self.refresh();
+        old
+    }
+
+    pub fn bind_axis(&mut self,name:&str,binding:AxisBinding) {
+        self.bindings.axes.entry(String::from(name)).or_default().push(binding);
+    }
+
+    pub fn unbind_axis(&mut self,name:&str) -> Option<Vec<AxisBinding>> {
+        self.bindings.axes.remove(name)
+    }
+
+    pub fn is_pressed(&self,action:&str) -> bool {
+        self.down.contains(action)
+    }
+
+    pub fn just_pressed(&self,action:&str) -> bool {
+        self.just_pressed.contains(action)
+    }
+
+    pub fn just_released(&self,action:&str) -> bool {
+        self.just_released.contains(action)
+    }
+
+    //正负方向各自有任意绑定按下时取1,结果在[-1,1]
+    pub fn axis(&self,name:&str) -> f32 {
+        let axes = match self.bindings.axes.get(name) {
+            Some(axes) => axes,
+            None => return 0f32
+        };
+        let positive = axes.iter().any(|a| self.is_active(&a.positive));
+        let negative = axes.iter().any(|a| self.is_active(&a.negative));
+        (positive as i32 - negative as i32) as f32
+    }
+
+    pub fn is_key_down(&self,code:KeyCode) -> bool {
+        self.keys.contains(&code)
+    }
+
+    pub fn is_button_down(&self,button:MouseButton) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+    }
+
+    pub fn on_key(&mut self,code:KeyCode,is_press:bool) {
+        if is_press { self.keys.insert(code); } else { self.keys.remove(&code); }
+        self.refresh();
+    }
+
+    pub fn on_mouse(&mut self,button:MouseButton,is_press:bool) {
+        if is_press { self.buttons.insert(button); } else { self.buttons.remove(&button); }
+        self.refresh();
+    }
+
+    pub fn set_modifiers(&mut self,modifiers:Modifiers) {
+        self.modifiers = modifiers;
+        self.refresh();
+    }
+
+    pub fn sync_asset(&mut self,storage:&AssetStorage<InputBindings>) {
+        let changed = match self.asset.as_mut() {
+            Some((handle,version)) => match storage.get_version(handle) {
+                Some(v) if *version != Some(v) => {
+                    *version = Some(v);
+                    storage.get(handle).cloned()
+                },
+                _ => None
+            },
+            None => None
+        };
+        if let Some(bindings) = changed {
+            self.set_bindings(bindings);
+        }
+    }
+
+    fn is_active(&self,binding:&Binding) -> bool {
+        let down = match binding.source {
+            InputSource::Key(code) => self.keys.contains(&code),
+            InputSource::Mouse(button) => self.buttons.contains(&button)
+        };
+        down && self.modifiers.contains(binding.modifiers)
+    }
+
+    //同一帧内按下又抬起也会同时记录在just_pressed和just_released中
+    fn refresh(&mut self) {
+        let down:HashSet<String> = self.bindings.actions.iter()
+                                       .filter(|(_,list)| list.iter().any(|b| self.is_active(b)))
+                                       .map(|(name,_)| name.clone()).collect();
+        for name in down.difference(&self.down) {
+            self.just_pressed.insert(name.clone());
+        }
+        for name in self.down.difference(&down) {
+            self.just_released.insert(name.clone());
+        }
+        self.down = down;
+    }
+}
+
+#[test]
+fn test_input_map_actions() {
+    let json = br#"{"actions":{"save":["Ctrl+S"],"fire":["Space","Mouse:Left"]},
+                    "axes":{"move_x":[{"positive":"D","negative":"A"},{"positive":"Right","negative":"Left"}]}}"#;
+    let mut map = InputMap::default();
+    map.set_bindings(InputBindings::from_json(json).unwrap());
+
+    map.on_key(KeyCode::S,true);
+    assert!(!map.is_pressed("save"));
+    map.set_modifiers(Modifiers::CTRL);
+    assert!(map.is_pressed("save") && map.just_pressed("save"));
+
+    map.begin_frame();
+    map.on_mouse(MouseButton::Left,true);
+    map.on_mouse(MouseButton::Left,false);
+    assert!(map.just_pressed("fire") && map.just_released("fire") && !map.is_pressed("fire"));
+    assert!(!map.just_pressed("save"));
+
+    map.on_key(KeyCode::D,true);
+    map.on_key(KeyCode::Left,true);
+    assert_eq!(map.axis("move_x"),0f32);
+    map.on_key(KeyCode::Left,false);
+    assert_eq!(map.axis("move_x"),1f32);
+
+    map.set_action("fire",vec![Binding::key(KeyCode::F)]);
+    map.begin_frame();
+    map.on_key(KeyCode::F,true);
+    assert!(map.just_pressed("fire"));
+
+    let err = InputBindings::from_json(br#"{"actions":{"jump":["Space","Hyper+J"]}}"#).err().unwrap();
+    assert_eq!(err.detail(),Some("'actions.jump[1]' unknown binding 'Hyper+J'"));
+}
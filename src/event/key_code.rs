@@ -0,0 +1,58 @@
+use winit::event::{VirtualKeyCode};
+
+macro_rules! key_codes {
+    ($($name:ident => $vk:ident),* $(,)?) => {
+        //引擎自己的按键枚举,名字同时用于InputMap的json配置
+        #[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+        pub enum KeyCode {
+            $($name,)*
+            Unknown
+        }
+
+        impl KeyCode {
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(KeyCode::$name => stringify!($name),)*
+                    KeyCode::Unknown => "Unknown"
+                }
+            }
+
+            pub fn from_name(name:&str) -> Option<KeyCode> {
+                match name {
+                    $(stringify!($name) => Some(KeyCode::$name),)*
+                    _ => None
+                }
+            }
+        }
+
+        impl From<VirtualKeyCode> for KeyCode {
+            fn from(vk:VirtualKeyCode) -> Self {
+                match vk {
+                    $(VirtualKeyCode::$vk => KeyCode::$name,)*
+                    _ => KeyCode::Unknown
+                }
+            }
+        }
+    };
+}
+
+key_codes! {
+    Key0 => Key0, Key1 => Key1, Key2 => Key2, Key3 => Key3, Key4 => Key4,
+    Key5 => Key5, Key6 => Key6, Key7 => Key7, Key8 => Key8, Key9 => Key9,
+    A => A, B => B, C => C, D => D, E => E, F => F, G => G, H => H, I => I, J => J, K => K, L => L, M => M,
+    N => N, O => O, P => P, Q => Q, R => R, S => S, T => T, U => U, V => V, W => W, X => X, Y => Y, Z => Z,
+    F1 => F1, F2 => F2, F3 => F3, F4 => F4, F5 => F5, F6 => F6,
+    F7 => F7, F8 => F8, F9 => F9, F10 => F10, F11 => F11, F12 => F12,
+    Escape => Escape, Tab => Tab, Space => Space, Enter => Return, Backspace => Back,
+    Insert => Insert, Delete => Delete, Home => Home, End => End, PageUp => PageUp, PageDown => PageDown,
+    Left => Left, Right => Right, Up => Up, Down => Down,
+    LShift => LShift, RShift => RShift, LControl => LControl, RControl => RControl,
+    LAlt => LAlt, RAlt => RAlt, LWin => LWin, RWin => RWin,
+    CapsLock => Capital, NumLock => Numlock, ScrollLock => Scroll, PrintScreen => Snapshot, Pause => Pause,
+    Minus => Minus, Equals => Equals, Comma => Comma, Period => Period, Slash => Slash, Backslash => Backslash,
+    Semicolon => Semicolon, Apostrophe => Apostrophe, LBracket => LBracket, RBracket => RBracket, Grave => Grave,
+    Numpad0 => Numpad0, Numpad1 => Numpad1, Numpad2 => Numpad2, Numpad3 => Numpad3, Numpad4 => Numpad4,
+    Numpad5 => Numpad5, Numpad6 => Numpad6, Numpad7 => Numpad7, Numpad8 => Numpad8, Numpad9 => Numpad9,
+    NumpadAdd => NumpadAdd, NumpadSubtract => NumpadSubtract, NumpadMultiply => NumpadMultiply,
+    NumpadDivide => NumpadDivide, NumpadDecimal => NumpadDecimal, NumpadEnter => NumpadEnter,
+}
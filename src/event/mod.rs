@@ -1,20 +1,164 @@
-use winit::{event::{Event,WindowEvent,ElementState}};
+use winit::{event::{Event,WindowEvent,ElementState,ModifiersState,MouseScrollDelta,TouchPhase}};
 use specs::{World,Entity,WorldExt,Component,DenseVecStorage,Join};
 pub mod cb_event;
 pub mod global;
+pub mod focus;
+pub mod drag;
+pub mod gesture;
+pub mod recognizer;
+pub mod key_code;
+pub mod input_map;
+pub mod replay;
 use crate::event::cb_event::{CABEventHandle,CABEventRoot};
+pub use crate::event::focus::{FocusManager};
+pub use crate::event::drag::{DragTracker};
+pub use crate::event::gesture::{TouchGesture};
+pub use crate::event::recognizer::{RecognizerSettings};
+pub use crate::event::key_code::{KeyCode};
+pub use crate::event::input_map::{InputMap,InputBindings,Binding,AxisBinding,InputSource};
+pub use crate::event::replay::{EventRecorder,EventReplayer};
 use std::collections::{HashMap};
+use crate::assets::{AssetStorage};
+use crate::core::{Time};
 use std::sync::{Arc};
+use std::ops::{BitOr,BitOrAssign};
+
+//鼠标和每根手指各自是一个指针
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum PointerId {
+    Mouse,
+    Touch(u64)
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Other(u16)
+}
+
+impl From<winit::event::MouseButton> for MouseButton {
+    fn from(btn:winit::event::MouseButton) -> Self {
+        match btn {
+            winit::event::MouseButton::Left => MouseButton::Left,
+            winit::event::MouseButton::Right => MouseButton::Right,
+            winit::event::MouseButton::Middle => MouseButton::Middle,
+            winit::event::MouseButton::Other(n) => MouseButton::Other(n)
+        }
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum ScrollDelta {
+    //按行滚动,正值为向上/向右
+    Line(f32,f32),
+    //触摸板等设备给出的像素值
+    Pixel(f64,f64)
+}
+
+impl From<MouseScrollDelta> for ScrollDelta {
+    fn from(delta:MouseScrollDelta) -> Self {
+        match delta {
+            MouseScrollDelta::LineDelta(x,y) => ScrollDelta::Line(x,y),
+            MouseScrollDelta::PixelDelta(p) => ScrollDelta::Pixel(p.x,p.y)
+        }
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,Default)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub const SHIFT:Modifiers = Modifiers(0x01);
+    pub const CTRL:Modifiers = Modifiers(0x02);
+    pub const ALT:Modifiers = Modifiers(0x04);
+    pub const LOGO:Modifiers = Modifiers(0x08);
+
+    pub fn empty() -> Self {
+        Modifiers(0)
+    }
+
+    pub fn from_bits(bits:u8) -> Self {
+        Modifiers(bits & 0x0f)
+    }
+
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(&self,other:Modifiers) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn shift(&self) -> bool { self.contains(Modifiers::SHIFT) }
+    pub fn ctrl(&self) -> bool { self.contains(Modifiers::CTRL) }
+    pub fn alt(&self) -> bool { self.contains(Modifiers::ALT) }
+    pub fn logo(&self) -> bool { self.contains(Modifiers::LOGO) }
+}
+
+impl BitOr for Modifiers {
+    type Output = Modifiers;
+    fn bitor(self,rhs:Modifiers) -> Modifiers {
+        Modifiers(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Modifiers {
+    fn bitor_assign(&mut self,rhs:Modifiers) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl From<ModifiersState> for Modifiers {
+    fn from(state:ModifiersState) -> Self {
+        let mut m = Modifiers::empty();
+        if state.shift() { m |= Modifiers::SHIFT; }
+        if state.ctrl() { m |= Modifiers::CTRL; }
+        if state.alt() { m |= Modifiers::ALT; }
+        if state.logo() { m |= Modifiers::LOGO; }
+        m
+    }
+}
+
 #[derive(Debug,Clone)]
 pub enum GameEvent {
-    TouchStart((f64,f64)),
-    TouchEnd((f64,f64)),
-    Click((f64,f64)),
-    Move((f64,f64)),
+    //主键(左键/触摸)按下和抬起,需要区分按键时使用MouseDown/MouseUp
+    TouchStart(PointerId,(f64,f64)),
+    TouchEnd(PointerId,(f64,f64)),
+    Click(PointerId,(f64,f64)),
+    Move(PointerId,(f64,f64)),
     MouseEnter((f64,f64)),
     MouseLeave((f64,f64)),
-    KeyBoard(u32,bool),
-    RecvChar(char)
+    KeyBoard(KeyCode,bool,Modifiers),
+    RecvChar(char),
+    FocusIn,
+    FocusOut,
+    MouseDown(MouseButton,(f64,f64),Modifiers),
+    MouseUp(MouseButton,(f64,f64),Modifiers),
+    Scroll(ScrollDelta,(f64,f64),Modifiers),
+    DragStart((f64,f64)),
+    //当前位置,与上一次Drag的位移
+    Drag((f64,f64),(f64,f64)),
+    DragEnd((f64,f64)),
+    //松开位置,被拖拽的实体
+    Drop((f64,f64),Entity),
+    //两指中心,与上一次相比两指距离的缩放比例
+    Pinch((f64,f64),f64),
+    //两指中心,与上一次相比中心的位移
+    Pan((f64,f64),(f64,f64)),
+    DoubleClick(PointerId,(f64,f64)),
+    //按住超过RecognizerSettings::long_press_time,有节点处理时不再派发随后的Click
+    LongPress(PointerId,(f64,f64)),
+    //鼠标在同一位置停留超过RecognizerSettings::hover_delay
+    HoverStay((f64,f64)),
+    ModifiersChanged(Modifiers),
+    //系统取消了触摸,不会产生Click和Drop
+    TouchCancel(PointerId,(f64,f64))
 }
 
 pub trait GameEventCallBack  :Send + Sync{
@@ -31,6 +175,22 @@ pub enum GameEventType {
     MouseLeave = 5,
     KeyBoard = 6,
     RecvChar = 7,
+    FocusIn = 8,
+    FocusOut = 9,
+    MouseDown = 10,
+    MouseUp = 11,
+    Scroll = 12,
+    DragStart = 13,
+    Drag = 14,
+    DragEnd = 15,
+    Drop = 16,
+    Pinch = 17,
+    Pan = 18,
+    DoubleClick = 19,
+    LongPress = 20,
+    HoverStay = 21,
+    ModifiersChanged = 22,
+    TouchCancel = 23,
 }
 
 impl GameEventType {
@@ -44,6 +204,22 @@ impl GameEventType {
             5 => Some(GameEventType::MouseLeave),
             6 => Some(GameEventType::KeyBoard),
             7 => Some(GameEventType::RecvChar),
+            8 => Some(GameEventType::FocusIn),
+            9 => Some(GameEventType::FocusOut),
+            10 => Some(GameEventType::MouseDown),
+            11 => Some(GameEventType::MouseUp),
+            12 => Some(GameEventType::Scroll),
+            13 => Some(GameEventType::DragStart),
+            14 => Some(GameEventType::Drag),
+            15 => Some(GameEventType::DragEnd),
+            16 => Some(GameEventType::Drop),
+            17 => Some(GameEventType::Pinch),
+            18 => Some(GameEventType::Pan),
+            19 => Some(GameEventType::DoubleClick),
+            20 => Some(GameEventType::LongPress),
+            21 => Some(GameEventType::HoverStay),
+            22 => Some(GameEventType::ModifiersChanged),
+            23 => Some(GameEventType::TouchCancel),
             _ => None
         }
     }
@@ -52,42 +228,126 @@ impl GameEventType {
 impl GameEvent {
     pub fn to_type(&self) -> GameEventType {
         match self {
-            GameEvent::TouchStart(_) => GameEventType::TouchStart,
-            GameEvent::TouchEnd(_) => GameEventType::TouchEnd,
-            GameEvent::Click(_) => GameEventType::Click,
-            GameEvent::Move(_) => GameEventType::MouseMove,
+            GameEvent::TouchStart(_,_) => GameEventType::TouchStart,
+            GameEvent::TouchEnd(_,_) => GameEventType::TouchEnd,
+            GameEvent::Click(_,_) => GameEventType::Click,
+            GameEvent::Move(_,_) => GameEventType::MouseMove,
             GameEvent::MouseEnter(_) => GameEventType::MouseEnter,
             GameEvent::MouseLeave(_) => GameEventType::MouseLeave,
-            GameEvent::KeyBoard(_,_) => GameEventType::KeyBoard,
-            GameEvent::RecvChar(_) => GameEventType::RecvChar
+            GameEvent::KeyBoard(_,_,_) => GameEventType::KeyBoard,
+            GameEvent::RecvChar(_) => GameEventType::RecvChar,
+            GameEvent::FocusIn => GameEventType::FocusIn,
+            GameEvent::FocusOut => GameEventType::FocusOut,
+            GameEvent::MouseDown(_,_,_) => GameEventType::MouseDown,
+            GameEvent::MouseUp(_,_,_) => GameEventType::MouseUp,
+            GameEvent::Scroll(_,_,_) => GameEventType::Scroll,
+            GameEvent::DragStart(_) => GameEventType::DragStart,
+            GameEvent::Drag(_,_) => GameEventType::Drag,
+            GameEvent::DragEnd(_) => GameEventType::DragEnd,
+            GameEvent::Drop(_,_) => GameEventType::Drop,
+            GameEvent::Pinch(_,_) => GameEventType::Pinch,
+            GameEvent::Pan(_,_) => GameEventType::Pan,
+            GameEvent::DoubleClick(_,_) => GameEventType::DoubleClick,
+            GameEvent::LongPress(_,_) => GameEventType::LongPress,
+            GameEvent::HoverStay(_) => GameEventType::HoverStay,
+            GameEvent::ModifiersChanged(_) => GameEventType::ModifiersChanged,
+            GameEvent::TouchCancel(_,_) => GameEventType::TouchCancel
         }
     }
 
     pub fn get_pos(&self) -> &(f64,f64) {
         match self {
-            GameEvent::TouchStart(pos) => pos,
-            GameEvent::TouchEnd(pos) => pos,
-            GameEvent::Click(pos) => pos,
-            GameEvent::Move(pos) => pos,
+            GameEvent::TouchStart(_,pos) => pos,
+            GameEvent::TouchEnd(_,pos) => pos,
+            GameEvent::Click(_,pos) => pos,
+            GameEvent::Move(_,pos) => pos,
             GameEvent::MouseEnter(pos) => pos,
             GameEvent::MouseLeave(pos) => pos,
+            GameEvent::MouseDown(_,pos,_) => pos,
+            GameEvent::MouseUp(_,pos,_) => pos,
+            GameEvent::Scroll(_,pos,_) => pos,
+            GameEvent::DragStart(pos) => pos,
+            GameEvent::Drag(pos,_) => pos,
+            GameEvent::DragEnd(pos) => pos,
+            GameEvent::Drop(pos,_) => pos,
+            GameEvent::Pinch(pos,_) => pos,
+            GameEvent::Pan(pos,_) => pos,
+            GameEvent::DoubleClick(_,pos) => pos,
+            GameEvent::LongPress(_,pos) => pos,
+            GameEvent::HoverStay(pos) => pos,
+            GameEvent::TouchCancel(_,pos) => pos,
             _ => &(0f64,0f64)
         }
     }
 
+    pub fn pointer(&self) -> Option<PointerId> {
+        match self {
+            GameEvent::TouchStart(p,_) => Some(*p),
+            GameEvent::TouchEnd(p,_) => Some(*p),
+            GameEvent::Click(p,_) => Some(*p),
+            GameEvent::Move(p,_) => Some(*p),
+            GameEvent::DoubleClick(p,_) => Some(*p),
+            GameEvent::LongPress(p,_) => Some(*p),
+            GameEvent::TouchCancel(p,_) => Some(*p),
+            _ => None
+        }
+    }
+
+    //事件自身携带的修饰键,指针事件返回empty,需要时用CurrentEvent::modifiers
+    pub fn modifiers(&self) -> Modifiers {
+        match self {
+            GameEvent::KeyBoard(_,_,m) => *m,
+            GameEvent::MouseDown(_,_,m) => *m,
+            GameEvent::MouseUp(_,_,m) => *m,
+            GameEvent::Scroll(_,_,m) => *m,
+            GameEvent::ModifiersChanged(m) => *m,
+            _ => Modifiers::empty()
+        }
+    }
+
 }
 
 type NodeEvent = Box<dyn Fn(Entity,&World) + 'static + Send + Sync>;
 
+//EventNode回调只有(Entity,&World),派发期间可以从这个资源中取到正在派发的事件(按键,滚动量,修饰键等)
+#[derive(Default)]
+pub struct CurrentEvent {
+    ev:Option<GameEvent>,
+    modifiers:Modifiers
+}
+
+impl CurrentEvent {
+    pub fn get(&self) -> Option<&GameEvent> {
+        self.ev.as_ref()
+    }
+
+    //当前按下的修饰键,Click/TouchStart等指针事件本身不带修饰键,从这里取
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    pub(crate) fn dispatch(world:&mut World,ev:&GameEvent,events:Vec<(Arc<NodeEvent>,Entity)>) {
+        if events.is_empty() {
+            return;
+        }
+        if !world.has_value::<CurrentEvent>() {
+            world.insert(CurrentEvent::default());
+        }
+        let old = world.write_resource::<CurrentEvent>().ev.replace(ev.clone());
+        for (call,eid) in events {
+            call(eid,world);
+        }
+        world.write_resource::<CurrentEvent>().ev = old;
+    }
+}
+
 pub enum EventNodeState {
-    MouseDown,
     MouseIn,
 }
 
 impl EventNodeState {
     fn value(self) -> u32 {
         match self {
-            EventNodeState::MouseDown => 0x01,
             EventNodeState::MouseIn => 0x02
         }
     }
@@ -96,14 +356,29 @@ impl EventNodeState {
 #[derive(Default,Clone)]
 pub struct EventNode {
     pub node_state:u32,
+    //在该节点上按下还未抬起的指针,Click只派发给同一指针按下过的节点
+    pressed:Vec<PointerId>,
     capture_event:HashMap<GameEventType,Arc<NodeEvent>>,
     bubble_event:HashMap<GameEventType,Arc<NodeEvent>>,
     is_stop_capture:bool,
     is_stop_bubble:bool,
-    pub is_through:bool
+    pub is_through:bool,
+    //不为None时可以通过点击获得焦点,>=0时参与Tab切换
+    pub tab_index:Option<i32>
 }
 
 impl EventNode {
+    pub fn is_pressed(&self) -> bool {
+        !self.pressed.is_empty()
+    }
+
+    pub fn release(&mut self,pointer:PointerId) {
+        self.pressed.retain(|p| *p != pointer);
+    }
+
+    pub fn is_focusable(&self) -> bool {
+        self.tab_index.is_some()
+    }
 
     pub fn register<F>(&mut self,is_capture:bool,typ:GameEventType,f:F) where F:Fn(Entity,&World) + 'static + Send + Sync  {
         if is_capture {
@@ -113,17 +388,36 @@ impl EventNode {
         };
     }
 
-    pub fn get_dispatch_event(&mut self,is_capture:bool,ev_type:GameEventType) -> Vec<Arc<NodeEvent>> {
+    pub fn has_event(&self,typ:&GameEventType) -> bool {
+        self.capture_event.contains_key(typ) || self.bubble_event.contains_key(typ)
+    }
+
+    //注册了任意拖拽事件的节点按下时会成为拖拽源
+    pub fn is_draggable(&self) -> bool {
+        self.has_event(&GameEventType::DragStart) || self.has_event(&GameEventType::Drag) || self.has_event(&GameEventType::DragEnd)
+    }
+
+    //不经过捕获冒泡,只取节点自身两个阶段的回调
+    pub fn get_self_events(&mut self,ev:&GameEvent) -> Vec<Arc<NodeEvent>> {
+        let mut ret_events = self.get_dispatch_event(true,ev);
+        ret_events.extend(self.get_dispatch_event(false,ev));
+        ret_events
+    }
+
+    pub fn get_dispatch_event(&mut self,is_capture:bool,ev:&GameEvent) -> Vec<Arc<NodeEvent>> {
+        let ev_type = ev.to_type();
         let may_node_ev = self.get_event_by_type(is_capture, &ev_type).map(|a| a.clone());
         let mut ret_events:Vec<Arc<NodeEvent>> = vec![];
-        match ev_type {
-            GameEventType::TouchStart => {
-                self.node_state |= EventNodeState::MouseDown.value();
+        match ev {
+            GameEvent::TouchStart(pointer,_) => {
+                if !self.pressed.contains(pointer) {
+                    self.pressed.push(*pointer);
+                }
                 if let Some(node_ev) = may_node_ev {
                     ret_events.push(node_ev);
                 }
             },
-            GameEventType::MouseEnter => {
+            GameEvent::MouseEnter(_) => {
                 if self.node_state & EventNodeState::MouseIn.value() == 0 {
                     if let Some(node_ev) = may_node_ev {
                         ret_events.push(node_ev);
@@ -133,8 +427,8 @@ impl EventNode {
                     }
                 }
             },
-            GameEventType::Click => {
-                if self.node_state & EventNodeState::MouseDown.value() > 0 {
+            GameEvent::Click(pointer,_) | GameEvent::DoubleClick(pointer,_) | GameEvent::LongPress(pointer,_) => {
+                if self.pressed.contains(pointer) {
                     if let Some(node_ev) = may_node_ev {
                         ret_events.push(node_ev);
                     }
@@ -167,6 +461,9 @@ pub struct GameEventHandle {
     mouse_pos:(f64,f64),
     cab_event_handle:CABEventHandle,
     view_size:(f64,f64),
+    scale_factor:f64,
+    modifiers:Modifiers,
+    gesture:TouchGesture
 }
 
 
@@ -174,78 +471,217 @@ impl GameEventHandle {
     pub fn new() -> Self {
         GameEventHandle {
             mouse_pos: (0f64,0f64),
-            cab_event_handle: CABEventHandle {},
-            view_size:(0f64,0f64)
+            cab_event_handle: CABEventHandle::default(),
+            view_size:(0f64,0f64),
+            scale_factor:1f64,
+            modifiers:Modifiers::empty(),
+            gesture:TouchGesture::default()
         }
     }
 
+    //逻辑像素大小,窗口大小变化时需要重新设置
     pub fn set_view_size(&mut self,size:(f64,f64)) {
         self.view_size = size;
     }
 
+    pub fn set_scale_factor(&mut self,scale_factor:f64) {
+        self.scale_factor = scale_factor;
+    }
+
     pub fn register(world:&mut World) {
         world.register::<CABEventRoot>();
         world.register::<EventNode>();
         world.register::<global::GlobalEventNode>();
+        world.insert(FocusManager::default());
+        world.insert(CurrentEvent::default());
+        world.insert(DragTracker::default());
+        world.insert(RecognizerSettings::default());
+        world.insert(InputMap::default());
     }
 
+    //winit给出的是物理像素坐标
     fn conv_pos(&self,x:f64,y:f64) -> (f64,f64) {
+        let (x,y) = (x / self.scale_factor,y / self.scale_factor);
         (x - self.view_size.0 * 0.5f64,-(y - self.view_size.1 * 0.5f64))
     }
 
     pub fn fire_event(&mut self,events:&Vec<Event<()>>,world:&mut World) {
-        let _a = &self.cab_event_handle;
+        let mut game_events = Vec::new();
         for ev in events.iter() {
-            match  ev {
-                Event::WindowEvent  {event,..} => {
-                    match event {
-                        WindowEvent::Touch(d) => {
-                            dbg!(&d);
-                        },
-                        WindowEvent::MouseInput {state,..} => {
-                           
-                           if *state == ElementState::Pressed {
-                               self.cab_event_handle.process(&GameEvent::TouchStart(self.mouse_pos),world);
-                               for ev in GameEventHandle::get_global_calls(world,GameEventType::TouchStart).iter() {
-                                  let gev = GameEvent::TouchStart(self.mouse_pos);
-                                  ev.run(&gev,world)
-                               }
-                           } else {
-                               self.cab_event_handle.process(&GameEvent::TouchEnd(self.mouse_pos),world);
-                               for ev in GameEventHandle::get_global_calls(world,GameEventType::TouchStart).iter() {
-                                let gev = GameEvent::TouchEnd(self.mouse_pos);
-                                ev.run(&gev,world)
-                             }
-                           }
-
-                           
-                        },
-                        WindowEvent::CursorMoved {position,..} => {
-                            self.mouse_pos = self.conv_pos(position.x as f64, position.y as f64);
-                            self.cab_event_handle.process(&GameEvent::Move(self.mouse_pos) , world);
-                            self.cab_event_handle.process(&GameEvent::MouseEnter(self.mouse_pos) , world);
-                            self.cab_event_handle.process_no_hit(&GameEvent::MouseLeave(self.mouse_pos) , world);
-                        },
-                        WindowEvent::KeyboardInput{input,..} => {
-                            let calls = GameEventHandle::get_global_calls(world, GameEventType::KeyBoard);
-                            for ev in calls.iter() {
-                                let code = input.virtual_keycode.map(|v|v as u32).unwrap_or(0u32);
-                                let game_ev = GameEvent::KeyBoard(code,input.state == ElementState::Pressed);
-                                ev.run(&game_ev,world);
-                            }
-                        },
-                        WindowEvent::ReceivedCharacter(chr) => {
-                            let calls = GameEventHandle::get_global_calls(world, GameEventType::RecvChar);
-                            for ev in calls.iter() {
-                                ev.run(&GameEvent::RecvChar(*chr), world);
-                            }
-                        }
-                        _ => ()
-                    }
-                },
-                _ => ()
+            self.convert_event(ev,&mut game_events);
+        }
+        self.fire_game_events(&game_events,world);
+    }
+
+    //把winit事件转换为输入事件,Click/Drag/FocusIn等派生事件在处理输入时再合成
+    fn convert_event(&mut self,ev:&Event<()>,out:&mut Vec<GameEvent>) {
+        let event = match ev {
+            Event::WindowEvent {event,..} => event,
+            _ => return
+        };
+        match event {
+            WindowEvent::Touch(touch) => {
+                let pointer = PointerId::Touch(touch.id);
+                let pos = self.conv_pos(touch.location.x,touch.location.y);
+                out.push(match touch.phase {
+                    TouchPhase::Started => GameEvent::TouchStart(pointer,pos),
+                    TouchPhase::Moved => GameEvent::Move(pointer,pos),
+                    TouchPhase::Ended => GameEvent::TouchEnd(pointer,pos),
+                    TouchPhase::Cancelled => GameEvent::TouchCancel(pointer,pos)
+                });
+            },
+            WindowEvent::MouseWheel {delta,..} => {
+                out.push(GameEvent::Scroll(ScrollDelta::from(*delta),self.mouse_pos,self.modifiers));
+            },
+            WindowEvent::MouseInput {state,button,..} => {
+                let button = MouseButton::from(*button);
+                let is_press = *state == ElementState::Pressed;
+                out.push(if is_press {
+                    GameEvent::MouseDown(button,self.mouse_pos,self.modifiers)
+                } else {
+                    GameEvent::MouseUp(button,self.mouse_pos,self.modifiers)
+                });
+                if button == MouseButton::Left {
+                    out.push(if is_press { GameEvent::TouchStart(PointerId::Mouse,self.mouse_pos) } else { GameEvent::TouchEnd(PointerId::Mouse,self.mouse_pos) });
+                }
+            },
+            WindowEvent::CursorMoved {position,..} => {
+                self.mouse_pos = self.conv_pos(position.x as f64, position.y as f64);
+                out.push(GameEvent::Move(PointerId::Mouse,self.mouse_pos));
+            },
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = Modifiers::from(*modifiers);
+                out.push(GameEvent::ModifiersChanged(self.modifiers));
+            },
+            WindowEvent::KeyboardInput{input,..} => {
+                let code = input.virtual_keycode.map(KeyCode::from).unwrap_or(KeyCode::Unknown);
+                out.push(GameEvent::KeyBoard(code,input.state == ElementState::Pressed,self.modifiers));
+            },
+            WindowEvent::ReceivedCharacter(chr) => out.push(GameEvent::RecvChar(*chr)),
+            _ => ()
+        }
+    }
+
+    //处理一帧的输入事件,EventReplayer回放时直接调用
+    pub fn fire_game_events(&mut self,events:&[GameEvent],world:&mut World) {
+        if let Some(mut input_map) = world.try_fetch_mut::<InputMap>() {
+            input_map.begin_frame();
+            if let Some(storage) = world.try_fetch::<AssetStorage<InputBindings>>() {
+                input_map.sync_asset(&storage);
             }
         }
+        if let Some(mut recorder) = world.try_fetch_mut::<EventRecorder>() {
+            let frame = world.try_fetch::<Time>().map(|t| t.frame_number()).unwrap_or(0);
+            for ev in events.iter() {
+                recorder.record(frame,ev);
+            }
+        }
+        for ev in events.iter() {
+            self.handle_input(ev,world);
+        }
+        self.cab_event_handle.update(world);
+        let request = world.write_resource::<FocusManager>().take_request();
+        if let Some(target) = request {
+            FocusManager::set_focus(world,target);
+        }
+    }
+
+    fn handle_input(&mut self,ev:&GameEvent,world:&mut World) {
+        match ev {
+            GameEvent::TouchStart(pointer,pos) => {
+                if let PointerId::Touch(id) = pointer {
+                    self.gesture.touch_start(*id,*pos);
+                }
+                self.pointer_down(*pointer,*pos,world);
+            },
+            GameEvent::TouchEnd(pointer,pos) => {
+                if let PointerId::Touch(id) = pointer {
+                    self.gesture.touch_end(*id);
+                }
+                self.pointer_up(*pointer,*pos,world);
+            },
+            GameEvent::TouchCancel(pointer,_) => {
+                if let PointerId::Touch(id) = pointer {
+                    self.gesture.touch_end(*id);
+                }
+                self.cab_event_handle.cancel_pointer(*pointer,world);
+                for call in GameEventHandle::get_global_calls(world,GameEventType::TouchCancel).iter() {
+                    call.run(ev,world)
+                }
+            },
+            GameEvent::Move(PointerId::Mouse,pos) => {
+                self.mouse_pos = *pos;
+                self.cab_event_handle.process(ev,world);
+                self.cab_event_handle.process(&GameEvent::MouseEnter(*pos),world);
+                self.cab_event_handle.process_no_hit(&GameEvent::MouseLeave(*pos),world);
+            },
+            GameEvent::Move(PointerId::Touch(id),pos) => {
+                self.cab_event_handle.process(ev,world);
+                for gesture_ev in self.gesture.touch_move(*id,*pos) {
+                    self.fire_hit_event(&gesture_ev,world);
+                }
+            },
+            GameEvent::MouseDown(button,_,_) | GameEvent::MouseUp(button,_,_) => {
+                if let Some(mut input_map) = world.try_fetch_mut::<InputMap>() {
+                    input_map.on_mouse(*button,ev.to_type() == GameEventType::MouseDown);
+                }
+                self.fire_hit_event(ev,world);
+            },
+            GameEvent::Scroll(_,_,_) => self.fire_hit_event(ev,world),
+            GameEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = *modifiers;
+                world.entry::<CurrentEvent>().or_insert_with(CurrentEvent::default).modifiers = *modifiers;
+                if let Some(mut input_map) = world.try_fetch_mut::<InputMap>() {
+                    input_map.set_modifiers(*modifiers);
+                }
+                for call in GameEventHandle::get_global_calls(world,GameEventType::ModifiersChanged).iter() {
+                    call.run(ev,world);
+                }
+            },
+            GameEvent::KeyBoard(code,is_press,modifiers) => {
+                if let Some(mut input_map) = world.try_fetch_mut::<InputMap>() {
+                    input_map.on_key(*code,*is_press);
+                }
+                FocusManager::route_event(world,ev);
+                for call in GameEventHandle::get_global_calls(world,GameEventType::KeyBoard).iter() {
+                    call.run(ev,world);
+                }
+                if *is_press && *code == KeyCode::Tab {
+                    FocusManager::focus_next(world,!modifiers.shift());
+                }
+            },
+            GameEvent::RecvChar(_) => {
+                FocusManager::route_event(world,ev);
+                for call in GameEventHandle::get_global_calls(world,GameEventType::RecvChar).iter() {
+                    call.run(ev,world);
+                }
+            },
+            _ => ()
+        }
+    }
+
+    fn pointer_down(&mut self,pointer:PointerId,pos:(f64,f64),world:&mut World) {
+        self.cab_event_handle.process(&GameEvent::TouchStart(pointer,pos),world);
+        //点中可获得焦点的节点时会请求焦点,否则失去焦点
+        let target = world.write_resource::<FocusManager>().take_request().unwrap_or(None);
+        FocusManager::set_focus(world,target);
+        let gev = GameEvent::TouchStart(pointer,pos);
+        for ev in GameEventHandle::get_global_calls(world,GameEventType::TouchStart).iter() {
+            ev.run(&gev,world)
+        }
+    }
+
+    fn pointer_up(&mut self,pointer:PointerId,pos:(f64,f64),world:&mut World) {
+        let gev = GameEvent::TouchEnd(pointer,pos);
+        self.fire_hit_event(&gev,world);
+    }
+
+    //按位置命中派发,再派发给全局监听
+    fn fire_hit_event(&mut self,gev:&GameEvent,world:&mut World) {
+        self.cab_event_handle.process(gev,world);
+        for ev in GameEventHandle::get_global_calls(world,gev.to_type()).iter() {
+            ev.run(gev,world)
+        }
     }
 
     fn get_global_calls(world:&World,typ:GameEventType) -> Vec<Arc<Box<dyn GameEventCallBack>>> {
@@ -258,4 +694,116 @@ impl GameEventHandle {
         }
         ret_vec
     }
-}
\ No newline at end of file
+}
+#[test]
+fn test_modifiers_and_pointer_events() {
+    let state = ModifiersState::SHIFT | ModifiersState::CTRL;
+    let m = Modifiers::from(state);
+    assert!(m.shift() && m.ctrl() && !m.alt());
+    assert!(m.contains(Modifiers::SHIFT | Modifiers::CTRL));
+    assert!(!m.contains(Modifiers::SHIFT | Modifiers::LOGO));
+
+    let scroll = GameEvent::Scroll(ScrollDelta::from(MouseScrollDelta::LineDelta(0f32,-1f32)),(3f64,4f64),m);
+    assert_eq!(scroll.to_type(),GameEventType::Scroll);
+    assert_eq!(scroll.get_pos(),&(3f64,4f64));
+    assert_eq!(scroll.modifiers(),m);
+    let up = GameEvent::MouseUp(MouseButton::from(winit::event::MouseButton::Right),(0f64,0f64),Modifiers::empty());
+    assert_eq!(GameEventType::from(up.to_type() as u32),Some(GameEventType::MouseUp));
+}
+
+#[test]
+fn test_multi_touch_routing() {
+    use specs::Builder;
+    use crate::common::{Tree,TreeNode,Transform,Rect2D,Hidden,HiddenPropagate};
+    use winit::{event::{Touch,DeviceId},window::WindowId,dpi::PhysicalPosition};
+    use std::sync::Mutex;
+    let touch = |id:u64,phase:TouchPhase,x:f64,y:f64| -> Event<'static,()> {
+        let device_id = unsafe { DeviceId::dummy() };
+        let event = WindowEvent::Touch(Touch {device_id,phase,location:PhysicalPosition::new(x,y),force:None,id });
+        Event::WindowEvent {window_id:unsafe { WindowId::dummy() },event }
+    };
+    let mut world = World::new();
+    world.register::<TreeNode>();
+    world.register::<Transform>();
+    world.register::<Rect2D>();
+    world.register::<Hidden>();
+    world.register::<HiddenPropagate>();
+    world.insert(Tree::default());
+    GameEventHandle::register(&mut world);
+
+    let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    let (l0,l1,l2) = (log.clone(),log.clone(),log.clone());
+    let mut ev_node = EventNode::default();
+    ev_node.register(false,GameEventType::Click,move |_,w| {
+        let pointer = w.read_resource::<CurrentEvent>().get().and_then(|e| e.pointer());
+        l0.lock().unwrap().push(format!("click {:?}",pointer));
+    });
+    ev_node.register(false,GameEventType::Pinch,move |_,w| {
+        if let Some(GameEvent::Pinch(_,scale)) = w.read_resource::<CurrentEvent>().get() {
+            l1.lock().unwrap().push(format!("pinch {}",scale));
+        }
+    });
+    ev_node.register(false,GameEventType::Pan,move |_,w| {
+        if let Some(GameEvent::Pan(_,delta)) = w.read_resource::<CurrentEvent>().get() {
+            l2.lock().unwrap().push(format!("pan {:?}",delta));
+        }
+    });
+    let root = world.create_entity().with(Transform::default()).with(Rect2D::new(200f32,200f32,[0.5f32,0.5f32])).with(CABEventRoot::default()).build();
+    Tree::add(&mut world,root,None);
+    let child = world.create_entity().with(Transform::default()).with(Rect2D::new(100f32,100f32,[0.5f32,0.5f32])).with(ev_node).build();
+    Tree::add(&mut world,child,Some(root));
+
+    let mut handle = GameEventHandle::new();
+    handle.set_view_size((200f64,200f64));
+    handle.fire_event(&vec![touch(1,TouchPhase::Started,100f64,100f64),touch(2,TouchPhase::Started,120f64,100f64)],&mut world);
+    assert!(world.read_storage::<EventNode>().get(child).unwrap().is_pressed());
+    handle.fire_event(&vec![touch(2,TouchPhase::Moved,140f64,100f64),touch(1,TouchPhase::Ended,100f64,100f64)],&mut world);
+    handle.fire_event(&vec![touch(2,TouchPhase::Cancelled,140f64,100f64)],&mut world);
+    assert!(!world.read_storage::<EventNode>().get(child).unwrap().is_pressed());
+    assert_eq!(*log.lock().unwrap(),vec!["pinch 2","pan (10.0, 0.0)","click Some(Touch(1))"]);
+}
+
+#[test]
+fn test_ctrl_click_modifiers() {
+    use specs::Builder;
+    use crate::common::{Tree,TreeNode,Transform,Rect2D,Hidden,HiddenPropagate};
+    use std::sync::Mutex;
+    struct LogModifiers(Arc<Mutex<Vec<String>>>);
+    impl GameEventCallBack for LogModifiers {
+        fn run(&self,ev:&GameEvent,_:&mut World) {
+            self.0.lock().unwrap().push(format!("changed {}",ev.modifiers().ctrl()));
+        }
+    }
+    let mut world = World::new();
+    world.register::<TreeNode>();
+    world.register::<Transform>();
+    world.register::<Rect2D>();
+    world.register::<Hidden>();
+    world.register::<HiddenPropagate>();
+    world.insert(Tree::default());
+    GameEventHandle::register(&mut world);
+
+    let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    let click_log = log.clone();
+    let mut ev_node = EventNode::default();
+    ev_node.register(false,GameEventType::Click,move |_,w| {
+        click_log.lock().unwrap().push(format!("click {}",w.read_resource::<CurrentEvent>().modifiers().ctrl()));
+    });
+    let root = world.create_entity().with(Transform::default()).with(Rect2D::new(100f32,100f32,[0.5f32,0.5f32]))
+                    .with(CABEventRoot::default()).with(ev_node).build();
+    Tree::add(&mut world,root,None);
+    let mut global_node = global::GlobalEventNode::default();
+    global_node.insert(GameEventType::ModifiersChanged,Box::new(LogModifiers(log.clone())));
+    world.create_entity().with(global_node).build();
+
+    let mut handle = GameEventHandle::new();
+    handle.set_view_size((100f64,100f64));
+    let click = |handle:&mut GameEventHandle,world:&mut World| {
+        handle.fire_game_events(&[GameEvent::TouchStart(PointerId::Mouse,(0f64,0f64)),GameEvent::TouchEnd(PointerId::Mouse,(0f64,0f64))],world);
+    };
+    handle.fire_game_events(&[GameEvent::ModifiersChanged(Modifiers::CTRL)],&mut world);
+    click(&mut handle,&mut world);
+    handle.fire_game_events(&[GameEvent::ModifiersChanged(Modifiers::empty())],&mut world);
+    click(&mut handle,&mut world);
+    assert_eq!(*log.lock().unwrap(),vec!["changed true","click true","changed false","click false"]);
+}
@@ -0,0 +1,125 @@
+use crate::event::{GameEvent,PointerId};
+
+//双击,长按,悬停的识别参数,时间单位为秒,距离单位与事件坐标相同
+pub struct RecognizerSettings {
+    pub double_click_interval:f64,
+    pub double_click_distance:f64,
+    pub long_press_time:f64,
+    //按住期间移动超过这个距离就不再算长按
+    pub long_press_tolerance:f64,
+    pub hover_delay:f64
+}
+
+impl Default for RecognizerSettings {
+    fn default() -> Self {
+        RecognizerSettings {
+            double_click_interval:0.3f64,
+            double_click_distance:8f64,
+            long_press_time:0.6f64,
+            long_press_tolerance:8f64,
+            hover_delay:0.5f64
+        }
+    }
+}
+
+struct PressState {
+    pointer:PointerId,
+    pos:(f64,f64),
+    time:f64,
+    //None:还未到时间,Some(true):已派发给至少一个节点,会吞掉随后的Click
+    fired:Option<bool>
+}
+
+#[derive(Default)]
+pub struct PointerRecognizer {
+    presses:Vec<PressState>,
+    last_click:Option<((f64,f64),f64)>,
+    hover:Option<((f64,f64),f64,bool)>
+}
+
+fn distance(a:(f64,f64),b:(f64,f64)) -> f64 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+impl PointerRecognizer {
+    pub fn press(&mut self,pointer:PointerId,pos:(f64,f64),now:f64) {
+        self.presses.retain(|p| p.pointer != pointer);
+        self.presses.push(PressState {pointer,pos,time:now,fired:None });
+    }
+
+    pub fn moved(&mut self,pointer:PointerId,pos:(f64,f64),now:f64,settings:&RecognizerSettings) {
+        let tolerance = settings.long_press_tolerance;
+        self.presses.retain(|p| p.pointer != pointer || p.fired.is_some() || distance(p.pos,pos) <= tolerance);
+        if pointer == PointerId::Mouse {
+            self.hover = Some((pos,now,false));
+        }
+    }
+
+    //返回是否因为长按而吞掉Click
+    pub fn release(&mut self,pointer:PointerId) -> bool {
+        let idx = self.presses.iter().position(|p| p.pointer == pointer);
+        idx.map(|i| self.presses.remove(i).fired == Some(true)).unwrap_or(false)
+    }
+
+    //返回这次Click是否构成双击
+    pub fn click(&mut self,pos:(f64,f64),now:f64,settings:&RecognizerSettings) -> bool {
+        if let Some((last_pos,last_time)) = self.last_click.take() {
+            if now - last_time <= settings.double_click_interval && distance(last_pos,pos) <= settings.double_click_distance {
+                return true;
+            }
+        }
+        self.last_click = Some((pos,now));
+        false
+    }
+
+    pub fn cancel(&mut self,pointer:PointerId) {
+        self.presses.retain(|p| p.pointer != pointer);
+    }
+
+    //到时间的LongPress和HoverStay,每个只返回一次
+    pub fn update(&mut self,now:f64,settings:&RecognizerSettings) -> Vec<GameEvent> {
+        let mut evs = Vec::new();
+        for press in self.presses.iter_mut() {
+            if press.fired.is_none() && now - press.time >= settings.long_press_time {
+                press.fired = Some(false);
+                evs.push(GameEvent::LongPress(press.pointer,press.pos));
+            }
+        }
+        if let Some((pos,time,fired)) = self.hover.as_mut() {
+            if !*fired && now - *time >= settings.hover_delay {
+                *fired = true;
+                evs.push(GameEvent::HoverStay(*pos));
+            }
+        }
+        evs
+    }
+
+    //LongPress确实有节点处理时才吞掉之后的Click
+    pub fn set_long_press_handled(&mut self,pointer:PointerId) {
+        if let Some(press) = self.presses.iter_mut().find(|p| p.pointer == pointer) {
+            press.fired = Some(true);
+        }
+    }
+}
+
+#[test]
+fn test_recognizer_timing() {
+    let settings = RecognizerSettings::default();
+    let mut rec = PointerRecognizer::default();
+    assert!(!rec.click((0f64,0f64),1f64,&settings));
+    assert!(rec.click((3f64,0f64),1.2f64,&settings));
+    assert!(!rec.click((3f64,0f64),1.3f64,&settings));
+    assert!(!rec.click((30f64,0f64),1.4f64,&settings));
+
+    rec.press(PointerId::Touch(1),(0f64,0f64),2f64);
+    assert!(rec.update(2.5f64,&settings).is_empty());
+    assert!(matches!(rec.update(2.7f64,&settings)[..],[GameEvent::LongPress(PointerId::Touch(1),_)]));
+    assert!(rec.update(3f64,&settings).is_empty());
+    rec.set_long_press_handled(PointerId::Touch(1));
+    assert!(rec.release(PointerId::Touch(1)));
+
+    rec.press(PointerId::Mouse,(0f64,0f64),4f64);
+    rec.moved(PointerId::Mouse,(20f64,0f64),4.1f64,&settings);
+    assert!(matches!(rec.update(5f64,&settings)[..],[GameEvent::HoverStay((x,_))] if x == 20f64));
+    assert!(!rec.release(PointerId::Mouse));
+}
@@ -0,0 +1,295 @@
+use specs::{World,WorldExt};
+use crate::event::{GameEvent,GameEventHandle,PointerId,MouseButton,ScrollDelta,Modifiers,KeyCode};
+use crate::core::{Time};
+use serde_json::{json,Value};
+use std::time::{Duration};
+use std::path::{Path};
+use std::io;
+
+//录制GameEventHandle处理的输入事件,帧号相对开始录制时的Time::frame_number
+//只记录输入层事件,Click/Drag/FocusIn等派生事件回放时会重新合成
+#[derive(Default)]
+pub struct EventRecorder {
+    start_frame:Option<u64>,
+    events:Vec<(u64,GameEvent)>
+}
+
+impl EventRecorder {
+    pub fn start(world:&mut World) {
+        world.insert(EventRecorder::default());
+    }
+
+    pub fn stop(world:&mut World) -> Option<EventRecorder> {
+        world.remove::<EventRecorder>()
+    }
+
+    pub fn events(&self) -> &Vec<(u64,GameEvent)> {
+        &self.events
+    }
+
+    pub fn record(&mut self,frame:u64,ev:&GameEvent) {
+        if encode_event(0,ev).is_none() {
+            return;
+        }
+        let start = *self.start_frame.get_or_insert(frame);
+        self.events.push((frame - start,ev.clone()));
+    }
+
+    //每行一个json事件,例如 {"frame":3,"type":"TouchStart","pointer":"Mouse","pos":[10.0,20.0]}
+    pub fn to_json_lines(&self) -> String {
+        let mut out = String::new();
+        for (frame,ev) in self.events.iter() {
+            if let Some(val) = encode_event(*frame,ev) {
+                out.push_str(&val.to_string());
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    pub fn save<P:AsRef<Path>>(&self,path:P) -> io::Result<()> {
+        std::fs::write(path,self.to_json_lines())
+    }
+}
+
+//按录制时的帧回放事件,回放期间App使用固定的delta时间
+pub struct EventReplayer {
+    events:Vec<(u64,GameEvent)>,
+    cursor:usize,
+    start_frame:Option<u64>,
+    delta:Duration
+}
+
+impl EventReplayer {
+    pub fn new(events:Vec<(u64,GameEvent)>) -> Self {
+        EventReplayer {events,cursor:0,start_frame:None,delta:Duration::from_secs_f64(1f64 / 60f64) }
+    }
+
+    pub fn from_json_lines(s:&str) -> io::Result<EventReplayer> {
+        let mut events = Vec::new();
+        for (i,line) in s.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let val:Value = serde_json::from_str(line).map_err(|e| invalid_data(format!("line {}: {}",i + 1,e)))?;
+            let ev = decode_event(&val).ok_or_else(|| invalid_data(format!("line {}: unknown event {}",i + 1,line)))?;
+            events.push(ev);
+        }
+        events.sort_by_key(|(frame,_)| *frame);
+        Ok(EventReplayer::new(events))
+    }
+
+    pub fn load<P:AsRef<Path>>(path:P) -> io::Result<EventReplayer> {
+        EventReplayer::from_json_lines(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn with_delta(mut self,delta:Duration) -> Self {
+        self.delta = delta;
+        self
+    }
+
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.events.len()
+    }
+
+    //第一次调用时的帧作为第0帧
+    pub fn take_frame(&mut self,frame:u64) -> Vec<GameEvent> {
+        let start = *self.start_frame.get_or_insert(frame);
+        let mut evs = Vec::new();
+        while let Some((ev_frame,ev)) = self.events.get(self.cursor) {
+            if *ev_frame > frame - start {
+                break;
+            }
+            evs.push(ev.clone());
+            self.cursor += 1;
+        }
+        evs
+    }
+
+    //World中存在EventReplayer时用它代替窗口事件,返回是否进行了回放
+    pub fn replay_frame(world:&mut World,handle:&mut GameEventHandle) -> bool {
+        let frame = world.try_fetch::<Time>().map(|t| t.frame_number()).unwrap_or(0);
+        let events = match world.try_fetch_mut::<EventReplayer>() {
+            Some(mut replayer) => replayer.take_frame(frame),
+            None => return false
+        };
+        handle.fire_game_events(&events,world);
+        true
+    }
+
+    //不依赖App时手动推进一帧
+    pub fn step(world:&mut World,handle:&mut GameEventHandle) -> bool {
+        if !EventReplayer::replay_frame(world,handle) {
+            return false;
+        }
+        let delta = world.read_resource::<EventReplayer>().delta();
+        let mut time = world.write_resource::<Time>();
+        time.set_delta_time(delta);
+        time.inc_frame_number();
+        true
+    }
+}
+
+fn invalid_data(msg:String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData,msg)
+}
+
+fn pointer_to_str(pointer:&PointerId) -> String {
+    match pointer {
+        PointerId::Mouse => String::from("Mouse"),
+        PointerId::Touch(id) => format!("Touch:{}",id)
+    }
+}
+
+fn pointer_from_str(s:&str) -> Option<PointerId> {
+    match s.strip_prefix("Touch:") {
+        Some(id) => Some(PointerId::Touch(id.parse().ok()?)),
+        None if s == "Mouse" => Some(PointerId::Mouse),
+        None => None
+    }
+}
+
+fn button_to_str(button:&MouseButton) -> String {
+    match button {
+        MouseButton::Left => String::from("Left"),
+        MouseButton::Right => String::from("Right"),
+        MouseButton::Middle => String::from("Middle"),
+        MouseButton::Other(n) => format!("Other:{}",n)
+    }
+}
+
+fn button_from_str(s:&str) -> Option<MouseButton> {
+    match s {
+        "Left" => Some(MouseButton::Left),
+        "Right" => Some(MouseButton::Right),
+        "Middle" => Some(MouseButton::Middle),
+        _ => Some(MouseButton::Other(s.strip_prefix("Other:")?.parse().ok()?))
+    }
+}
+
+fn encode_event(frame:u64,ev:&GameEvent) -> Option<Value> {
+    let pos = ev.get_pos();
+    let mut val = match ev {
+        GameEvent::TouchStart(p,_) | GameEvent::TouchEnd(p,_) | GameEvent::TouchCancel(p,_) | GameEvent::Move(p,_) => {
+            json!({"pointer":pointer_to_str(p),"pos":[pos.0,pos.1]})
+        },
+        GameEvent::MouseDown(b,_,m) | GameEvent::MouseUp(b,_,m) => {
+            json!({"button":button_to_str(b),"pos":[pos.0,pos.1],"mods":m.bits()})
+        },
+        GameEvent::Scroll(delta,_,m) => {
+            let (kind,x,y) = match delta {
+                ScrollDelta::Line(x,y) => ("line",*x as f64,*y as f64),
+                ScrollDelta::Pixel(x,y) => ("pixel",*x,*y)
+            };
+            json!({"delta":[x,y],"unit":kind,"pos":[pos.0,pos.1],"mods":m.bits()})
+        },
+        GameEvent::ModifiersChanged(m) => json!({"mods":m.bits()}),
+        GameEvent::KeyBoard(code,is_press,m) => json!({"key":code.name(),"press":is_press,"mods":m.bits()}),
+        GameEvent::RecvChar(chr) => json!({"char":chr.to_string()}),
+        _ => return None
+    };
+    val["frame"] = json!(frame);
+    val["type"] = json!(format!("{:?}",ev.to_type()));
+    Some(val)
+}
+
+fn decode_event(val:&Value) -> Option<(u64,GameEvent)> {
+    let frame = val["frame"].as_u64()?;
+    let pos = || -> Option<(f64,f64)> { Some((val["pos"][0].as_f64()?,val["pos"][1].as_f64()?)) };
+    let pointer = || val["pointer"].as_str().and_then(pointer_from_str);
+    let button = || val["button"].as_str().and_then(button_from_str);
+    let mods = || val["mods"].as_u64().map(|m| Modifiers::from_bits(m as u8));
+    let ev = match val["type"].as_str()? {
+        "TouchStart" => GameEvent::TouchStart(pointer()?,pos()?),
+        "TouchEnd" => GameEvent::TouchEnd(pointer()?,pos()?),
+        "TouchCancel" => GameEvent::TouchCancel(pointer()?,pos()?),
+        "MouseMove" => GameEvent::Move(pointer()?,pos()?),
+        "MouseDown" => GameEvent::MouseDown(button()?,pos()?,mods()?),
+        "MouseUp" => GameEvent::MouseUp(button()?,pos()?,mods()?),
+        "Scroll" => {
+            let (x,y) = (val["delta"][0].as_f64()?,val["delta"][1].as_f64()?);
+            let delta = match val["unit"].as_str()? {
+                "line" => ScrollDelta::Line(x as f32,y as f32),
+                "pixel" => ScrollDelta::Pixel(x,y),
+                _ => return None
+            };
+            GameEvent::Scroll(delta,pos()?,mods()?)
+        },
+        "ModifiersChanged" => GameEvent::ModifiersChanged(mods()?),
+        "KeyBoard" => GameEvent::KeyBoard(KeyCode::from_name(val["key"].as_str()?)?,val["press"].as_bool()?,mods()?),
+        "RecvChar" => GameEvent::RecvChar(val["char"].as_str()?.chars().next()?),
+        _ => return None
+    };
+    Some((frame,ev))
+}
+
+#[test]
+fn test_record_and_replay() {
+    use specs::Builder;
+    use crate::common::{Tree,TreeNode,Transform,Rect2D,Hidden,HiddenPropagate};
+    use crate::event::{EventNode,GameEventType};
+    use crate::event::cb_event::{CABEventRoot};
+    use std::sync::{Arc,Mutex};
+    let build_world = |log:Arc<Mutex<Vec<String>>>| -> World {
+        let mut world = World::new();
+        world.register::<TreeNode>();
+        world.register::<Transform>();
+        world.register::<Rect2D>();
+        world.register::<Hidden>();
+        world.register::<HiddenPropagate>();
+        world.insert(Tree::default());
+        world.insert(Time::default());
+        GameEventHandle::register(&mut world);
+        let mut ev_node = EventNode::default();
+        for typ in [GameEventType::Click,GameEventType::LongPress,GameEventType::KeyBoard] {
+            let (log,name) = (log.clone(),format!("{:?}",typ));
+            ev_node.register(false,typ,move |_,w| {
+                let frame = w.read_resource::<Time>().frame_number();
+                log.lock().unwrap().push(format!("{} {}",name,frame));
+            });
+        }
+        ev_node.tab_index = Some(0);
+        let root = world.create_entity().with(Transform::default()).with(Rect2D::new(200f32,200f32,[0.5f32,0.5f32])).with(CABEventRoot::default()).build();
+        Tree::add(&mut world,root,None);
+        let child = world.create_entity().with(Transform::default()).with(Rect2D::new(100f32,100f32,[0.5f32,0.5f32])).with(ev_node).build();
+        Tree::add(&mut world,child,Some(root));
+        world
+    };
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut world = build_world(log.clone());
+    let mut handle = GameEventHandle::new();
+    world.write_resource::<Time>().inc_frame_number();
+    EventRecorder::start(&mut world);
+    let frames:[&[GameEvent];4] = [
+        &[GameEvent::Move(PointerId::Mouse,(0f64,0f64)),GameEvent::TouchStart(PointerId::Mouse,(0f64,0f64))],
+        &[GameEvent::TouchEnd(PointerId::Mouse,(0f64,0f64)),GameEvent::KeyBoard(KeyCode::A,true,Modifiers::CTRL)],
+        &[],
+        &[GameEvent::TouchStart(PointerId::Touch(3),(10f64,10f64))]
+    ];
+    for evs in frames.iter() {
+        handle.fire_game_events(evs,&mut world);
+        world.write_resource::<Time>().inc_frame_number();
+    }
+    let recorder = EventRecorder::stop(&mut world).unwrap();
+    assert_eq!(recorder.events().len(),5);
+    assert_eq!(*log.lock().unwrap(),vec!["Click 2","KeyBoard 2"]);
+
+    //长按需要0.6秒,用0.25秒的固定帧间隔回放时在按下3帧后触发,并吞掉松开时的Click
+    let mut text = recorder.to_json_lines();
+    text.push_str("{\"frame\":7,\"type\":\"TouchEnd\",\"pointer\":\"Touch:3\",\"pos\":[10.0,10.0]}\n");
+    let replay_log = Arc::new(Mutex::new(Vec::new()));
+    let mut world = build_world(replay_log.clone());
+    let mut handle = GameEventHandle::new();
+    world.insert(EventReplayer::from_json_lines(&text).unwrap().with_delta(Duration::from_millis(250)));
+    while !world.read_resource::<EventReplayer>().is_finished() {
+        assert!(EventReplayer::step(&mut world,&mut handle));
+    }
+    assert_eq!(*replay_log.lock().unwrap(),vec!["Click 1","KeyBoard 1","LongPress 6"]);
+
+    assert!(EventReplayer::from_json_lines("{\"frame\":0,\"type\":\"Click\"}").is_err());
+}
@@ -85,15 +85,38 @@ impl From<Orthographic> for Projection {
 
 impl From<Projection> for Camera {
     fn from(proj: Projection) -> Self {
-        Camera { inner: proj }
+        Camera {
+            inner: proj,
+            viewport: [0f32,0f32,1f32,1f32],
+            clear: CameraClear::None,
+            culling_mask: u32::MAX,
+            order: 0,
+            follow_view_size: false
+        }
     }
 }
 
-
+//相机开始绘制前对自己视口区域的清除方式,需要渲染节点带有深度附件
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraClear {
+    //保留前面相机的颜色和深度
+    None,
+    Depth,
+    Color([f32;4])
+}
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Camera {
     inner: Projection,
+    //归一化的视口[x,y,w,h],左上角为原点
+    pub viewport: [f32;4],
+    pub clear: CameraClear,
+    //与EntityInfo::layer比较,没有EntityInfo的实体在第0层
+    pub culling_mask: u32,
+    //多个相机按order从小到大依次绘制
+    pub order: i32,
+    //窗口大小变化时按视口的像素大小重新计算standard_2d投影
+    pub follow_view_size: bool
 }
 
 impl Camera {
@@ -122,12 +145,50 @@ impl Camera {
     pub fn projection_mut(&mut self) -> &mut Projection {
         &mut self.inner
     }
+
+    pub fn with_viewport(mut self,x:f32,y:f32,w:f32,h:f32) -> Self {
+        self.viewport = [x,y,w,h];
+        self
+    }
+
+    pub fn with_clear(mut self,clear:CameraClear) -> Self {
+        self.clear = clear;
+        self
+    }
+
+    pub fn with_culling_mask(mut self,mask:u32) -> Self {
+        self.culling_mask = mask;
+        self
+    }
+
+    //后绘制的相机和前面的相机共用深度缓冲,默认的CameraClear::None下与前面相机同样z的不透明精灵会因深度测试被丢弃,
+    //UI等叠加相机需要同时设置with_clear(CameraClear::Depth)
+    pub fn with_order(mut self,order:i32) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn with_follow_view_size(mut self,follow:bool) -> Self {
+        self.follow_view_size = follow;
+        self
+    }
+
+    //view_width,view_height为整个输出的逻辑大小,投影只覆盖相机自己的视口
+    pub fn resize_to_view(&mut self,view_width:f32,view_height:f32) {
+        let [_,_,w,h] = self.viewport;
+        self.inner = Camera::standard_2d(view_width * w,view_height * h).inner;
+    }
+
+    pub fn is_layer_visible(&self,layer:u32) -> bool {
+        layer < 32 && self.culling_mask & (1 << layer) != 0
+    }
 }
 
 impl Component for Camera {
     type Storage = HashMapStorage<Self>;
 }
 
+//所有Camera都会参与渲染,ActiveCamera只指定需要单个相机时使用的主相机
 #[derive(Clone, Debug, PartialEq, Default)]
 pub struct ActiveCamera {
     /// Camera entity
@@ -0,0 +1,73 @@
+use specs::{Entity};
+use fnv::{FnvHashMap};
+
+//渲染输出只绘制部分相机或层,例如小地图的离屏输出只绘制小地图相机
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Default)]
+pub enum CameraFilter {
+    #[default]
+    All,
+    Camera(Entity),
+    //只绘制EntityInfo::layer在mask中的实体
+    Layers(u32)
+}
+
+//按输出名记录过滤条件,Flat2D组通过with_output关联输出名
+//指定给某个输出的相机不会再绘制到其他输出
+#[derive(Default,Debug)]
+pub struct RenderFilters {
+    filters:FnvHashMap<String,CameraFilter>
+}
+
+impl RenderFilters {
+    pub fn set(&mut self,output:&str,filter:CameraFilter) {
+        self.filters.insert(String::from(output),filter);
+    }
+
+    pub fn remove(&mut self,output:&str) {
+        self.filters.remove(output);
+    }
+
+    pub fn get(&self,output:&str) -> CameraFilter {
+        self.filters.get(output).copied().unwrap_or_default()
+    }
+
+    pub fn accept_camera(&self,output:Option<&str>,camera:Option<Entity>) -> bool {
+        if let Some(CameraFilter::Camera(target)) = output.map(|name| self.get(name)) {
+            return camera == Some(target);
+        }
+        let camera = match camera {
+            Some(camera) => camera,
+            None => return true
+        };
+        !self.filters.iter().any(|(name,filter)| Some(name.as_str()) != output && *filter == CameraFilter::Camera(camera))
+    }
+
+    pub fn accept_layer(&self,output:Option<&str>,layer:u32) -> bool {
+        match output.map(|name| self.get(name)) {
+            Some(CameraFilter::Layers(mask)) => layer < 32 && mask & (1 << layer) != 0,
+            _ => true
+        }
+    }
+}
+
+#[test]
+fn test_render_filters() {
+    use specs::{World,WorldExt,Builder};
+    let mut world = World::new();
+    let main_camera = world.create_entity().build();
+    let minimap_camera = world.create_entity().build();
+    let mut filters = RenderFilters::default();
+    assert!(filters.accept_camera(None,Some(minimap_camera)) && filters.accept_camera(Some("minimap"),Some(main_camera)));
+
+    filters.set("minimap",CameraFilter::Camera(minimap_camera));
+    assert!(filters.accept_camera(Some("minimap"),Some(minimap_camera)));
+    assert!(!filters.accept_camera(Some("minimap"),Some(main_camera)));
+    assert!(!filters.accept_camera(Some("minimap"),None));
+    assert!(filters.accept_camera(None,Some(main_camera)) && filters.accept_camera(None,None));
+    assert!(!filters.accept_camera(None,Some(minimap_camera)));
+
+    filters.set("ui",CameraFilter::Layers(1 << 5));
+    assert!(filters.accept_layer(Some("ui"),5) && !filters.accept_layer(Some("ui"),0));
+    assert!(filters.accept_layer(None,0) && filters.accept_layer(Some("minimap"),0));
+    assert!(filters.accept_camera(Some("ui"),Some(main_camera)) && !filters.accept_camera(Some("ui"),Some(minimap_camera)));
+}
@@ -1,5 +1,5 @@
 use crate::render::{SpriteMesh};
-use specs::{Component,DenseVecStorage};
+use specs::{Component,DenseVecStorage,FlaggedStorage};
 
 pub struct Mesh2D {
     pub mesh:Option<SpriteMesh>,
@@ -23,6 +23,7 @@ impl Mesh2D {
     }
 }
 
+//网格变化会改变包围盒,SpriteVisibilitySortingSystem需要读取修改事件
 impl Component for Mesh2D {
-    type Storage = DenseVecStorage<Self>;
+    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
 }
\ No newline at end of file
@@ -1,11 +1,13 @@
 mod image;
 mod sprite;
 mod sprite_sheet;
+mod sprite_animation;
 mod text;
 mod mesh2d;
 pub use image::{ImageRender};
 pub use sprite::{SpriteRender};
-pub use sprite_sheet::{SpriteSheet,Sprite,TextureCoordinate};
+pub use sprite_animation::{SpriteAnimation,AnimationClip,AnimationMode,AnimationEventKind};
+pub use sprite_sheet::{SpriteSheet,Sprite,TextureCoordinate,FrameTag,TagDirection};
 pub use text::{TextRender,LineMode};
 pub use crate::render::SpriteMesh;
 pub use mesh2d::{Mesh2D};
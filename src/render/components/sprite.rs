@@ -2,13 +2,14 @@ use crate::render::components::{SpriteSheet,Mesh2D};
 use crate::assets::{Handle,AssetStorage};
 use crate::common::{Transform,rect::{Rect},Rect2D};
 use specs::{Component,storage::{DenseVecStorage}};
-use crate::render::components::{ImageGenericInfo,ImageType,Sprite};
+use crate::render::components::{ImageGenericInfo,ImageType,Sprite,SpriteMesh};
 
 
 pub struct SpriteRender {
     info:ImageGenericInfo,
     sprite_name:Option<String>,
     pub sprite_sheet:Option<Handle<SpriteSheet>>,
+    sheet_version:u32
 }
 
 impl SpriteRender {
@@ -16,6 +17,7 @@ impl SpriteRender {
         SpriteRender {
             sprite_name: sprite_name.map(|s| s.to_string()),
             sprite_sheet,
+            sheet_version:0,
             info: ImageGenericInfo {
                 color: [1.0,1.0,1.0,1.0],
                 typ: ImageType::Simple,
@@ -37,7 +39,7 @@ impl SpriteRender {
 
     pub fn get_sprite(&self,sprite_sheet_storage:&AssetStorage<SpriteSheet>) -> Option<Sprite> {
         if self.is_valid() {
-            let sprite_sheet = sprite_sheet_storage.get(self.sprite_sheet.as_ref().unwrap()).unwrap();
+            let sprite_sheet = sprite_sheet_storage.get(self.sprite_sheet.as_ref().unwrap())?;
             sprite_sheet.get_sprite(&self.sprite_name.as_ref().unwrap()).map(|s| s.clone())
         } else {
             None
@@ -85,8 +87,8 @@ impl SpriteRender {
     }
 
     pub fn uv_rect_and_size(&self,storage:&AssetStorage<SpriteSheet>) -> Option<(Rect<f32>,(u32,u32))> {
-        let sprite_sheet = storage.get(&self.sprite_sheet.as_ref().unwrap()).unwrap();
-        let sprite = sprite_sheet.get_sprite(&self.sprite_name.as_ref().unwrap()).unwrap();
+        let sprite_sheet = storage.get(self.sprite_sheet.as_ref()?)?;
+        let sprite = sprite_sheet.get_sprite(self.sprite_name.as_ref()?)?;
         Some((Rect {
             x: sprite.coord.left,
             y: sprite.coord.top,
@@ -95,6 +97,46 @@ impl SpriteRender {
         },(sprite.rect.width as u32,sprite.rect.height as u32)))
     }
 
+    //旋转的图块先按0-1的uv生成网格,再把uv映射到图集中顺时针旋转90度的区域
+    fn build_mesh(&self,t:&Transform,storage:&AssetStorage<SpriteSheet>,rect2d:&Rect2D) -> SpriteMesh {
+        let (rect,size) = self.uv_rect_and_size(storage).unwrap();
+        let sprite = self.get_sprite(storage).unwrap();
+        let mut mesh = if !sprite.rotated {
+            self.info.to_mesh(t,rect,size,rect2d)
+        } else {
+            let mut mesh = self.info.to_mesh(t,Rect {x:0f32,y:0f32,width:1f32,height:1f32},size,rect2d);
+            for vert in mesh.meshes.iter_mut() {
+                let uv:&mut [f32;2] = vert.uv.as_mut();
+                let (s,v) = (uv[0],uv[1]);
+                uv[0] = rect.x + (1f32 - v) * rect.width;
+                uv[1] = rect.y + s * rect.height;
+            }
+            mesh
+        };
+        SpriteRender::apply_trim_and_pivot(&sprite,rect2d,&mut mesh);
+        mesh
+    }
+
+    //Rect2D对应裁剪前的原图,裁剪后的图块按trim_offset和source_size缩放到原图中的位置
+    //pivot相对图块中心的偏移会平移整个网格,pivot的y轴向下
+    fn apply_trim_and_pivot(sprite:&Sprite,rect2d:&Rect2D,mesh:&mut SpriteMesh) {
+        if !sprite.is_trimmed() && sprite.pivot == (0.5f32,0.5f32) {
+            return;
+        }
+        let (w,h) = (rect2d.width(),rect2d.height());
+        let (src_w,src_h) = (sprite.source_size.0.max(1) as f32,sprite.source_size.1.max(1) as f32);
+        let (x_min,y_max) = (-w * rect2d.anchor()[0],h * (1f32 - rect2d.anchor()[1]));
+        let (scale_x,scale_y) = (sprite.rect.width as f32 / src_w,sprite.rect.height as f32 / src_h);
+        let trim_x = x_min + w * sprite.trim_offset.0 as f32 / src_w;
+        let trim_y = y_max - h * sprite.trim_offset.1 as f32 / src_h;
+        let (dx,dy) = ((0.5f32 - sprite.pivot.0) * w,(sprite.pivot.1 - 0.5f32) * h);
+        for vert in mesh.meshes.iter_mut() {
+            let pos:&mut [f32;3] = vert.pos.as_mut();
+            pos[0] = trim_x + (pos[0] - x_min) * scale_x + dx;
+            pos[1] = trim_y - (y_max - pos[1]) * scale_y + dy;
+        }
+    }
+
     pub fn sprite_name(&self) -> Option<&String> {
         self.sprite_name.as_ref()
     }
@@ -107,21 +149,27 @@ impl SpriteRender {
         if ! self.is_valid() || (rect2d.width <= 0f32 && rect2d.height <= 0f32) {
             return;
         }
+        //图集还没加载完成或者找不到图块时跳过,等下一帧再生成网格
+        match storage.get_version(self.sprite_sheet.as_ref().unwrap()) {
+            Some(version) if self.uv_rect_and_size(storage).is_some() => {
+                if version != self.sheet_version {
+                    self.sheet_version = version;
+                    mesh2d.is_dirty = true;
+                }
+            },
+            _ => return
+        }
         match mesh2d.mesh.as_mut() {
             Some(mesh) => {
                 if mesh2d.is_dirty {
-                    let (rect,size) = self.uv_rect_and_size(&storage).unwrap();
-                    let new_mesh = self.info.to_mesh(t,rect,size,rect2d);
-                    *mesh = new_mesh;
+                    *mesh = self.build_mesh(t,storage,rect2d);
                     mesh2d.is_dirty = false;
                 }
                 let model:[[f32; 4]; 4] = (*t.global_matrix()).into();
                 mesh.sprite_arg.model = model.into();
             },
             None => {
-                let (rect,size) = self.uv_rect_and_size(&storage).unwrap();
-                let new_mesh = self.info.to_mesh(t,rect,size,rect2d);
-                mesh2d.mesh = Some(new_mesh);
+                mesh2d.mesh = Some(self.build_mesh(t,storage,rect2d));
                 mesh2d.is_dirty = false;
             }
         }
@@ -130,4 +178,32 @@ impl SpriteRender {
 
 impl Component for SpriteRender {
     type Storage = DenseVecStorage<Self>;
-}
\ No newline at end of file
+}
+#[test]
+fn test_rotated_trimmed_mesh() {
+    use crate::render::components::{TextureCoordinate};
+    use fnv::FnvHashMap;
+    let sprite = |name:&str,rect:Rect<i32>,coord:TextureCoordinate,rotated:bool,source_size:(u32,u32),trim_offset:(i32,i32),pivot:(f32,f32)| {
+        Sprite {name:String::from(name),rect,coord,rotated,source_size,trim_offset,pivot,duration:None }
+    };
+    let sprites = vec![
+        sprite("a",Rect {x:10,y:0,width:6,height:4},TextureCoordinate {left:10f32 / 32f32,right:14f32 / 32f32,top:0f32,bottom:6f32 / 32f32},true,(8,8),(1,2),(0.5f32,0.5f32)),
+        sprite("b",Rect {x:0,y:0,width:8,height:8},TextureCoordinate {left:0f32,right:0.25f32,top:0f32,bottom:0.25f32},false,(8,8),(0,0),(0f32,1f32))
+    ];
+    let name_dic:FnvHashMap<String,u32> = sprites.iter().enumerate().map(|(i,s)| (s.name.clone(),i as u32)).collect();
+    let mut storage:AssetStorage<SpriteSheet> = AssetStorage::new();
+    let sheet = storage.insert(SpriteSheet::new(32,32,Handle::new(0),sprites,name_dic,FnvHashMap::default(),Vec::new()));
+    let rect2d = Rect2D::new(8f32,8f32,[0.5f32,0.5f32]);
+    let vert = |mesh:&SpriteMesh,i:usize| {
+        let (pos,uv):(&[f32;3],&[f32;2]) = (mesh.meshes[i].pos.as_ref(),mesh.meshes[i].uv.as_ref());
+        (pos[0],pos[1],uv[0],uv[1])
+    };
+
+    let mesh = SpriteRender::new(Some(sheet.clone()),Some("a")).build_mesh(&Transform::default(),&storage,&rect2d);
+    assert_eq!(vert(&mesh,0),(-3f32,2f32,14f32 / 32f32,0f32));
+    assert_eq!(vert(&mesh,3),(3f32,-2f32,10f32 / 32f32,6f32 / 32f32));
+
+    let mesh = SpriteRender::new(Some(sheet),Some("b")).build_mesh(&Transform::default(),&storage,&rect2d);
+    assert_eq!(vert(&mesh,0),(0f32,8f32,0f32,0f32));
+    assert_eq!(vert(&mesh,3),(8f32,0f32,0.25f32,0.25f32));
+}
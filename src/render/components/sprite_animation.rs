@@ -0,0 +1,244 @@
+use crate::render::components::{SpriteSheet,TagDirection};
+use specs::{Component,storage::{DenseVecStorage}};
+use std::collections::HashMap;
+
+const DEFAULT_FRAME_DURATION:f32 = 0.1f32;
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum AnimationMode {
+    Once,
+    Loop,
+    PingPong
+}
+
+#[derive(Debug,Clone)]
+pub enum ClipFrames {
+    Names(Vec<String>),
+    //SpriteSheet中的frame tag,方向由tag决定
+    Tag(String)
+}
+
+#[derive(Debug,Clone)]
+pub struct AnimationClip {
+    frames:ClipFrames,
+    //为空时使用sheet中的帧时长,只有一个时作用于所有帧
+    durations:Vec<f32>,
+    mode:Option<AnimationMode>
+}
+
+impl AnimationClip {
+    pub fn from_names(names:&[&str],frame_duration:f32,mode:AnimationMode) -> Self {
+        AnimationClip {
+            frames:ClipFrames::Names(names.iter().map(|s| String::from(*s)).collect()),
+            durations:vec![frame_duration],
+            mode:Some(mode)
+        }
+    }
+
+    //mode为None时PingPong的tag使用PingPong,其余为Loop
+    pub fn from_tag(tag:&str,mode:Option<AnimationMode>) -> Self {
+        AnimationClip {frames:ClipFrames::Tag(String::from(tag)),durations:Vec::new(),mode }
+    }
+
+    pub fn with_durations(mut self,durations:Vec<f32>) -> Self {
+        self.durations = durations;
+        self
+    }
+
+    fn resolve(&self,sheet:&SpriteSheet) -> (Vec<(String,f32)>,AnimationMode) {
+        let (indexs,mode):(Vec<usize>,AnimationMode) = match &self.frames {
+            ClipFrames::Names(names) => {
+                let indexs = names.iter().filter_map(|n| sheet.sprite_index(n)).map(|i| i as usize).collect();
+                (indexs,self.mode.unwrap_or(AnimationMode::Loop))
+            },
+            ClipFrames::Tag(tag_name) => match sheet.get_tag(tag_name) {
+                Some(tag) => {
+                    let mut indexs:Vec<usize> = (tag.from as usize..=tag.to as usize).collect();
+                    if tag.direction == TagDirection::Reverse {
+                        indexs.reverse();
+                    }
+                    let tag_mode = if tag.direction == TagDirection::PingPong { AnimationMode::PingPong } else { AnimationMode::Loop };
+                    (indexs,self.mode.unwrap_or(tag_mode))
+                },
+                None => (Vec::new(),AnimationMode::Once)
+            }
+        };
+        let frames = indexs.iter().enumerate().filter_map(|(i,idx)| {
+            let sprite = sheet.get_sprite_by_index(*idx)?;
+            let duration = match self.durations.len() {
+                0 => sprite.duration.unwrap_or(DEFAULT_FRAME_DURATION),
+                1 => self.durations[0],
+                _ => self.durations.get(i).cloned().unwrap_or(DEFAULT_FRAME_DURATION)
+            };
+            Some((sprite.name.clone(),duration))
+        }).collect();
+        (frames,mode)
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum AnimationEventKind {
+    //Loop/PingPong每完成一轮
+    Looped,
+    //Once播放结束
+    Finished
+}
+
+pub struct SpriteAnimation {
+    clips:HashMap<String,AnimationClip>,
+    current:Option<String>,
+    frames:Vec<(String,f32)>,
+    mode:AnimationMode,
+    sheet_version:Option<u32>,
+    frame:usize,
+    step:i32,
+    elapsed:f32,
+    playing:bool,
+    pub speed:f32
+}
+
+impl Default for SpriteAnimation {
+    fn default() -> Self {
+        SpriteAnimation {
+            clips:HashMap::new(),
+            current:None,
+            frames:Vec::new(),
+            mode:AnimationMode::Loop,
+            sheet_version:None,
+            frame:0,
+            step:1,
+            elapsed:0f32,
+            playing:false,
+            speed:1f32
+        }
+    }
+}
+
+impl SpriteAnimation {
+    pub fn new() -> Self {
+        SpriteAnimation::default()
+    }
+
+    pub fn add_clip(&mut self,name:&str,clip:AnimationClip) {
+        self.clips.insert(String::from(name),clip);
+    }
+
+    pub fn with_clip(mut self,name:&str,clip:AnimationClip) -> Self {
+        self.add_clip(name,clip);
+        self
+    }
+
+    pub fn play(&mut self,name:&str) -> bool {
+        if !self.clips.contains_key(name) {
+            return false;
+        }
+        self.current = Some(String::from(name));
+        self.sheet_version = None;
+        self.frame = 0;
+        self.step = 1;
+        self.elapsed = 0f32;
+        self.playing = true;
+        true
+    }
+
+    pub fn stop(&mut self) {
+        self.playing = false;
+        self.frame = 0;
+        self.elapsed = 0f32;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn resume(&mut self) {
+        if self.current.is_some() {
+            self.playing = true;
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn current_clip(&self) -> Option<&String> {
+        self.current.as_ref()
+    }
+
+    pub fn current_frame(&self) -> usize {
+        self.frame
+    }
+
+    pub fn current_sprite(&self) -> Option<&String> {
+        self.frames.get(self.frame).map(|f| &f.0)
+    }
+
+    //sheet重新加载或切换clip后重新解析帧列表,返回是否重新解析
+    pub fn sync_sheet(&mut self,sheet:&SpriteSheet,version:u32) -> bool {
+        if self.sheet_version == Some(version) {
+            return false;
+        }
+        self.sheet_version = Some(version);
+        if let Some(clip) = self.current.as_ref().and_then(|name| self.clips.get(name)) {
+            let (frames,mode) = clip.resolve(sheet);
+            self.frames = frames;
+            self.mode = mode;
+            if self.frame >= self.frames.len() {
+                self.frame = 0;
+            }
+        }
+        true
+    }
+
+    pub fn advance(&mut self,dt:f32) -> Option<AnimationEventKind> {
+        if !self.playing || self.frames.is_empty() {
+            return None;
+        }
+        let mut event = None;
+        self.elapsed += dt * self.speed;
+        loop {
+            let duration = self.frames[self.frame].1;
+            if self.elapsed < duration || duration <= 0f32 {
+                break;
+            }
+            self.elapsed -= duration;
+            match self.next_frame() {
+                Some((next,looped)) => {
+                    self.frame = next;
+                    if looped {
+                        event = Some(AnimationEventKind::Looped);
+                    }
+                },
+                None => {
+                    self.playing = false;
+                    self.elapsed = 0f32;
+                    return Some(AnimationEventKind::Finished);
+                }
+            }
+        }
+        event
+    }
+
+    fn next_frame(&mut self) -> Option<(usize,bool)> {
+        let len = self.frames.len();
+        match self.mode {
+            AnimationMode::Once => if self.frame + 1 < len { Some((self.frame + 1,false)) } else { None },
+            AnimationMode::Loop => Some(((self.frame + 1) % len,self.frame + 1 == len)),
+            AnimationMode::PingPong => {
+                if len == 1 {
+                    return Some((0,true));
+                }
+                let next = self.frame as i32 + self.step;
+                if next < 0 || next >= len as i32 {
+                    self.step = -self.step;
+                }
+                let next = (self.frame as i32 + self.step) as usize;
+                Some((next,next == 0))
+            }
+        }
+    }
+}
+
+impl Component for SpriteAnimation {
+    type Storage = DenseVecStorage<Self>;
+}
@@ -12,6 +12,7 @@ pub struct SpriteSheet {
     sprites: Vec<Sprite>,
     name_dic: FnvHashMap<String, u32>,
     borders: FnvHashMap<String, Vec<(f32, f32, f32, f32)>>,
+    tags: Vec<FrameTag>,
 }
 
 impl SpriteSheet {
@@ -22,6 +23,7 @@ impl SpriteSheet {
         sprites: Vec<Sprite>,
         name_dic: FnvHashMap<String, u32>,
         borders: FnvHashMap<String, Vec<(f32, f32, f32, f32)>>,
+        tags: Vec<FrameTag>,
     ) -> Self {
         Self {
             width,
@@ -30,6 +32,7 @@ impl SpriteSheet {
             sprites,
             name_dic,
             borders,
+            tags,
         }
     }
 
@@ -38,17 +41,68 @@ impl SpriteSheet {
         self.sprites.get(idx as usize)
     }
 
+    pub fn get_sprite_by_index(&self, idx: usize) -> Option<&Sprite> {
+        self.sprites.get(idx)
+    }
+
+    pub fn sprite_index(&self, sprite_name: &str) -> Option<u32> {
+        self.name_dic.get(sprite_name).copied()
+    }
+
+    pub fn sprite_count(&self) -> usize {
+        self.sprites.len()
+    }
+
+    pub fn get_tag(&self, tag_name: &str) -> Option<&FrameTag> {
+        self.tags.iter().find(|t| t.name == tag_name)
+    }
+
+    pub fn tags(&self) -> &[FrameTag] {
+        &self.tags
+    }
+
     pub fn get_border(&self, sprite_name: &String, idx: usize) -> Option<(f32, f32, f32, f32)> {
         let border_arr = self.borders.get(sprite_name)?;
         border_arr.get(idx).map(|v| *v)
     }
 }
 
+//rect为图集中的位置与未旋转时的宽高,coord为图集中实际占用区域的uv
+//rotated为true时图块在图集中顺时针旋转了90度
 #[derive(Debug, Clone)]
 pub struct Sprite {
     pub name: String,
     pub rect: Rect<i32>,
     pub coord: TextureCoordinate,
+    pub rotated: bool,
+    //裁剪前的原始尺寸与裁剪后图块在原图中的偏移
+    pub source_size: (u32, u32),
+    pub trim_offset: (i32, i32),
+    pub pivot: (f32, f32),
+    //单位秒,Aseprite导出的帧时长
+    pub duration: Option<f32>,
+}
+
+impl Sprite {
+    pub fn is_trimmed(&self) -> bool {
+        self.source_size != (self.rect.width as u32, self.rect.height as u32)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagDirection {
+    Forward,
+    Reverse,
+    PingPong,
+}
+
+//Aseprite的frameTags, from/to为sprites下标(包含to)
+#[derive(Debug, Clone)]
+pub struct FrameTag {
+    pub name: String,
+    pub from: u32,
+    pub to: u32,
+    pub direction: TagDirection,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -2,33 +2,94 @@ use crate::render::types::{Backend};
 use crate::render::utils::uniform::{DynamicUniform};
 use rendy::factory::{Factory};
 use crate::render::pod::{ViewArgs};
-use specs::{World};
-use crate::render::CameraGatherer;
+use specs::{World,Read,ReadStorage,SystemData};
+use crate::render::{CameraGatherer,Camera,CameraClear,SpriteVisibility,RenderFilters};
 use rendy::command::{RenderPassEncoder};
-use rendy::hal::{device::{OutOfMemory}};
+use rendy::hal::{device::{OutOfMemory},pso::{Rect,Viewport,ClearRect},command::{AttachmentClear,ClearColor}};
+
+//每个绘制的相机一份uniform和视口,cameras记录它在SpriteVisibility::cameras中的下标
 #[derive(Debug)]
 pub struct CameraEnv<B:Backend> {
-    uniform: DynamicUniform<B, ViewArgs>
+    uniforms: Vec<DynamicUniform<B, ViewArgs>>,
+    views: Vec<(Viewport,CameraClear)>,
+    cameras: Vec<usize>,
+    framebuffer_size: (u32,u32)
 }
 
 impl<B: Backend> CameraEnv<B> {
-    pub fn new(factory: &Factory<B>) -> Result<Self, OutOfMemory> {
+    pub fn new(factory: &Factory<B>,framebuffer_width: u32,framebuffer_height: u32) -> Result<Self, OutOfMemory> {
         Ok(Self {
-            uniform: DynamicUniform::new(factory, rendy::hal::pso::ShaderStageFlags::VERTEX)?,
+            uniforms: vec![DynamicUniform::new(factory, rendy::hal::pso::ShaderStageFlags::VERTEX)?],
+            views: Vec::new(),
+            cameras: Vec::new(),
+            framebuffer_size: (framebuffer_width,framebuffer_height)
         })
     }
 
     pub fn raw_layout(&self) -> &B::DescriptorSetLayout {
-        self.uniform.raw_layout()
+        self.uniforms[0].raw_layout()
+    }
+
+    pub fn view_count(&self) -> usize {
+        self.views.len()
     }
 
-    pub fn process(&mut self, factory: &Factory<B>, index: usize, world: &World) {
-        let projview = CameraGatherer::gather(world).projview;
-        self.uniform.write(factory, index, projview);
+    pub fn camera_index(&self,view: usize) -> usize {
+        self.cameras[view]
+    }
+
+    //output为None时是没有指定输出名的节点,RenderFilters决定哪些相机绘制到这个输出
+    pub fn process(&mut self, factory: &Factory<B>, index: usize, world: &World, output: Option<&str>) {
+        let (visibility,cameras,filters) = <(Read<'_, SpriteVisibility>,ReadStorage<'_, Camera>,Read<'_, RenderFilters>)>::fetch(world);
+        let (fw,fh) = (self.framebuffer_size.0 as f32,self.framebuffer_size.1 as f32);
+        self.views.clear();
+        self.cameras.clear();
+        for (camera_index,view) in visibility.cameras.iter().enumerate() {
+            if !filters.accept_camera(output,view.camera) {
+                continue;
+            }
+            let i = self.views.len();
+            if self.uniforms.len() <= i {
+                self.uniforms.push(DynamicUniform::new(factory, rendy::hal::pso::ShaderStageFlags::VERTEX).unwrap());
+            }
+            let projview = CameraGatherer::gather_camera(world,view.camera).projview;
+            self.uniforms[i].write(factory, index, projview);
+
+            let camera = view.camera.and_then(|e| cameras.get(e));
+            let [x,y,w,h] = camera.map(|c| c.viewport).unwrap_or([0f32,0f32,1f32,1f32]);
+            let rect = Rect {
+                x: (x * fw) as i16,
+                y: (y * fh) as i16,
+                w: (w * fw) as i16,
+                h: (h * fh) as i16
+            };
+            let clear = camera.map(|c| c.clear).unwrap_or(CameraClear::None);
+            self.views.push((Viewport {rect,depth:0.0..1.0 },clear));
+            self.cameras.push(camera_index);
+        }
     }
 
     #[inline]
-    pub fn bind(&self,index: usize,pipeline_layout: &B::PipelineLayout,set_id: u32,encoder: &mut RenderPassEncoder<'_, B>) {
-        self.uniform.bind(index, pipeline_layout, set_id, encoder);
+    pub fn bind(&self,index: usize,view: usize,pipeline_layout: &B::PipelineLayout,set_id: u32,encoder: &mut RenderPassEncoder<'_, B>) {
+        let viewport = &self.views[view].0;
+        self.uniforms[view].bind(index, pipeline_layout, set_id, encoder);
+        unsafe {
+            encoder.set_viewports(0, Some(viewport));
+            encoder.set_scissors(0, Some(&viewport.rect));
+        }
+    }
+
+    //按相机的CameraClear清除它的视口区域
+    pub fn clear(&self,view: usize,encoder: &mut RenderPassEncoder<'_, B>) {
+        let (viewport,clear) = &self.views[view];
+        let depth = AttachmentClear::DepthStencil {depth:Some(1.0),stencil:None };
+        let clears = match *clear {
+            CameraClear::None => return,
+            CameraClear::Depth => vec![depth],
+            CameraClear::Color(color) => vec![AttachmentClear::Color {index:0,value:ClearColor {float32:color } },depth]
+        };
+        unsafe {
+            encoder.clear_attachments(clears, Some(ClearRect {rect:viewport.rect,layers:0..1 }));
+        }
     }
-}
\ No newline at end of file
+}
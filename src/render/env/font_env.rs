@@ -28,7 +28,7 @@ impl TextHashKey {
 pub struct FontEnv<B:Backend> {
     pub font_tex:Option<Handle<Texture>>,
     glyph_brush:GlyphBrush<Vec<Vertex2D>>,
-    fonts_map: HashMap<u32, FontId>,
+    fonts_map: HashMap<u32, (FontId,Handle<FontAsset>,u32)>,
     mark: PhantomData<B>,
 }
 
@@ -80,21 +80,30 @@ impl<B> FontEnv<B> where B:Backend {
             if !text.is_valid() {
                 continue;
             } 
-            rect.clear_dirty();
             let text_font = text.font.as_ref().unwrap();
             let mut font_lookup = None;
-            if self.fonts_map.contains_key(&text_font.id()) {
-                font_lookup = Some(*self.fonts_map.get(&text_font.id()).unwrap());
-            } else {
-                let may_font_asset = font_storage.get(text_font);
-                if let Some(font_asset) = may_font_asset {
-                  let font_id =  self.glyph_brush.add_font(font_asset.font.clone());
-                  self.fonts_map.insert(text_font.id(), font_id);
-                  font_lookup = Some(font_id);
+            let font_version = font_storage.get_version(text_font);
+            match self.fonts_map.get(&text_font.id()) {
+                Some((font_id,_,version)) if Some(*version) == font_version => {
+                    font_lookup = Some(*font_id);
+                },
+                _ => {
+                    let may_font_asset = font_storage.get_with_version(text_font);
+                    if let Some((font_asset,version)) = may_font_asset {
+                      let font_id =  self.glyph_brush.add_font(font_asset.font.clone());
+                      //glyph_brush无法移除字体,持有Handle防止id被回收复用
+                      self.fonts_map.insert(text_font.id(), (font_id,text_font.clone(),*version));
+                      font_lookup = Some(font_id);
+                    }
                 }
             };
 
-            let font_id = font_lookup.unwrap();
+            //字体还没加载完成时保留dirty标记,加载后再排版
+            let font_id = match font_lookup {
+                Some(font_id) => font_id,
+                None => continue
+            };
+            rect.clear_dirty();
             let col3 = t.global_matrix().column(3);
             
             let (h,v) = text.anchor.to_hv_align();
@@ -4,33 +4,37 @@ use rendy::command::{RenderPassEncoder};
 use rendy::resource::{Handle as RendyHandle,DescriptorSetLayout,Escape,DescriptorSet};
 use rendy::hal::pso::{ShaderStageFlags,CreationError};
 use  rendy::hal::{device::Device,image};
-use crate::assets::{AssetStorage,Handle};
-use specs::{World};
+use crate::assets::{AssetStorage,Handle,AssetID};
+use specs::{World,ReaderId};
+use shrev::{EventChannel};
+use std::any::{TypeId};
 use crate::render::utils::{LookupBuilder,desc_write,texture_desc};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct TextureId(u32);
 
+//不持有贴图的Handle,贴图离开视野时描述符集保留,资源被释放后才移除
 #[derive(Debug)]
 pub struct TextureSet<B:Backend> {
     set:Escape<DescriptorSet<B>>,
-    handle:Handle<Texture>,
-    layout: image::Layout
+    version:u32
 }
 
 #[derive(Debug)]
 pub struct TextureEnv<B:Backend> {
     layout: RendyHandle<DescriptorSetLayout<B>>,
-    textures:Vec<TextureSet<B>>,
-    lookup:LookupBuilder<u32>
+    textures:Vec<Option<TextureSet<B>>>,
+    lookup:LookupBuilder<u32>,
+    freed:Option<ReaderId<AssetID>>
 }
 
 impl<B: Backend> TextureEnv<B> {
-    pub fn new(factory: &Factory<B>) -> Result<Self, CreationError> {
+    pub fn new(factory: &Factory<B>,world:&World) -> Result<Self, CreationError> {
         Ok(Self {
             layout: set_layout! {factory, [1] CombinedImageSampler ShaderStageFlags::FRAGMENT},
             textures: Vec::with_capacity(1024),
-            lookup: LookupBuilder::new()
+            lookup: LookupBuilder::new(),
+            freed: world.try_fetch_mut::<EventChannel<AssetID>>().map(|mut channel| channel.register_reader())
         })
     }
 
@@ -39,14 +43,17 @@ impl<B: Backend> TextureEnv<B> {
     }
     
 
+    //贴图还在加载中时返回None,这时不分配槽位
     pub fn insert(&mut self,factory: &Factory<B>,world:&World,handle:&Handle<Texture>, layout: image::Layout) -> Option<TextureId> {
+        let tex_storage = world.fetch::<AssetStorage<Texture>>();
+        let (tex,version) = tex_storage.get_with_version(handle)?;
         let id = self.lookup.forward(handle.id());
-        if self.textures.get(id).is_some() {
-            return Some(TextureId(id as u32));
+        if let Some(Some(tex_set)) = self.textures.get(id) {
+            if tex_set.version == *version {
+                return Some(TextureId(id as u32));
+            }
         }
-       
-        let tex_storage = world.fetch::<AssetStorage<Texture>>();
-        let tex = tex_storage.get(handle)?;
+
         let set = factory.create_descriptor_set(self.layout.clone()).unwrap();
         unsafe {
             let desc = texture_desc(tex, layout)?;
@@ -55,21 +62,35 @@ impl<B: Backend> TextureEnv<B> {
         };
         let tex_set = TextureSet {
             set,
-            handle:handle.clone(),
-            layout
-        };
-        if self.textures.len() == id {
-            self.textures.push(tex_set);
-        } else {
-            self.textures[id] = tex_set;
+            version:*version
         };
+        if self.textures.len() <= id {
+            self.textures.resize_with(id + 1, || None);
+        }
+        self.textures[id] = Some(tex_set);
         Some(TextureId(id as u32))
     }
 
     pub fn bind(&self,pipeline_layout: &B::PipelineLayout,set_id: u32,texture_id: TextureId,encoder: &mut RenderPassEncoder<'_, B>) {
-        let tex_set = &self.textures[texture_id.0 as usize];
+        let tex_set = self.textures[texture_id.0 as usize].as_ref().unwrap();
         unsafe {
             encoder.bind_graphics_descriptor_sets(pipeline_layout,set_id,Some(tex_set.set.raw()),std::iter::empty());
         }
     }
+
+    //移除已释放贴图的描述符集,id被新贴图复用前必须先调用,所以放在prepare开始时
+    pub fn maintain(&mut self,world:&World) {
+        let (channel,reader) = match (world.try_fetch::<EventChannel<AssetID>>(),self.freed.as_mut()) {
+            (Some(channel),Some(reader)) => (channel,reader),
+            _ => return
+        };
+        for (type_id,id) in channel.read(reader) {
+            if *type_id != TypeId::of::<Texture>() {
+                continue;
+            }
+            if let Some(tex_set) = self.lookup.get(*id).and_then(|idx| self.textures.get_mut(idx)) {
+                tex_set.take();
+            }
+        }
+    }
 }
\ No newline at end of file
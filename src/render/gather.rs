@@ -3,7 +3,7 @@ use crate::render::{Camera,ActiveCamera};
 use crate::common::{Transform};
 use glsl_layout::*;
 use nalgebra::{convert,Vector3,Matrix4};
-use specs::{World,Read,ReadStorage,SystemData,Join};
+use specs::{World,Read,ReadStorage,SystemData,Join,Entity,Entities};
 
 type Std140<T> = <T as AsStd140>::Std140;
 
@@ -14,19 +14,22 @@ pub struct CameraGatherer {
 }
 
 impl CameraGatherer {
+    //主相机:ActiveCamera指定的相机,否则取第一个相机
     pub fn gather(world: &World) -> Self {
-       
-        let (active_camera,cameras,transforms) = <(Read<'_, ActiveCamera>, 
-                                                   ReadStorage<'_, Camera>, 
-                                                   ReadStorage<'_, Transform>)>::fetch(world);
-        
+        let entity = {
+            let (entities,active_camera,cameras) = <(Entities<'_>,Read<'_, ActiveCamera>,ReadStorage<'_, Camera>)>::fetch(world);
+            active_camera.entity.filter(|e| cameras.contains(*e))
+                                .or_else(|| (&entities,&cameras).join().map(|(e,_)| e).next())
+        };
+        Self::gather_camera(world,entity)
+    }
+
+    pub fn gather_camera(world: &World,entity:Option<Entity>) -> Self {
+        let (cameras,transforms) = <(ReadStorage<'_, Camera>,ReadStorage<'_, Transform>)>::fetch(world);
         let identity = Transform::default();
         let defcam = Camera::standard_2d(1.0, 1.0);
-        let (camera, transform) = active_camera.entity.as_ref().and_then(|ac| {
-            cameras.get(*ac).map(|camera| (camera, transforms.get(*ac).unwrap_or(&identity)))
-        }).unwrap_or_else(|| {
-            (&cameras, &transforms).join().next().unwrap_or((&defcam, &identity))
-        });
+        let camera = entity.and_then(|e| cameras.get(e)).unwrap_or(&defcam);
+        let transform = entity.and_then(|e| transforms.get(e)).unwrap_or(&identity);
         let camera_position =  convert::<_, Vector3<f32>>(transform.global_matrix().column(3).xyz()).into_pod();
         let proj = camera.as_matrix();
         let view = transform.global_view_matrix();
@@ -44,4 +47,4 @@ impl CameraGatherer {
             projview,
         }
     }
-}
\ No newline at end of file
+}
@@ -1,13 +1,13 @@
 use crate::render::pipeline::{PipelineDescBuilder, PipelinesBuilder};
 use crate::render::pod::{Vertex2D,SpriteArg};
 use crate::assets::{AssetStorage};
-use crate::render::utils::{simple_shader_set,ChangeDetection};
-use crate::common::{Transform};
+use crate::render::utils::{simple_shader_set};
+use crate::common::{Transform,EntityInfo};
 use rendy::command::{QueueId, RenderPassEncoder};
 use rendy::factory::{Factory};
 use specs::{Read,ReadStorage,World,SystemData,Join,Entities};
 use crate::render::types::{Backend};
-use crate::render::{SpriteVisibility,SpriteMeshId,SpriteDynamicMesh};
+use crate::render::{SpriteVisibility,SpriteMeshId,SpriteDynamicMesh,RenderFilters};
 use crate::render::components::{ImageRender,SpriteRender,SpriteSheet,TextRender,Mesh2D};
 use crate::render::batch::{OnLevelBatch,OrderedOneLevelBatch};
 use rendy::graph::{
@@ -39,169 +39,95 @@ lazy_static::lazy_static! {
 }
 
 #[derive(Debug)]
-pub struct Flat2DGroupDesc;
+pub struct Flat2DGroupDesc {
+    output:Option<String>
+}
 
 impl Flat2DGroupDesc {
     pub fn new() -> Self {
-        Flat2DGroupDesc
+        Flat2DGroupDesc {output:None }
+    }
+
+    //按RenderFilters中output的过滤条件绘制
+    pub fn with_output(mut self,output:&str) -> Self {
+        self.output = Some(String::from(output));
+        self
     }
 }
 
 impl<B: Backend> RenderGroupDesc<B, World> for Flat2DGroupDesc {
-    fn build<'a>(self,_ctx: &GraphContext<B>,factory: &mut Factory<B>,_queue: QueueId,_world: &World,
+    fn build<'a>(self,_ctx: &GraphContext<B>,factory: &mut Factory<B>,_queue: QueueId,world: &World,
                  framebuffer_width: u32,framebuffer_height: u32,subpass: Subpass<'_, B>,_buffers: Vec<NodeBuffer>,_images: Vec<NodeImage>) 
                  -> Result<Box<dyn RenderGroup<B, World>>, CreationError> {
         
-        let camera_env = CameraEnv::new(factory)?;
-        let texture_env = TextureEnv::new(factory)?;
+        let camera_env = CameraEnv::new(factory,framebuffer_width,framebuffer_height)?;
+        let texture_env = TextureEnv::new(factory,world)?;
         
-        let (pipeline, pipeline_layout) = build_sprite_pipeline(
+        let (mut pipelines, pipeline_layout) = build_sprite_pipelines(
             factory,
             subpass,
-            framebuffer_width,
-            framebuffer_height,
-            false,vec![camera_env.raw_layout(),texture_env.raw_layout()]).unwrap();
+            vec![camera_env.raw_layout(),texture_env.raw_layout()]).unwrap();
+        let transparent_pipeline = pipelines.pop().unwrap();
+        let pipeline = pipelines.pop().unwrap();
         Ok(Box::new(Flat2DGroup {
+            output:self.output,
             camera_env,
             texture_env,
             pipeline,
+            transparent_pipeline,
             pipeline_layout,
-            sprites:Default::default(),
+            sprites:Vec::new(),
+            transparent_sprites:Vec::new(),
             dynamic_mesh:SpriteDynamicMesh::default()
         }))
     }
 
 }
 
+//一个相机内的绘制步骤
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub(crate) enum CameraPass {
+    Clear,
+    Opaque,
+    Transparent
+}
+
+//相机按顺序完整绘制,后面相机的清除不会擦掉前面相机的内容,透明物体也只和自己相机的深度比较
+pub(crate) fn camera_passes(view_count:usize) -> impl Iterator<Item = (usize,CameraPass)> {
+    (0..view_count).flat_map(|view| [CameraPass::Clear,CameraPass::Opaque,CameraPass::Transparent].iter().map(move |pass| (view,*pass)))
+}
 
 #[derive(Debug)]
 pub struct Flat2DGroup<B: Backend> {
+    output:Option<String>,
     camera_env: CameraEnv<B>,
     pipeline: B::GraphicsPipeline,
+    transparent_pipeline: B::GraphicsPipeline,
     pipeline_layout:B::PipelineLayout,
-    sprites:OnLevelBatch<TextureId,SpriteMeshId>,
+    //每个相机一组
+    sprites:Vec<OnLevelBatch<TextureId,SpriteMeshId>>,
+    transparent_sprites:Vec<OrderedOneLevelBatch<TextureId,SpriteMeshId>>,
     texture_env: TextureEnv<B>,
     dynamic_mesh:SpriteDynamicMesh<B>
 }
 
 impl<B: Backend> RenderGroup<B, World> for Flat2DGroup<B> {
-    fn prepare(&mut self,factory: &Factory<B>,_queue: QueueId,index: usize,_subpass: Subpass<'_, B>,aux: &World) -> PrepareResult {
+    fn prepare(&mut self,factory: &Factory<B>,_queue: QueueId,index: usize,_subpass: Subpass<'_, B>,world: &World) -> PrepareResult {
         #[cfg(feature = "profiler")]
         profile_scope!("Flat2DGroup prepare");
-        let (entities,image_renders,transforms,visibility,mesh2ds) = <(
-            Entities<'_>,
-            ReadStorage<'_, ImageRender>,
-            ReadStorage<'_, Transform>,
-            Read<'_, SpriteVisibility>,
-            ReadStorage<'_,Mesh2D>,
-        )>::fetch(aux);
-
-        self.camera_env.process(factory,index,aux);
-        let sprites_ref = &mut self.sprites;
-        let textures_ref = &mut self.texture_env;
-        sprites_ref.clear_inner();
-        self.dynamic_mesh.clear();
-        
-        for (_,img,_,_,mesh2d) in (&entities,&image_renders,&transforms,&visibility.visible_unordered,&mesh2ds).join() {
-            let mesh = mesh2d.mesh.as_ref();
-            if mesh.is_some() && img.texture.is_some() {
-                let tex_id = textures_ref.insert(factory,aux,img.texture.as_ref().unwrap(),hal::image::Layout::ShaderReadOnlyOptimal).unwrap();
-                let did = self.dynamic_mesh.insert(mesh.unwrap());
-                sprites_ref.insert(tex_id, Some(did));
-            }
-        };
-       
-        self.dynamic_mesh.write(factory,index);
-        sprites_ref.prune();
-        PrepareResult::DrawRecord
-    }
-
-    fn draw_inline(&mut self,mut encoder: RenderPassEncoder<'_, B>,index: usize,_subpass: Subpass<'_, B>,_aux: &World) {
-        let layout = &self.pipeline_layout;
-        encoder.bind_graphics_pipeline(&self.pipeline);
-        self.camera_env.bind(index, layout, 0, &mut encoder);
-        self.dynamic_mesh.bind(index,&mut encoder);
-        for (&tex, lst) in self.sprites.get_map().iter() {
-            self.texture_env.bind(layout, 1, tex, &mut encoder);
-            for mesh_id in lst {
-                self.dynamic_mesh.draw_index(mesh_id,&mut encoder);
-            }
-        }
-    }
-
-    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
-        unsafe {
-            factory.device().destroy_graphics_pipeline(self.pipeline);
-            factory.device().destroy_pipeline_layout(self.pipeline_layout);
-        };
-    }
-}
-
-
-#[derive(Clone, Debug, PartialEq)]
-pub struct Flat2DTransparentDesc;
-
-impl Flat2DTransparentDesc {
-    pub fn new() -> Self {
-        Flat2DTransparentDesc
-    }
-}
-
-impl<B: Backend> RenderGroupDesc<B, World> for Flat2DTransparentDesc {
-    fn build<'a>(self,_ctx: &GraphContext<B>,factory: &mut Factory<B>,_queue: QueueId,_aux: &World,
-                 framebuffer_width: u32,framebuffer_height: u32,subpass: Subpass<'_, B>,_buffers: Vec<NodeBuffer>,_images: Vec<NodeImage>) 
-                 -> Result<Box<dyn RenderGroup<B, World>>, CreationError> {
-        let camera_env = CameraEnv::new(factory)?;
-        let texture_env = TextureEnv::new(factory)?; 
-       
-        let (pipeline, pipeline_layout) = build_sprite_pipeline(
-             factory,
-             subpass,
-             framebuffer_width,
-             framebuffer_height,
-             true,vec![camera_env.raw_layout(),texture_env.raw_layout()]).unwrap();
-        Ok(Box::new(Flat2DTransparent {
-            camera_env,
-            texture_env,
-            dynamic_mesh:SpriteDynamicMesh::default(),
-            pipeline,
-            pipeline_layout,
-            sprites:OrderedOneLevelBatch::default(),
-            change:ChangeDetection::default()
-        }))
-    }
-
-}
-
-#[derive(Debug)]
-pub struct Flat2DTransparent<B: Backend> {
-    camera_env: CameraEnv<B>,
-    texture_env: TextureEnv<B>,
-    dynamic_mesh:SpriteDynamicMesh<B>,
-    pipeline: B::GraphicsPipeline,
-    pipeline_layout:B::PipelineLayout,
-    sprites:OrderedOneLevelBatch<TextureId,SpriteMeshId>,
-    change: ChangeDetection,
-}
-
-impl<B: Backend> RenderGroup<B, World> for Flat2DTransparent<B> {
-    fn prepare(
-        &mut self,
-        factory: &Factory<B>,
-        _queue: QueueId,
-        index: usize,
-        _subpass: hal::pass::Subpass<'_, B>,
-        world: &World,
-    ) -> PrepareResult {
-        let (sprite_sheet_storage,
+        let (entities,
+            sprite_sheet_storage,
             img_renders,
             sprite_renders,
             mes2des,
             transforms,
             visibility,
             font_env,
-            texts
+            texts,
+            infos,
+            filters
         ) = <(
+            Entities<'_>,
             Read<'_, AssetStorage<SpriteSheet>>,
             ReadStorage<'_,ImageRender>,
             ReadStorage<'_, SpriteRender>,
@@ -209,67 +135,111 @@ impl<B: Backend> RenderGroup<B, World> for Flat2DTransparent<B> {
             ReadStorage<'_, Transform>,
             Read<'_, SpriteVisibility>,
             Read<'_,FontEnv<B>>,
-            ReadStorage<'_,TextRender>
+            ReadStorage<'_,TextRender>,
+            ReadStorage<'_,EntityInfo>,
+            Read<'_,RenderFilters>
         )>::fetch(world);
-        self.camera_env.process(factory,index,world);
-        self.sprites.swap_clear();
+
+        let output = self.output.as_deref();
+        self.camera_env.process(factory,index,world,output);
+        let view_count = self.camera_env.view_count();
+        self.sprites.resize_with(view_count, Default::default);
+        self.transparent_sprites.resize_with(view_count, Default::default);
         let textures_ref = &mut self.texture_env;
-        let sprites_ref = &mut self.sprites;
+        textures_ref.maintain(world);
         self.dynamic_mesh.clear();
-        
+
         let mut image_joined = (&img_renders, &transforms,&mes2des).join();
         let mut sprite_joined = (&sprite_renders,&transforms,&mes2des).join();
         let mut text_joined = (&texts,&transforms,&mes2des).join();
-        let font_tex_id = textures_ref.insert(factory,world,font_env.font_tex.as_ref().unwrap(),hal::image::Layout::ShaderReadOnlyOptimal).unwrap();
+        let font_tex_id = font_env.font_tex.as_ref().and_then(|tex| textures_ref.insert(factory,world,tex,hal::image::Layout::ShaderReadOnlyOptimal));
         
-        for e in visibility.visible_ordered.iter() {
-           let may_image = image_joined.get_unchecked(e.id());
-           if let Some((image,_,mesh2d)) = may_image {
-                if let Some(ref mesh) = mesh2d.mesh {
-                    if let Some(tex_id) = image.texture.as_ref() {
-                        let tex_id = textures_ref.insert(factory,world,tex_id,hal::image::Layout::ShaderReadOnlyOptimal).unwrap();
-                        let did = self.dynamic_mesh.insert(mesh);
-                        sprites_ref.insert(tex_id, Some(did));
-                    }
+        for view_index in 0..view_count {
+            let view = &visibility.cameras[self.camera_env.camera_index(view_index)];
+            let sprites_ref = &mut self.sprites[view_index];
+            sprites_ref.clear_inner();
+            for (_,img,_,_,mesh2d,info) in (&entities,&img_renders,&transforms,&view.visible_unordered,&mes2des,infos.maybe()).join() {
+                if !filters.accept_layer(output,info.map(|info| info.layer).unwrap_or(0)) {
+                    continue;
                 }
-           }
-           let may_sprite = sprite_joined.get_unchecked(e.id());
-           if let Some((sprite,_,mesh2d)) = may_sprite {
-            if let Some(ref mesh) = mesh2d.mesh  {
-                if let Some(sheet) = sprite.sprite_sheet.as_ref() {
-                    let ref_tex = &sprite_sheet_storage.get(sheet).unwrap().texture;
-                    let tex_id = textures_ref.insert(factory,world,ref_tex,hal::image::Layout::ShaderReadOnlyOptimal).unwrap();
+                //贴图还在加载中时跳过
+                let tex_id = img.texture.as_ref().and_then(|tex| textures_ref.insert(factory,world,tex,hal::image::Layout::ShaderReadOnlyOptimal));
+                if let (Some(mesh),Some(tex_id)) = (mesh2d.mesh.as_ref(),tex_id) {
                     let did = self.dynamic_mesh.insert(mesh);
                     sprites_ref.insert(tex_id, Some(did));
                 }
-            }
-           }
+            };
+            sprites_ref.prune();
 
-           let may_text = text_joined.get_unchecked(e.id());
-           if let Some((_,_,mesh2d)) = may_text {
-                if let Some(ref mesh) = mesh2d.mesh  {
-                    let did = self.dynamic_mesh.insert(mesh);
-                    sprites_ref.insert(font_tex_id, Some(did));
-                }
-           }
-        }
+            let sprites_ref = &mut self.transparent_sprites[view_index];
+            sprites_ref.swap_clear();
+            for e in view.visible_ordered.iter() {
+             if !filters.accept_layer(output,infos.get(*e).map(|info| info.layer).unwrap_or(0)) {
+                 continue;
+             }
+             let may_image = image_joined.get_unchecked(e.id());
+             if let Some((image,_,mesh2d)) = may_image {
+                  if let Some(ref mesh) = mesh2d.mesh {
+                      if let Some(tex_id) = image.texture.as_ref().and_then(|tex| textures_ref.insert(factory,world,tex,hal::image::Layout::ShaderReadOnlyOptimal)) {
+                          let did = self.dynamic_mesh.insert(mesh);
+                          sprites_ref.insert(tex_id, Some(did));
+                      }
+                  }
+             }
+             let may_sprite = sprite_joined.get_unchecked(e.id());
+             if let Some((sprite,_,mesh2d)) = may_sprite {
+              if let Some(ref mesh) = mesh2d.mesh  {
+                  let sheet = sprite.sprite_sheet.as_ref().and_then(|sheet| sprite_sheet_storage.get(sheet));
+                  if let Some(tex_id) = sheet.and_then(|sheet| textures_ref.insert(factory,world,&sheet.texture,hal::image::Layout::ShaderReadOnlyOptimal)) {
+                      let did = self.dynamic_mesh.insert(mesh);
+                      sprites_ref.insert(tex_id, Some(did));
+                  }
+              }
+             }
 
+             let may_text = text_joined.get_unchecked(e.id());
+             if let (Some((_,_,mesh2d)),Some(font_tex_id)) = (may_text,font_tex_id) {
+                  if let Some(ref mesh) = mesh2d.mesh  {
+                      let did = self.dynamic_mesh.insert(mesh);
+                      sprites_ref.insert(font_tex_id, Some(did));
+                  }
+             }
+            }
+        }
+       
         self.dynamic_mesh.write(factory,index);
         PrepareResult::DrawRecord
     }
 
-    fn draw_inline(&mut self,mut encoder: RenderPassEncoder<'_, B>,index: usize,_subpass: hal::pass::Subpass<'_, B>,_world: &World) {
+    fn draw_inline(&mut self,mut encoder: RenderPassEncoder<'_, B>,index: usize,_subpass: Subpass<'_, B>,_aux: &World) {
         let layout = &self.pipeline_layout;
-        encoder.bind_graphics_pipeline(&self.pipeline);
-        self.camera_env.bind(index, layout, 0, &mut encoder);
-
         self.dynamic_mesh.bind(index,&mut encoder);
-        let data_list = self.sprites.data();
-        for (&tex,range) in self.sprites.iter() {
-            self.texture_env.bind(layout, 1, tex, &mut encoder);
-            for idx in range {
-                let mesh_id = unsafe { data_list.get_unchecked(idx as usize) };
-                self.dynamic_mesh.draw_index(mesh_id,&mut encoder);
+        for (view,pass) in camera_passes(self.camera_env.view_count()) {
+            match pass {
+                CameraPass::Clear => self.camera_env.clear(view, &mut encoder),
+                CameraPass::Opaque => {
+                    encoder.bind_graphics_pipeline(&self.pipeline);
+                    self.camera_env.bind(index, view, layout, 0, &mut encoder);
+                    for (&tex, lst) in self.sprites[view].get_map().iter() {
+                        self.texture_env.bind(layout, 1, tex, &mut encoder);
+                        for mesh_id in lst {
+                            self.dynamic_mesh.draw_index(mesh_id,&mut encoder);
+                        }
+                    }
+                },
+                CameraPass::Transparent => {
+                    encoder.bind_graphics_pipeline(&self.transparent_pipeline);
+                    self.camera_env.bind(index, view, layout, 0, &mut encoder);
+                    let sprites = &self.transparent_sprites[view];
+                    let data_list = sprites.data();
+                    for (&tex,range) in sprites.iter() {
+                        self.texture_env.bind(layout, 1, tex, &mut encoder);
+                        for idx in range {
+                            let mesh_id = unsafe { data_list.get_unchecked(idx as usize) };
+                            self.dynamic_mesh.draw_index(mesh_id,&mut encoder);
+                        }
+                    }
+                }
             }
         }
     }
@@ -277,15 +247,15 @@ impl<B: Backend> RenderGroup<B, World> for Flat2DTransparent<B> {
     fn dispose(self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
         unsafe {
             factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory.device().destroy_graphics_pipeline(self.transparent_pipeline);
             factory.device().destroy_pipeline_layout(self.pipeline_layout);
-        }
+        };
     }
-
 }
 
-fn build_sprite_pipeline<B: Backend>(factory: &Factory<B>,subpass: Subpass<'_, B>,framebuffer_width: u32,
-                                     framebuffer_height: u32,transparent: bool,layouts: Vec<&B::DescriptorSetLayout>) 
-                                     -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+//视口和裁剪由CameraEnv按相机动态设置,返回[不透明,透明]两个管线,共用一个管线布局
+fn build_sprite_pipelines<B: Backend>(factory: &Factory<B>,subpass: Subpass<'_, B>,layouts: Vec<&B::DescriptorSetLayout>) 
+                                     -> Result<(Vec<B::GraphicsPipeline>, B::PipelineLayout), failure::Error> {
     let pipeline_layout = unsafe {
         factory
             .device()
@@ -296,28 +266,29 @@ fn build_sprite_pipeline<B: Backend>(factory: &Factory<B>,subpass: Subpass<'_, B
     let shader_fragment = unsafe { SPRITE_FRAGMENT.module(factory).unwrap() };
 
     let vert_format = vec![(Vertex2D::vertex(), pso::VertexInputRate::Vertex),(SpriteArg::vertex(),pso::VertexInputRate::Instance(1))];
+    let sprite_pipeline = |transparent:bool| {
+        PipelineDescBuilder::new()
+            .with_vertex_desc(&vert_format)
+            .with_input_assembler(pso::InputAssemblerDesc::new(Primitive::TriangleList))
+            .with_shaders(simple_shader_set(&shader_vertex, Some(&shader_fragment)))
+            .with_layout(&pipeline_layout)
+            .with_subpass(subpass)
+            .with_blend_targets(vec![pso::ColorBlendDesc {
+                mask: pso::ColorMask::ALL,
+                blend: if transparent {
+                    Some(pso::BlendState::PREMULTIPLIED_ALPHA)
+                } else {
+                    None
+                },
+            }])
+            .with_depth_test(pso::DepthTest {
+                fun: pso::Comparison::Less,
+                write: !transparent,
+            })
+    };
     let pipes = PipelinesBuilder::new()
-        .with_pipeline(
-            PipelineDescBuilder::new()
-                .with_vertex_desc(&vert_format)
-                .with_input_assembler(pso::InputAssemblerDesc::new(Primitive::TriangleList))
-                .with_shaders(simple_shader_set(&shader_vertex, Some(&shader_fragment)))
-                .with_layout(&pipeline_layout)
-                .with_subpass(subpass)
-                .with_framebuffer_size(framebuffer_width, framebuffer_height)
-                .with_blend_targets(vec![pso::ColorBlendDesc {
-                    mask: pso::ColorMask::ALL,
-                    blend: if transparent {
-                        Some(pso::BlendState::PREMULTIPLIED_ALPHA)
-                    } else {
-                        None
-                    },
-                }])
-                .with_depth_test(pso::DepthTest {
-                    fun: pso::Comparison::Less,
-                    write: !transparent,
-                }),
-        )
+        .with_pipeline(sprite_pipeline(false))
+        .with_pipeline(sprite_pipeline(true))
         .build(factory, None);
     unsafe {
         factory.destroy_shader_module(shader_vertex);
@@ -331,6 +302,33 @@ fn build_sprite_pipeline<B: Backend>(factory: &Factory<B>,subpass: Subpass<'_, B
             }
             Err(e)
         }
-        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+        Ok(pipes) => Ok((pipes, pipeline_layout)),
+    }
+}
+
+#[test]
+fn test_overlapping_cameras_pass_order() {
+    use specs::{WorldExt,Builder,RunNow};
+    use crate::render::{Camera,CameraClear,Transparent,SpriteVisibilitySortingSystem};
+    let mut world = World::new();
+    let mut system = SpriteVisibilitySortingSystem::new(&mut world);
+    let red = CameraClear::Color([1f32,0f32,0f32,1f32]);
+    let main_camera = world.create_entity().with(Transform::default()).with(Camera::standard_2d(100f32,100f32).with_clear(red)).build();
+    //叠加的相机需要清除深度,否则同样z的精灵会被第一个相机的深度挡住
+    let map_camera = world.create_entity().with(Transform::default())
+                          .with(Camera::standard_2d(100f32,100f32).with_order(1).with_viewport(0.5f32,0f32,0.5f32,0.5f32).with_clear(CameraClear::Depth)).build();
+    let sprite = world.create_entity().with(Transform::default()).build();
+    let glass = world.create_entity().with(Transform::default()).with(Transparent).build();
+    system.run_now(&world);
+
+    let visibility = world.read_resource::<SpriteVisibility>();
+    assert_eq!(visibility.cameras.iter().map(|c| c.camera).collect::<Vec<_>>(),vec![Some(main_camera),Some(map_camera)]);
+    for view in visibility.cameras.iter() {
+        assert!(view.visible_unordered.contains(sprite.id()));
+        assert_eq!(view.visible_ordered,vec![glass]);
     }
+    //第二个相机的清除必须在第一个相机的透明物体之后,否则会擦掉重叠区域的内容
+    let passes:Vec<_> = camera_passes(visibility.cameras.len()).collect();
+    assert_eq!(passes,vec![(0,CameraPass::Clear),(0,CameraPass::Opaque),(0,CameraPass::Transparent),
+                           (1,CameraPass::Clear),(1,CameraPass::Opaque),(1,CameraPass::Transparent)]);
 }
@@ -1,2 +1,2 @@
 mod flat2d;
-pub use flat2d::{Flat2DGroupDesc,Flat2DTransparentDesc};
\ No newline at end of file
+pub use flat2d::{Flat2DGroupDesc};
\ No newline at end of file
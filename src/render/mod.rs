@@ -8,6 +8,7 @@ pub mod batch;
 pub mod types;
 pub mod components;
 mod sprite_mesh;
+mod sprite_animation;
 #[macro_use]
 pub mod macros;
 pub mod pod;
@@ -16,18 +17,28 @@ pub mod utils;
 pub mod groups;
 pub mod env;
 mod sprite_visibility;
+mod spatial_grid;
 mod transparent;
+mod sorting_order;
+mod offscreen;
+mod camera_filter;
 pub use transparent::{Transparent};
-pub use sprite_visibility::{SpriteVisibilitySortingSystem,SpriteVisibility};
+pub use sorting_order::{SortingOrder,SortingSettings};
+pub use sprite_visibility::{SpriteVisibilitySortingSystem,SpriteVisibility,CameraVisibility,CullingStats};
 use rendy::hal;
 use rendy::wsi::Surface;
 use rendy::hal::{Backend};
 pub use render::{RenderSystem,RenderBuilder};
 pub use graph_node::{GraphNodeBuilder,GraphNode};
-pub use camera::{Camera,ActiveCamera};
+pub use camera::{Camera,ActiveCamera,CameraClear};
 pub use gather::{CameraGatherer};
 pub use font::{FontAsset};
 pub use sprite_mesh::{SpriteMeshSystem,SpriteMesh,SpriteMeshId,SpriteDynamicMesh};
+pub use sprite_animation::{SpriteAnimationSystem,SpriteAnimationEvent};
+pub use offscreen::{create_render_texture,CapturedImage,FrameCapture};
+pub use camera_filter::{CameraFilter,RenderFilters};
+use crate::assets::{Handle};
+use crate::render::types::{Texture};
 
 #[derive(Debug, Copy,Clone)]
 pub struct ImageOptions {
@@ -41,6 +52,8 @@ pub struct ImageOptions {
 pub enum OutputColor<B: Backend> {
     Image(ImageOptions),
     Surface(Surface<B>, Option<hal::command::ClearValue>),
+    //渲染到离屏图像,每帧结束后复制到这张贴图,贴图由create_render_texture创建
+    Texture(Handle<Texture>, Option<hal::command::ClearValue>),
 }
 
 #[derive(Debug)]
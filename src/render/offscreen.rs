@@ -0,0 +1,344 @@
+use rendy::command::{CommandBuffer,CommandPool,ExecutableState,Families,Family,FamilyId,Fence,MultiShot,
+                     PendingState,Queue,SimultaneousUse,Submission,Submit,QueueId,Graphics};
+use rendy::factory::{Factory,ImageState};
+use rendy::frame::{Frames};
+use rendy::graph::{GraphContext,BufferId,ImageId,NodeId,gfx_acquire_barriers,gfx_release_barriers,BufferAccess,
+                   DynNode,ImageAccess,NodeBuffer,NodeBuildError,NodeBuilder,NodeImage};
+use rendy::resource::{Buffer,BufferInfo,Escape,Handle as RHandle,Image};
+use rendy::texture::{TextureBuilder,pixel::{Rgba8Srgb}};
+use rendy::memory::{Download};
+use rendy::hal::{self,format::Format,command::CommandBuffer as _};
+use specs::{World};
+use crate::assets::{AssetStorage,Handle};
+use crate::render::types::{Backend,Texture};
+use std::path::{Path};
+use std::io;
+
+//离屏渲染的目标贴图,图像格式为Rgba8Srgb,可以直接给ImageRender使用
+pub fn create_render_texture<B:Backend>(world:&World,width:u32,height:u32) -> Handle<Texture> {
+    let texture = {
+        let mut factory = world.fetch_mut::<Factory<B>>();
+        let qid = *world.fetch::<QueueId>();
+        TextureBuilder::new()
+            .with_kind(hal::image::Kind::D2(width,height,1,1))
+            .with_view_kind(hal::image::ViewKind::D2)
+            .with_data_width(width)
+            .with_data_height(height)
+            .with_data(vec![Rgba8Srgb { repr: [0,0,0,0] }; (width * height) as usize])
+            .build(ImageState {
+                queue:qid,
+                stage:hal::pso::PipelineStage::FRAGMENT_SHADER,
+                access:hal::image::Access::SHADER_READ,
+                layout:hal::image::Layout::ShaderReadOnlyOptimal
+            },&mut factory).map(B::wrap_texture).expect("Failed to create render texture")
+    };
+    world.fetch_mut::<AssetStorage<Texture>>().insert(texture)
+}
+
+//读回到CPU的一帧图像,像素为按行排列的RGBA8,第一行是图像顶部
+#[derive(Debug,Clone,PartialEq)]
+pub struct CapturedImage {
+    pub width:u32,
+    pub height:u32,
+    pub pixels:Vec<u8>
+}
+
+impl CapturedImage {
+    pub fn get_pixel(&self,x:u32,y:u32) -> [u8;4] {
+        let idx = ((y * self.width + x) * 4) as usize;
+        [self.pixels[idx],self.pixels[idx + 1],self.pixels[idx + 2],self.pixels[idx + 3]]
+    }
+
+    pub fn save_png<P:AsRef<Path>>(&self,path:P) -> io::Result<()> {
+        image::save_buffer_with_format(path,&self.pixels,self.width,self.height,image::ColorType::RGBA(8),image::ImageFormat::PNG)
+    }
+
+    pub fn load_png<P:AsRef<Path>>(path:P) -> io::Result<CapturedImage> {
+        let img = image::open(path).map_err(|e| io::Error::new(io::ErrorKind::InvalidData,e.to_string()))?.to_rgba();
+        Ok(CapturedImage {width:img.width(),height:img.height(),pixels:img.into_raw() })
+    }
+
+    //与基准图比较时使用,尺寸不同返回None,否则返回所有通道的最大差值
+    pub fn max_difference(&self,other:&CapturedImage) -> Option<u8> {
+        if self.width != other.width || self.height != other.height {
+            return None;
+        }
+        Some(self.pixels.iter().zip(other.pixels.iter()).map(|(a,b)| a.abs_diff(*b)).max().unwrap_or(0))
+    }
+}
+
+//请求读回下一帧,完成后通过take取得结果,只能读回RenderBuilder::with_capture指定的离屏输出
+#[derive(Default)]
+pub struct FrameCapture {
+    requested:bool,
+    result:Option<CapturedImage>
+}
+
+impl FrameCapture {
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested
+    }
+
+    pub fn take(&mut self) -> Option<CapturedImage> {
+        self.result.take()
+    }
+
+    pub(crate) fn finish(&mut self,image:CapturedImage) {
+        self.requested = false;
+        self.result = Some(image);
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum CopyTarget<B:Backend> {
+    Texture(RHandle<Image<B>>),
+    Readback
+}
+
+type PendingBuffer<B> = CommandBuffer<B,hal::queue::QueueType,PendingState<ExecutableState<MultiShot<SimultaneousUse>>>>;
+
+#[derive(Debug)]
+struct Recorded<B:Backend> {
+    submit:Submit<B,SimultaneousUse>,
+    buffer:PendingBuffer<B>
+}
+
+//把渲染节点输出的图像复制到贴图或者CPU可读的Buffer
+#[derive(Debug)]
+pub(crate) struct ImageCopyBuilder<B:Backend> {
+    image:ImageId,
+    target:CopyTarget<B>,
+    dependencies:Vec<NodeId>
+}
+
+impl<B:Backend> ImageCopyBuilder<B> {
+    pub fn new(image:ImageId,target:CopyTarget<B>,dep:NodeId) -> Self {
+        ImageCopyBuilder {image,target,dependencies:vec![dep] }
+    }
+}
+
+#[derive(Debug)]
+struct ImageCopyNode<B:Backend> {
+    pool:CommandPool<B,hal::queue::QueueType>,
+    //Texture每帧提交copy,Readback平时只提交布局转换,请求时提交copy
+    copy:Recorded<B>,
+    idle:Option<Recorded<B>>,
+    readback:Option<(Escape<Buffer<B>>,hal::image::Extent,bool)>
+}
+
+unsafe impl<B:Backend> Sync for ImageCopyNode<B> {}
+unsafe impl<B:Backend> Send for ImageCopyNode<B> {}
+
+fn color_range() -> hal::image::SubresourceRange {
+    hal::image::SubresourceRange {aspects:hal::format::Aspects::COLOR,levels:0..1,layers:0..1 }
+}
+
+fn color_layers() -> hal::image::SubresourceLayers {
+    hal::image::SubresourceLayers {aspects:hal::format::Aspects::COLOR,level:0,layers:0..1 }
+}
+
+fn texture_barrier<B:Backend>(image:&Image<B>,states:std::ops::Range<hal::image::State>) -> hal::memory::Barrier<'_,B> {
+    hal::memory::Barrier::Image {states,families:None,target:image.raw(),range:color_range() }
+}
+
+fn record<B:Backend>(ctx:&GraphContext<B>,pool:&mut CommandPool<B,hal::queue::QueueType>,input:&NodeImage,
+                     copy:Option<(&CopyTarget<B>,Option<&Buffer<B>>)>) -> Recorded<B> {
+    let input_res = ctx.get_image(input.id).expect("Context must contain node's image");
+    let extent = input_res.kind().extent();
+    let buf_initial = pool.allocate_buffers(1).pop().unwrap();
+    let mut buf_recording = buf_initial.begin(MultiShot(SimultaneousUse),());
+    let (mut stages,mut barriers) = gfx_acquire_barriers(ctx,None,Some(input));
+    let shader_read = (hal::image::Access::SHADER_READ,hal::image::Layout::ShaderReadOnlyOptimal);
+    let transfer_write = (hal::image::Access::TRANSFER_WRITE,hal::image::Layout::TransferDstOptimal);
+    stages.start |= hal::pso::PipelineStage::TRANSFER;
+    stages.end |= hal::pso::PipelineStage::TRANSFER;
+    if let Some((CopyTarget::Texture(image),_)) = copy {
+        stages.start |= hal::pso::PipelineStage::FRAGMENT_SHADER;
+        barriers.push(texture_barrier(image,shader_read..transfer_write));
+    }
+    unsafe {
+        buf_recording.encoder().pipeline_barrier(stages,hal::memory::Dependencies::empty(),barriers);
+    }
+    match copy {
+        Some((CopyTarget::Texture(image),_)) => unsafe {
+            buf_recording.encoder().copy_image(input_res.raw(),input.layout,image.raw(),hal::image::Layout::TransferDstOptimal,
+                Some(hal::command::ImageCopy {
+                    src_subresource:color_layers(),
+                    src_offset:hal::image::Offset::ZERO,
+                    dst_subresource:color_layers(),
+                    dst_offset:hal::image::Offset::ZERO,
+                    extent:hal::image::Extent {width:extent.width,height:extent.height,depth:1 }
+                }));
+        },
+        //rendy的Encoder没有封装copy_image_to_buffer
+        Some((CopyTarget::Readback,Some(buffer))) => unsafe {
+            buf_recording.raw().copy_image_to_buffer(input_res.raw(),input.layout,buffer.raw(),
+                Some(hal::command::BufferImageCopy {
+                    buffer_offset:0,
+                    buffer_width:extent.width,
+                    buffer_height:extent.height,
+                    image_layers:color_layers(),
+                    image_offset:hal::image::Offset::ZERO,
+                    image_extent:hal::image::Extent {width:extent.width,height:extent.height,depth:1 }
+                }));
+        },
+        _ => ()
+    }
+    let (mut stages,mut barriers) = gfx_release_barriers(ctx,None,Some(input));
+    stages.start |= hal::pso::PipelineStage::TRANSFER;
+    stages.end |= hal::pso::PipelineStage::BOTTOM_OF_PIPE;
+    match copy {
+        Some((CopyTarget::Texture(image),_)) => {
+            stages.end |= hal::pso::PipelineStage::FRAGMENT_SHADER;
+            barriers.push(texture_barrier(image,transfer_write..shader_read));
+        },
+        Some((CopyTarget::Readback,Some(buffer))) => {
+            stages.end |= hal::pso::PipelineStage::HOST;
+            barriers.push(hal::memory::Barrier::Buffer {
+                states:hal::buffer::Access::TRANSFER_WRITE..hal::buffer::Access::HOST_READ,
+                families:None,
+                target:buffer.raw(),
+                range:None..None
+            });
+        },
+        _ => ()
+    }
+    unsafe {
+        buf_recording.encoder().pipeline_barrier(stages,hal::memory::Dependencies::empty(),barriers);
+    }
+    let (submit,buffer) = buf_recording.finish().submit();
+    Recorded {submit,buffer }
+}
+
+//读回的像素统一转换为RGBA,只支持每通道8位的格式,其他格式在构建节点时返回错误
+fn readback_swizzle(format:Format) -> Result<bool,NodeBuildError> {
+    match format {
+        Format::Rgba8Unorm | Format::Rgba8Srgb => Ok(false),
+        Format::Bgra8Unorm | Format::Bgra8Srgb => Ok(true),
+        _ => Err(NodeBuildError::View(hal::image::ViewError::BadFormat(format)))
+    }
+}
+
+impl<B:Backend> NodeBuilder<B,World> for ImageCopyBuilder<B> {
+    //目标贴图由图形队列使用,复制也放在图形队列避免队列间的所有权转移
+    fn family(&self,_factory:&mut Factory<B>,families:&Families<B>) -> Option<FamilyId> {
+        families.with_capability::<Graphics>()
+    }
+
+    fn buffers(&self) -> Vec<(BufferId,BufferAccess)> {
+        Vec::new()
+    }
+
+    fn images(&self) -> Vec<(ImageId,ImageAccess)> {
+        vec![(self.image,ImageAccess {
+            access:hal::image::Access::TRANSFER_READ,
+            layout:hal::image::Layout::TransferSrcOptimal,
+            usage:hal::image::Usage::TRANSFER_SRC,
+            stages:hal::pso::PipelineStage::TRANSFER
+        })]
+    }
+
+    fn dependencies(&self) -> Vec<NodeId> {
+        self.dependencies.clone()
+    }
+
+    fn build<'a>(self:Box<Self>,ctx:&GraphContext<B>,factory:&mut Factory<B>,family:&mut Family<B>,_queue:usize,
+                 _aux:&World,buffers:Vec<NodeBuffer>,images:Vec<NodeImage>) -> Result<Box<dyn DynNode<B,World>>,NodeBuildError> {
+        assert_eq!(buffers.len(),0);
+        assert_eq!(images.len(),1);
+        let input = images.into_iter().next().unwrap();
+        let swizzle = match &self.target {
+            CopyTarget::Readback => {
+                let input_res = ctx.get_image(input.id).expect("Context must contain node's image");
+                readback_swizzle(input_res.format())?
+            },
+            _ => false
+        };
+        let mut pool = factory.create_command_pool(family).map_err(NodeBuildError::OutOfMemory)?;
+        let node = match &self.target {
+            CopyTarget::Texture(_) => {
+                let copy = record(ctx,&mut pool,&input,Some((&self.target,None)));
+                ImageCopyNode {pool,copy,idle:None,readback:None }
+            },
+            CopyTarget::Readback => {
+                let input_res = ctx.get_image(input.id).expect("Context must contain node's image");
+                let extent = input_res.kind().extent();
+                let info = BufferInfo {size:(extent.width * extent.height * 4) as u64,usage:hal::buffer::Usage::TRANSFER_DST };
+                let buffer = factory.create_buffer(info,Download).expect("Failed to create readback buffer");
+                let copy = record(ctx,&mut pool,&input,Some((&self.target,Some(&buffer))));
+                let idle = record(ctx,&mut pool,&input,None);
+                ImageCopyNode {pool,copy,idle:Some(idle),readback:Some((buffer,extent,swizzle)) }
+            }
+        };
+        Ok(Box::new(node))
+    }
+}
+
+impl<B:Backend> DynNode<B,World> for ImageCopyNode<B> {
+    unsafe fn run<'a>(&mut self,_ctx:&GraphContext<B>,factory:&Factory<B>,queue:&mut Queue<B>,aux:&World,_frames:&Frames<B>,
+                      waits:&[(&'a B::Semaphore,hal::pso::PipelineStage)],signals:&[&'a B::Semaphore],fence:Option<&mut Fence<B>>) {
+        let capture = self.readback.is_some() && aux.try_fetch::<FrameCapture>().map(|c| c.is_requested()).unwrap_or(false);
+        let submit = match &self.idle {
+            Some(idle) if !capture => &idle.submit,
+            _ => &self.copy.submit
+        };
+        queue.submit(Some(Submission::new().submits(Some(submit))
+                                           .wait(waits.iter().cloned())
+                                           .signal(signals.iter().cloned())),fence);
+        if !capture {
+            return;
+        }
+        //截图是低频操作,直接等待GPU完成再读取
+        factory.wait_idle().unwrap();
+        let (buffer,extent,swizzle) = self.readback.as_mut().unwrap();
+        let size = buffer.size();
+        let mut pixels:Vec<u8> = {
+            let mut mapped = buffer.map(factory.device(),0..size).expect("Failed to map readback buffer");
+            mapped.read::<u8>(factory.device(),0..size).expect("Failed to read readback buffer").to_vec()
+        };
+        if *swizzle {
+            for px in pixels.chunks_exact_mut(4) {
+                px.swap(0,2);
+            }
+        }
+        if let Some(mut capture) = aux.try_fetch_mut::<FrameCapture>() {
+            capture.finish(CapturedImage {width:extent.width,height:extent.height,pixels });
+        }
+    }
+
+    unsafe fn dispose(mut self:Box<Self>,factory:&mut Factory<B>,_aux:&World) {
+        for recorded in self.idle.take().into_iter().chain(Some(self.copy)) {
+            self.pool.free_buffers(Some(recorded.buffer.mark_complete()));
+        }
+        factory.destroy_command_pool(self.pool);
+    }
+}
+
+#[test]
+fn test_captured_image_png() {
+    let img = CapturedImage {width:2,height:1,pixels:vec![255,0,0,255,0,128,255,64] };
+    assert_eq!(img.get_pixel(1,0),[0,128,255,64]);
+    let path = std::env::temp_dir().join("seija_capture_test.png");
+    img.save_png(&path).unwrap();
+    let loaded = CapturedImage::load_png(&path).unwrap();
+    assert_eq!(img.max_difference(&loaded),Some(0));
+    let mut other = loaded.clone();
+    other.pixels[1] = 10;
+    assert_eq!(img.max_difference(&other),Some(10));
+    assert_eq!(img.max_difference(&CapturedImage {width:1,height:1,pixels:vec![0;4] }),None);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn test_readback_format() {
+    assert!(!readback_swizzle(Format::Rgba8Srgb).unwrap());
+    assert!(readback_swizzle(Format::Bgra8Unorm).unwrap());
+    match readback_swizzle(Format::Rgba16Sfloat) {
+        Err(NodeBuildError::View(hal::image::ViewError::BadFormat(Format::Rgba16Sfloat))) => (),
+        _ => panic!("expected BadFormat")
+    }
+}
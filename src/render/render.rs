@@ -8,7 +8,8 @@ use std::collections::{HashMap};
 use crate::render::render_plan::{RenderPlan};
 use specs::{World,WorldExt,};
 use rendy::factory::{Factory};
-use crate::render::{OutputColor,Camera,Transparent,env::{FontEnv}};
+use crate::render::{OutputColor,Camera,ActiveCamera,Transparent,SortingOrder,FrameCapture,RenderFilters,env::{FontEnv}};
+use crate::render::offscreen::{ImageCopyBuilder,CopyTarget};
 use rendy::core::hal::window::{Extent2D};
 use crate::render::components::{ImageRender,SpriteRender,TextRender,Mesh2D};
 use crate::assets::{AssetStorage};
@@ -34,37 +35,49 @@ impl<B> RenderSystem<B> where B:Backend {
         world.insert(factory);
         world.insert(plan);
         world.insert(queue_id);
+        RenderSystem::<B>::register(world);
+        
+        RenderSystem {
+            families: families,
+            graph: None,
+            render_builder:None,
+        }
+    }
+
+    //渲染相关的组件和资源,headless模式下没有Factory也需要注册
+    pub fn register(world:&mut World) {
         world.register::<Camera>();
         world.register::<ImageRender>();
         world.register::<SpriteRender>();
         world.register::<Transparent>();
+        world.register::<SortingOrder>();
         world.register::<TextRender>();
         world.register::<Mesh2D>();
         world.insert(FontEnv::<B>::default());
-        
-        RenderSystem {
-            families: families,
-            graph: None,
-            render_builder:None,
-        }
+        world.insert(ActiveCamera::default());
+        world.insert(FrameCapture::default());
+        world.insert(RenderFilters::default());
     }
 
 
     pub fn build(&mut self,mut builder:RenderBuilder<B>,world:&World) {
-        let graph_builder = builder.build(&mut world.fetch_mut::<RenderPlan>());
+        let graph_builder = builder.build(&mut world.fetch_mut::<RenderPlan>(),world);
         let graph = graph_builder.build(&mut world.fetch_mut::<Factory<B>>(),&mut self.families,&world);
         self.graph = Some(graph.unwrap());
         self.render_builder = Some(builder);
     }
 
-    pub fn re_build(&mut self,world:&World) {
-        world.fetch_mut::<RenderPlan>().clear();
-        let graph_builder = self.render_builder.as_mut().unwrap().build(&mut world.fetch_mut::<RenderPlan>());
-        let new_graph = graph_builder.build(&mut world.fetch_mut::<Factory<B>>(),&mut self.families,world).unwrap();
+    //build时节点和Surface已经被取走,重建需要传入新的RenderBuilder
+    //旧的渲染图先释放,它持有的Surface会一起销毁
+    pub fn re_build(&mut self,mut builder:RenderBuilder<B>,world:&World) {
         if let Some(graph) = self.graph.take() {
             graph.dispose(&mut world.fetch_mut::<Factory<B>>(), world);
         }
+        world.fetch_mut::<RenderPlan>().clear();
+        let graph_builder = builder.build(&mut world.fetch_mut::<RenderPlan>(),world);
+        let new_graph = graph_builder.build(&mut world.fetch_mut::<Factory<B>>(),&mut self.families,world).unwrap();
         self.graph = Some(new_graph);
+        self.render_builder = Some(builder);
     }
 
     pub fn update(&mut self,world:&World) {
@@ -96,6 +109,7 @@ impl<B> RenderSystem<B> where B:Backend {
 pub struct RenderBuilder<B:Backend> {
     nodes:HashMap<String,GraphNode<B>>,
     roots:Vec<String>,
+    captures:Vec<String>,
     graph_builder:Option<GraphBuilder<B,World>>,
     render_size:Extent2D
 }
@@ -105,10 +119,17 @@ impl<B> RenderBuilder<B> where B:Backend {
         RenderBuilder {
             nodes:HashMap::new(),
             roots:Vec::new(),
+            captures:Vec::new(),
             graph_builder:None,
             render_size:Extent2D {width:1024,height:768}
         }
     }
+    //输出到Surface时使用的大小,物理像素
+    pub fn with_render_size(mut self,width:u32,height:u32) -> RenderBuilder<B> {
+        self.render_size = Extent2D {width,height};
+        self
+    }
+
     pub fn with_node(mut self,node:GraphNode<B>) -> RenderBuilder<B> {
         self.nodes.insert(node.node_name(), node);
         self
@@ -121,7 +142,13 @@ impl<B> RenderBuilder<B> where B:Backend {
         self
     }
 
-    pub fn build(&mut self,plan:&mut RenderPlan) -> GraphBuilder<B,World> {
+    //允许通过FrameCapture读回这个节点的离屏输出,不支持直接输出到Surface的节点
+    pub fn with_capture(mut self,node_name:&str) -> RenderBuilder<B> {
+        self.captures.push(String::from(node_name));
+        self
+    }
+
+    pub fn build(&mut self,plan:&mut RenderPlan,world:&World) -> GraphBuilder<B,World> {
         self.graph_builder = Some(GraphBuilder::new());
         let mut build_lst:Vec<String> = Vec::new();
         for node_name in self.roots.iter() {
@@ -132,8 +159,14 @@ impl<B> RenderBuilder<B> where B:Backend {
         let drain_node_ref = &mut drain_node;
         for node_name in build_lst {
             let node = drain_node_ref.get_mut(&node_name).unwrap();
-            self.eval_node(node,plan);
+            self.eval_node(node,plan,world);
         };
+        let graph_builder = self.graph_builder.as_mut().unwrap();
+        for node_name in self.captures.iter() {
+            let image_id = *plan.outputs.get(node_name).expect("capture node has no image output");
+            let node_id = *plan.node_passes.get(node_name).expect("not found nodeid");
+            graph_builder.add_node(ImageCopyBuilder::new(image_id,CopyTarget::Readback,node_id));
+        }
         self.graph_builder.take().unwrap()
     }
 
@@ -149,7 +182,7 @@ impl<B> RenderBuilder<B> where B:Backend {
        ret
     }
 
-    fn eval_node(&mut self,node:&mut GraphNode<B>,plan:&mut RenderPlan) {
+    fn eval_node(&mut self,node:&mut GraphNode<B>,plan:&mut RenderPlan,world:&World) {
         let mut subpass = SubpassBuilder::new();
         let mut pass = RenderPassNodeBuilder::new();
         let node_name = node.node_name();
@@ -158,6 +191,7 @@ impl<B> RenderBuilder<B> where B:Backend {
         for group in node.groups.drain(..).map(|a|a.1) {
             subpass.add_dyn_group(group);
         }
+        let mut copies = Vec::new();
         for (_,color) in node.outputs.colors.drain(..).enumerate() {
             match color {
                 OutputColor::Image(img) => {
@@ -168,6 +202,14 @@ impl<B> RenderBuilder<B> where B:Backend {
                 OutputColor::Surface(surface, clear) => {
                     subpass.add_color_surface();
                     pass.add_surface(surface,self.render_size,clear);
+                },
+                OutputColor::Texture(handle, clear) => {
+                    let storage = world.fetch::<AssetStorage<Texture>>();
+                    let image = storage.get(&handle).and_then(B::unwrap_texture).expect("render texture not found").image().clone();
+                    let image_id = graph_builder.create_image(image.kind(),1,image.format(),clear);
+                    plan.outputs.insert(node_name.clone(),image_id);
+                    subpass.add_color(image_id);
+                    copies.push((image_id,image));
                 }
             }
         }
@@ -181,7 +223,11 @@ impl<B> RenderBuilder<B> where B:Backend {
             subpass.add_dependency(*node_id);
         }
         pass.add_subpass(subpass);
-        let node_id = graph_builder.add_node(pass);
+        let mut node_id = graph_builder.add_node(pass);
+        //依赖这个节点的其他节点需要等贴图复制完成
+        for (image_id,image) in copies {
+            node_id = graph_builder.add_node(ImageCopyBuilder::new(image_id,CopyTarget::Texture(image),node_id));
+        }
         plan.node_passes.insert(node_name.clone(),node_id);
     }
 }
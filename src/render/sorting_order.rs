@@ -0,0 +1,26 @@
+use specs::{Component,DenseVecStorage};
+
+//透明物体的绘制顺序,先比较layer再比较order,小的先画,都相同时才按离相机的距离排序
+//没有这个组件的实体相当于SortingOrder {layer:0,order:0}
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SortingOrder {
+    pub layer:i32,
+    pub order:i32
+}
+
+impl SortingOrder {
+    pub fn new(layer:i32,order:i32) -> Self {
+        SortingOrder {layer,order }
+    }
+}
+
+impl Component for SortingOrder {
+    type Storage = DenseVecStorage<Self>;
+}
+
+//tree_order为true时,layer和order相同的实体按在Tree中的先序遍历顺序绘制,子节点画在父节点之上
+//不在Tree中的实体排在Tree中的实体之前
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SortingSettings {
+    pub tree_order:bool
+}
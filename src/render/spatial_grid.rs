@@ -0,0 +1,147 @@
+use fnv::{FnvHashMap};
+use hibitset::{BitSet,BitSetLike};
+
+//世界空间的包围盒[min_x,min_y,max_x,max_y]
+pub type Bounds = [f32;4];
+
+//超过这个格子数的大物体不放进格子,每次查询直接比较包围盒
+const MAX_CELLS_PER_ENTRY:i64 = 64;
+
+pub fn intersects(a:&Bounds,b:&Bounds) -> bool {
+    a[0] <= b[2] && b[0] <= a[2] && a[1] <= b[3] && b[1] <= a[3]
+}
+
+struct Entry {
+    bounds:Option<Bounds>,
+    cells:Option<[i32;4]>
+}
+
+//按固定大小的格子索引实体包围盒,只有包围盒变化的实体需要更新,查询开销与视野内的格子数和实体数相关
+pub struct SpatialGrid {
+    cell_size:f32,
+    cells:FnvHashMap<(i32,i32),Vec<u32>>,
+    entries:FnvHashMap<u32,Entry>,
+    //没有包围盒或者太大的实体
+    overflow:BitSet
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size:f32) -> Self {
+        SpatialGrid {
+            cell_size,
+            cells:FnvHashMap::default(),
+            entries:FnvHashMap::default(),
+            overflow:BitSet::new()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn cell_range(&self,bounds:&Bounds) -> [i32;4] {
+        let s = self.cell_size;
+        [(bounds[0] / s).floor() as i32,(bounds[1] / s).floor() as i32,(bounds[2] / s).floor() as i32,(bounds[3] / s).floor() as i32]
+    }
+
+    fn range_count(range:&[i32;4]) -> i64 {
+        (range[2] as i64 - range[0] as i64 + 1).saturating_mul(range[3] as i64 - range[1] as i64 + 1)
+    }
+
+    //bounds为None时认为总是可见
+    pub fn update(&mut self,id:u32,bounds:Option<Bounds>) {
+        let cells = bounds.map(|b| self.cell_range(&b)).filter(|r| SpatialGrid::range_count(r) <= MAX_CELLS_PER_ENTRY);
+        if let Some(old) = self.entries.get_mut(&id) {
+            if old.cells == cells {
+                old.bounds = bounds;
+                return;
+            }
+        }
+        self.remove(id);
+        match cells {
+            Some(range) => {
+                for x in range[0]..=range[2] {
+                    for y in range[1]..=range[3] {
+                        self.cells.entry((x,y)).or_default().push(id);
+                    }
+                }
+            },
+            None => { self.overflow.add(id); }
+        }
+        self.entries.insert(id,Entry {bounds,cells });
+    }
+
+    pub fn remove(&mut self,id:u32) {
+        let entry = match self.entries.remove(&id) {
+            Some(entry) => entry,
+            None => return
+        };
+        match entry.cells {
+            Some(range) => {
+                for x in range[0]..=range[2] {
+                    for y in range[1]..=range[3] {
+                        if let Some(lst) = self.cells.get_mut(&(x,y)) {
+                            if let Some(idx) = lst.iter().position(|v| *v == id) {
+                                lst.swap_remove(idx);
+                            }
+                            if lst.is_empty() {
+                                self.cells.remove(&(x,y));
+                            }
+                        }
+                    }
+                }
+            },
+            None => { self.overflow.remove(id); }
+        }
+    }
+
+    //包围盒与view相交的实体写入out
+    pub fn query(&self,view:&Bounds,out:&mut BitSet) {
+        out.clear();
+        let range = self.cell_range(view);
+        let mut test = |id:u32| {
+            if !out.contains(id) && self.entries.get(&id).and_then(|e| e.bounds).is_none_or(|b| intersects(&b,view)) {
+                out.add(id);
+            }
+        };
+        //视野覆盖的格子比已有的格子多时直接遍历已有的格子
+        if SpatialGrid::range_count(&range) > self.cells.len() as i64 {
+            for ((x,y),lst) in self.cells.iter() {
+                if *x >= range[0] && *x <= range[2] && *y >= range[1] && *y <= range[3] {
+                    lst.iter().for_each(|id| test(*id));
+                }
+            }
+        } else {
+            for x in range[0]..=range[2] {
+                for y in range[1]..=range[3] {
+                    if let Some(lst) = self.cells.get(&(x,y)) {
+                        lst.iter().for_each(|id| test(*id));
+                    }
+                }
+            }
+        }
+        for id in (&self.overflow).iter() {
+            test(id);
+        }
+    }
+}
+
+#[test]
+fn test_spatial_grid_query() {
+    let mut grid = SpatialGrid::new(10f32);
+    grid.update(0,Some([0f32,0f32,5f32,5f32]));
+    grid.update(1,Some([100f32,100f32,105f32,105f32]));
+    grid.update(2,Some([-1000f32,-1000f32,1000f32,1000f32]));
+    grid.update(3,None);
+    let mut out = BitSet::new();
+    grid.query(&[-10f32,-10f32,10f32,10f32],&mut out);
+    assert_eq!((&out).iter().collect::<Vec<_>>(),vec![0,2,3]);
+
+    grid.update(0,Some([50f32,50f32,55f32,55f32]));
+    grid.remove(2);
+    grid.query(&[-10f32,-10f32,10f32,10f32],&mut out);
+    assert_eq!((&out).iter().collect::<Vec<_>>(),vec![3]);
+    grid.query(&[0f32,0f32,1000f32,1000f32],&mut out);
+    assert_eq!((&out).iter().collect::<Vec<_>>(),vec![0,1,3]);
+    assert_eq!(grid.len(),3);
+}
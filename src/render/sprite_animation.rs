@@ -0,0 +1,117 @@
+use crate::assets::{AssetStorage};
+use crate::core::{Time};
+use crate::render::components::{SpriteAnimation,AnimationEventKind,SpriteRender,SpriteSheet,Mesh2D};
+use specs::{Entity,Entities,Read,Write,WriteStorage,System,Join};
+use shrev::{EventChannel};
+
+#[derive(Debug,Clone)]
+pub struct SpriteAnimationEvent {
+    pub entity:Entity,
+    pub clip:String,
+    pub kind:AnimationEventKind
+}
+
+#[derive(Default)]
+pub struct SpriteAnimationSystem;
+
+impl<'a> System<'a> for SpriteAnimationSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a,Time>,
+        Read<'a,AssetStorage<SpriteSheet>>,
+        WriteStorage<'a,SpriteAnimation>,
+        WriteStorage<'a,SpriteRender>,
+        WriteStorage<'a,Mesh2D>,
+        Write<'a,EventChannel<SpriteAnimationEvent>>
+    );
+
+    fn run(&mut self,(entities,time,sheets,mut anims,mut sprites,mut meshes,mut events):Self::SystemData) {
+        let dt = time.delta_seconds();
+        for (entity,anim,sprite) in (&entities,&mut anims,&mut sprites).join() {
+            let sheet_handle = match sprite.sprite_sheet.as_ref() {
+                Some(handle) => handle,
+                None => continue
+            };
+            if let (Some(sheet),Some(version)) = (sheets.get(sheet_handle),sheets.get_version(sheet_handle)) {
+                anim.sync_sheet(sheet,version);
+            }
+            if let Some(kind) = anim.advance(dt) {
+                if let Some(clip) = anim.current_clip() {
+                    events.single_write(SpriteAnimationEvent {entity,clip:clip.clone(),kind });
+                }
+            }
+            if let Some(name) = anim.current_sprite() {
+                if sprite.sprite_name() != Some(name) {
+                    sprite.set_sprite_name(Some(name));
+                    if let Some(mesh) = meshes.get_mut(entity) {
+                        mesh.is_dirty = true;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_animation_system() {
+    use crate::assets::{Handle};
+    use crate::common::rect::{Rect};
+    use crate::render::components::{AnimationClip,AnimationMode,Sprite,TextureCoordinate};
+    use specs::{World,WorldExt,Builder,RunNow};
+    use fnv::FnvHashMap;
+    use std::time::{Duration};
+    let mut world = World::new();
+    world.register::<SpriteAnimation>();
+    world.register::<SpriteRender>();
+    world.register::<Mesh2D>();
+    world.insert(Time::default());
+    world.insert(EventChannel::<SpriteAnimationEvent>::new());
+    let mut sheets:AssetStorage<SpriteSheet> = AssetStorage::new();
+    let sprites:Vec<Sprite> = ["a","b","c"].iter().enumerate().map(|(i,name)| Sprite {
+        name:String::from(*name),
+        rect:Rect {x:i as i32 * 8,y:0,width:8,height:8},
+        coord:TextureCoordinate {left:i as f32 / 3f32,right:(i + 1) as f32 / 3f32,top:0f32,bottom:1f32},
+        rotated:false,
+        source_size:(8,8),
+        trim_offset:(0,0),
+        pivot:(0.5f32,0.5f32),
+        duration:None
+    }).collect();
+    let name_dic:FnvHashMap<String,u32> = sprites.iter().enumerate().map(|(i,s)| (s.name.clone(),i as u32)).collect();
+    let sheet = sheets.insert(SpriteSheet::new(24,8,Handle::new(0),sprites,name_dic,FnvHashMap::default(),Vec::new()));
+    world.insert(sheets);
+    world.fetch_mut::<Time>().set_delta_time(Duration::from_millis(101));
+    let mut reader = world.fetch_mut::<EventChannel<SpriteAnimationEvent>>().register_reader();
+
+    let mut anim = SpriteAnimation::new().with_clip("run",AnimationClip::from_names(&["a","b","c"],0.1,AnimationMode::PingPong))
+                                         .with_clip("once",AnimationClip::from_names(&["a","b","c"],0.1,AnimationMode::Once));
+    anim.play("run");
+    let entity = world.create_entity().with(anim).with(SpriteRender::new(Some(sheet),Some("a"))).with(Mesh2D::default()).build();
+    let mut system = SpriteAnimationSystem;
+    let mut step = |world:&World| {
+        world.write_storage::<Mesh2D>().get_mut(entity).unwrap().is_dirty = false;
+        system.run_now(world);
+        let name = world.read_storage::<SpriteRender>().get(entity).and_then(|s| s.sprite_name().cloned()).unwrap();
+        let dirty = world.read_storage::<Mesh2D>().get(entity).unwrap().is_dirty;
+        let events:Vec<(String,AnimationEventKind)> = world.fetch_mut::<EventChannel<SpriteAnimationEvent>>().read(&mut reader).map(|e| (e.clip.clone(),e.kind)).collect();
+        (name,dirty,events)
+    };
+    let mut seq = Vec::new();
+    let mut looped = 0;
+    for _ in 0..5 {
+        let (name,dirty,events) = step(&world);
+        assert!(dirty);
+        looped += events.iter().filter(|e| e.1 == AnimationEventKind::Looped).count();
+        seq.push(name);
+    }
+    assert_eq!(seq,vec!["b","c","b","a","b"]);
+    assert_eq!(looped,1);
+
+    world.write_storage::<SpriteAnimation>().get_mut(entity).unwrap().play("once");
+    assert_eq!(step(&world).0,"b");
+    assert_eq!(step(&world).0,"c");
+    let (name,dirty,events) = step(&world);
+    assert_eq!((name.as_str(),dirty),("c",false));
+    assert_eq!(events,vec![(String::from("once"),AnimationEventKind::Finished)]);
+    assert!(!world.read_storage::<SpriteAnimation>().get(entity).unwrap().is_playing());
+}
@@ -4,7 +4,7 @@ use crate::render::components::{ImageRender, Mesh2D, SpriteRender, SpriteSheet,
 use crate::render::pod::{SpriteArg, Vertex2D};
 use crate::render::types::{Backend, Texture};
 use crate::render::utils::vertex::{DynamicIndexBuffer, DynamicVertexBuffer};
-use crate::render::{env::FontEnv, FontAsset};
+use crate::render::{env::FontEnv, FontAsset, SpriteVisibility};
 use rendy::command::{QueueId, RenderPassEncoder};
 use rendy::factory::Factory;
 use specs::{Join, Read, ReadStorage, System, Write, WriteStorage};
@@ -21,21 +21,27 @@ pub struct SpriteMesh {
 
 impl SpriteMesh {
     pub fn calc_size(&self) -> (f32, f32) {
+        let [min_x, min_y, max_x, max_y] = self.calc_bounds();
+        (max_x - min_x, max_y - min_y)
+    }
+
+    //本地空间的包围盒[min_x,min_y,max_x,max_y]
+    pub fn calc_bounds(&self) -> [f32; 4] {
         let mut idx = 0;
         let mut min_x: f32 = f32::INFINITY;
         let mut max_x: f32 = -f32::INFINITY;
         let mut min_y: f32 = f32::INFINITY;
         let mut max_y: f32 = -f32::INFINITY;
-        while idx < self.meshes.len() {
+        while idx + 3 < self.meshes.len() {
             let lt: &[f32; 3] = self.meshes[idx].pos.as_ref();
             let rb: &[f32; 3] = self.meshes[idx + 3].pos.as_ref();
             min_x = lt[0].min(rb[0]).min(min_x);
-            max_x = lt[0].max(rb[0]).max(min_x);
+            max_x = lt[0].max(rb[0]).max(max_x);
             min_y = lt[1].min(rb[1]).min(min_y);
             max_y = lt[1].max(rb[1]).max(max_y);
             idx += 4
         }
-        (max_x - min_x, max_y - min_y)
+        [min_x, min_y, max_x, max_y]
     }
 }
 
@@ -68,6 +74,7 @@ impl<'a, B: Backend> System<'a> for SpriteMeshSystem<B> {
         Option<Read<'a, QueueId>>,
         Option<Write<'a, Factory<B>>>,
         Read<'a, AssetStorage<FontAsset>>,
+        Read<'a, SpriteVisibility>,
     );
 
     fn run(
@@ -85,11 +92,13 @@ impl<'a, B: Backend> System<'a> for SpriteMeshSystem<B> {
             may_qid,
             may_factory,
             font_storage,
+            visibility,
         ): Self::SystemData,
     ) {
-        for (img, mesh2d, t, rect) in (&mut images, &mut mesh2ds, &trans, &mut rects).join() {
-            if let Some(tex_id) = img.texture.as_ref() {
-                let tex = tex_storage.get(tex_id).unwrap();
+        //视野外的实体不更新网格,Rect2D的dirty标记保留到重新可见时
+        for (img, mesh2d, t, rect, _) in (&mut images, &mut mesh2ds, &trans, &mut rects, &visibility.visible).join() {
+            //贴图还在加载中时跳过
+            if let Some(tex) = img.texture.as_ref().and_then(|tex_id| tex_storage.get(tex_id)) {
                 if rect.dirty {
                     mesh2d.is_dirty = true;
                     rect.clear_dirty()
@@ -98,7 +107,7 @@ impl<'a, B: Backend> System<'a> for SpriteMeshSystem<B> {
             }
         }
 
-        for (sprite, mesh2d, t, rect) in (&mut sprites, &mut mesh2ds, &trans, &mut rects).join() {
+        for (sprite, mesh2d, t, rect, _) in (&mut sprites, &mut mesh2ds, &trans, &mut rects, &visibility.visible).join() {
             if rect.dirty {
                 mesh2d.is_dirty = true;
                 rect.clear_dirty()
@@ -112,15 +121,16 @@ impl<'a, B: Backend> System<'a> for SpriteMeshSystem<B> {
             &ReadStorage<'a, Transform>,
             &mut WriteStorage<'a, Rect2D>,
         ) = (&mut texts, &mut mesh2ds, &trans, &mut rects);
-        let qid = may_qid.unwrap();
-        let mut factory = may_factory.unwrap();
-        font_env.process(
-            &mut tex_storage,
-            &font_storage,
-            text_iter,
-            *qid,
-            &mut factory,
-        );
+        //headless模式下没有Factory,不生成文字贴图
+        if let (Some(qid), Some(mut factory)) = (may_qid, may_factory) {
+            font_env.process(
+                &mut tex_storage,
+                &font_storage,
+                text_iter,
+                *qid,
+                &mut factory,
+            );
+        }
     }
 }
 
@@ -1,24 +1,52 @@
 use hibitset::{BitSet};
-use specs::{Entity,Entities,Write,ReadStorage,Read,System,Join};
+use specs::{Entity,Entities,Read,Write,ReadStorage,System,SystemData,Join,World,WorldExt,ReaderId,storage::ComponentEvent};
+use fnv::{FnvHashMap};
 
-use nalgebra::{Point3,Vector3};
-use crate::render::{Camera,ActiveCamera,Transparent};
-use crate::common::{Transform,Hidden,HiddenPropagate};
+use nalgebra::{Point3,Vector3,Vector4};
+use crate::render::{Camera,Transparent,SortingOrder,SortingSettings,components::{Mesh2D}};
+use crate::render::spatial_grid::{SpatialGrid,Bounds};
+use crate::common::{Transform,Hidden,HiddenPropagate,EntityInfo,Rect2D,Tree,TreeNode,TreeEvent};
 use std::cmp::Ordering;
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
 
+//一个相机能看到的实体,camera为None时表示场景中没有相机,使用默认相机
 #[derive(Default, Debug)]
-pub struct SpriteVisibility {
+pub struct CameraVisibility {
+    pub camera: Option<Entity>,
     pub visible_unordered: BitSet,
     pub visible_ordered: Vec<Entity>,
+    pub stats: CullingStats,
+}
+
+//用于性能分析,culled包括被视野,层和隐藏剔除的实体
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct CullingStats {
+    pub visible: usize,
+    pub culled: usize,
 }
 
+//按Camera::order排好序,至少有一个
+#[derive(Default, Debug)]
+pub struct SpriteVisibility {
+    pub cameras: Vec<CameraVisibility>,
+    //至少被一个相机看到的实体,SpriteMeshSystem只处理这些实体
+    pub visible: BitSet,
+}
 
-#[derive(Default,Debug)]
 pub struct SpriteVisibilitySortingSystem {
     centroids: Vec<Internals>,
     transparent: Vec<Internals>,
+    grid: SpatialGrid,
+    candidates: BitSet,
+    dirty: BitSet,
+    transform_events: ReaderId<ComponentEvent>,
+    rect_events: ReaderId<ComponentEvent>,
+    mesh_events: ReaderId<ComponentEvent>,
+    //Tree中的先序遍历序号,只在Tree变化后重新计算
+    tree_index: FnvHashMap<u32,u32>,
+    tree_events: Option<ReaderId<TreeEvent>>,
+    tree_dirty: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -28,12 +56,97 @@ struct Internals {
     centroid: Point3<f32>,
     camera_distance: f32,
     from_camera: Vector3<f32>,
+    sorting: SortingOrder,
+    tree_index: Option<u32>,
 }
 
+const DEFAULT_CELL_SIZE:f32 = 256f32;
+
 impl SpriteVisibilitySortingSystem {
-    pub fn new() -> Self {
-        Default::default()
+    pub fn new(world: &mut World) -> Self {
+        <SpriteVisibilitySortingSystem as System<'_>>::SystemData::setup(world);
+        world.register::<Rect2D>();
+        world.register::<Mesh2D>();
+        let transform_events = world.write_storage::<Transform>().register_reader();
+        let rect_events = world.write_storage::<Rect2D>().register_reader();
+        let mesh_events = world.write_storage::<Mesh2D>().register_reader();
+        let tree_events = world.try_fetch_mut::<Tree>().map(|mut tree| tree.channel.register_reader());
+        SpriteVisibilitySortingSystem {
+            centroids: Vec::new(),
+            transparent: Vec::new(),
+            grid: SpatialGrid::new(DEFAULT_CELL_SIZE),
+            candidates: BitSet::new(),
+            dirty: BitSet::new(),
+            transform_events,
+            rect_events,
+            mesh_events,
+            tree_index: FnvHashMap::default(),
+            tree_events,
+            tree_dirty: true
+        }
+    }
+
+    //格子大小与世界单位相同,一般取屏幕大小的几分之一
+    pub fn with_cell_size(mut self,cell_size:f32) -> Self {
+        self.grid = SpatialGrid::new(cell_size);
+        self.dirty.clear();
+        self
+    }
+
+    fn update_tree_index(&mut self,tree:&Tree,tree_nodes:&ReadStorage<TreeNode>) {
+        match self.tree_events.as_mut() {
+            //创建时还没有Tree,只能每帧重新计算
+            None => self.tree_dirty = true,
+            Some(reader) => if tree.channel.read(reader).count() > 0 {
+                self.tree_dirty = true;
+            }
+        }
+        if !self.tree_dirty {
+            return;
+        }
+        self.tree_dirty = false;
+        self.tree_index.clear();
+        let mut stack:Vec<Entity> = tree.roots().iter().rev().cloned().collect();
+        while let Some(e) = stack.pop() {
+            let index = self.tree_index.len() as u32;
+            self.tree_index.insert(e.id(),index);
+            if let Some(node) = tree_nodes.get(e) {
+                stack.extend(node.children.iter().rev());
+            }
+        }
+    }
+}
+
+//Rect2D和网格顶点在世界空间的包围盒,都没有时返回None表示不剔除
+fn world_bounds(t:&Transform,rect:Option<&Rect2D>,mesh:Option<&Mesh2D>) -> Option<Bounds> {
+    let mut local:Option<Bounds> = rect.filter(|r| r.width > 0f32 || r.height > 0f32).map(|r| {
+        let [x0,x1,y0,y1] = r.corner_point();
+        [x0,y0,x1,y1]
+    });
+    if let Some(mesh) = mesh.and_then(|m| m.mesh.as_ref()).filter(|m| !m.meshes.is_empty()) {
+        let b = mesh.calc_bounds();
+        local = Some(local.map_or(b,|l| [l[0].min(b[0]),l[1].min(b[1]),l[2].max(b[2]),l[3].max(b[3])]));
+    }
+    let local = local?;
+    Some(transform_bounds(t.global_matrix(),&local,&[0f32]))
+}
+
+fn transform_bounds(mat:&nalgebra::Matrix4<f32>,b:&Bounds,zs:&[f32]) -> Bounds {
+    let mut ret = [f32::INFINITY,f32::INFINITY,-f32::INFINITY,-f32::INFINITY];
+    for &z in zs {
+        for &(x,y) in &[(b[0],b[1]),(b[2],b[1]),(b[0],b[3]),(b[2],b[3])] {
+            let p = mat * Vector4::new(x,y,z,1f32);
+            ret = [ret[0].min(p.x),ret[1].min(p.y),ret[2].max(p.x),ret[3].max(p.y)];
+        }
     }
+    ret
+}
+
+//正交相机在世界空间能看到的范围
+fn camera_bounds(camera:&Camera,t:Option<&Transform>) -> Bounds {
+    let identity = nalgebra::Matrix4::identity();
+    let inverse = t.map_or(&identity,|t| t.global_matrix()) * camera.as_inverse_matrix();
+    transform_bounds(&inverse,&[-1f32,-1f32,1f32,1f32],&[0f32,1f32])
 }
 
 impl<'a> System<'a> for SpriteVisibilitySortingSystem {
@@ -42,48 +155,195 @@ impl<'a> System<'a> for SpriteVisibilitySortingSystem {
         Write<'a, SpriteVisibility>,
         ReadStorage<'a, Hidden>,
         ReadStorage<'a, HiddenPropagate>,
-        Read<'a, ActiveCamera>,
         ReadStorage<'a, Camera>,
         ReadStorage<'a, Transparent>,
         ReadStorage<'a, Transform>,
+        ReadStorage<'a, EntityInfo>,
+        ReadStorage<'a, Rect2D>,
+        ReadStorage<'a, Mesh2D>,
+        ReadStorage<'a, SortingOrder>,
+        Read<'a, SortingSettings>,
+        Option<Read<'a, Tree>>,
+        ReadStorage<'a, TreeNode>,
     );
 
-    fn run(&mut self,(entities, mut visibility, hidden, hidden_prop, active, camera, transparent,transform): Self::SystemData) {
+    fn run(&mut self,(entities, mut visibility, hidden, hidden_prop, camera, transparent,transform,infos,rects,meshes,
+                      sortings,settings,tree,tree_nodes): Self::SystemData) {
        #[cfg(feature = "profiler")]
        profile_scope!("SpriteVisibilitySortingSystem");
-       //todo high cpu
+       let events = transform.channel().read(&mut self.transform_events).chain(rects.channel().read(&mut self.rect_events))
+                                                                          .chain(meshes.channel().read(&mut self.mesh_events));
+       for ev in events {
+           match ev {
+               ComponentEvent::Inserted(id) | ComponentEvent::Modified(id) | ComponentEvent::Removed(id) => { self.dirty.add(*id); }
+           }
+       }
+       for id in (&self.dirty).join() {
+           let entity = entities.entity(id);
+           match transform.get(entity).filter(|_| entities.is_alive(entity)) {
+               Some(t) => self.grid.update(id,world_bounds(t,rects.get(entity),meshes.get(entity))),
+               None => self.grid.remove(id)
+           }
+       }
+       self.dirty.clear();
+       let use_tree_order = match tree.as_ref() {
+           Some(tree) if settings.tree_order => {
+               self.update_tree_index(tree,&tree_nodes);
+               true
+           },
+           _ => false
+       };
+
+       let mut cameras:Vec<(Option<Entity>,Option<&Camera>)> = (&*entities,&camera).join().map(|(e,c)| (Some(e),Some(c))).collect();
+       cameras.sort_by_key(|(e,c)| (c.map(|c| c.order),e.map(|e| e.id())));
+       if cameras.is_empty() {
+           cameras.push((None,None));
+       }
+       let visibility = &mut *visibility;
+       visibility.cameras.resize_with(cameras.len(), Default::default);
+       visibility.visible.clear();
+
        let origin = Point3::<f32>::origin();
-       let camera_trans = active.entity.and_then(|a| transform.get(a))
-                                       .or_else(|| (&camera, &transform).join().map(|ct| ct.1).next());
-       let camera_backward = camera_trans.map(|c| c.global_matrix().column(2).xyz())
-                                         .unwrap_or_else(Vector3::z);
-       let camera_centroid = camera_trans.map(|t| t.global_matrix().transform_point(&origin))
-                                         .unwrap_or_else(|| origin);
-       self.centroids.clear();
-       self.centroids.extend((&*entities,&transform,!&hidden,!&hidden_prop).join().map(|(e,t,_,_)| (e,t.global_matrix().transform_point(&origin)))
-                                                           .filter(|(_,c)| (c - camera_centroid).dot(&camera_backward) >= 0.0)
-                                                           .map(|(entity,centroid)| Internals {
-                                                               entity,
-                                                               transparent:transparent.contains(entity),
-                                                               centroid,
-                                                               camera_distance:(centroid.z - camera_centroid.z).abs(),
-                                                               from_camera: centroid - camera_centroid,
-                                                           })
-                           );
-       visibility.visible_unordered.clear();
-       visibility.visible_unordered.extend(self.centroids.iter()
-                                                         .filter(|c| !c.transparent)
-                                                         .map(|c| c.entity.id()));
-       self.transparent.clear();
-       self.transparent.extend(self.centroids.drain(..).filter(|c| c.transparent));
-
-       self.transparent.sort_by(|a, b| {
-            b.camera_distance.partial_cmp(&a.camera_distance).unwrap_or(Ordering::Equal)
-        });
-
-        visibility.visible_ordered.clear();
-        visibility.visible_ordered.extend(self.transparent.iter().map(|c| c.entity));
+       for ((camera_entity,cam),view) in cameras.into_iter().zip(visibility.cameras.iter_mut()) {
+           let camera_trans = camera_entity.and_then(|e| transform.get(e));
+           let camera_backward = camera_trans.map(|c| c.global_matrix().column(2).xyz())
+                                             .unwrap_or_else(Vector3::z);
+           let camera_centroid = camera_trans.map(|t| t.global_matrix().transform_point(&origin))
+                                             .unwrap_or_else(|| origin);
+           //没有相机时不做视野剔除
+           let view_bounds = cam.map_or([f32::MIN,f32::MIN,f32::MAX,f32::MAX],|c| camera_bounds(c,camera_trans));
+           self.grid.query(&view_bounds,&mut self.candidates);
+           self.centroids.clear();
+           self.centroids.extend((&*entities,&transform,!&hidden,!&hidden_prop,&self.candidates).join()
+                                                               .filter(|(e,_,_,_,_)| {
+                                                                   let layer = infos.get(*e).map(|info| info.layer).unwrap_or(0);
+                                                                   cam.is_none_or(|c| c.is_layer_visible(layer))
+                                                               })
+                                                               .map(|(e,t,_,_,_)| (e,t.global_matrix().transform_point(&origin)))
+                                                               .filter(|(_,c)| (c - camera_centroid).dot(&camera_backward) >= 0.0)
+                                                               .map(|(entity,centroid)| Internals {
+                                                                   entity,
+                                                                   transparent:transparent.contains(entity),
+                                                                   centroid,
+                                                                   camera_distance:(centroid.z - camera_centroid.z).abs(),
+                                                                   from_camera: centroid - camera_centroid,
+                                                                   sorting:sortings.get(entity).cloned().unwrap_or_default(),
+                                                                   tree_index:None
+                                                               })
+                               );
+           view.camera = camera_entity;
+           view.stats = CullingStats {
+               visible: self.centroids.len(),
+               culled: self.grid.len().saturating_sub(self.centroids.len())
+           };
+           visibility.visible.extend(self.centroids.iter().map(|c| c.entity.id()));
+           view.visible_unordered.clear();
+           view.visible_unordered.extend(self.centroids.iter()
+                                                       .filter(|c| !c.transparent)
+                                                       .map(|c| c.entity.id()));
+           self.transparent.clear();
+           self.transparent.extend(self.centroids.drain(..).filter(|c| c.transparent));
+           if use_tree_order {
+               for c in self.transparent.iter_mut() {
+                   c.tree_index = self.tree_index.get(&c.entity.id()).cloned();
+               }
+           }
+           //稳定排序,键完全相同的实体保持实体id的顺序,不会在帧之间闪烁
+           self.transparent.sort_by(|a, b| {
+                a.sorting.layer.cmp(&b.sorting.layer)
+                 .then(a.sorting.order.cmp(&b.sorting.order))
+                 .then(a.tree_index.cmp(&b.tree_index))
+                 .then_with(|| b.camera_distance.partial_cmp(&a.camera_distance).unwrap_or(Ordering::Equal))
+           });
+
+           view.visible_ordered.clear();
+           view.visible_ordered.extend(self.transparent.iter().map(|c| c.entity));
+       }
     }
 
 
 }
+
+#[test]
+fn test_camera_culling_mask() {
+    use specs::{World,WorldExt,Builder,RunNow};
+    let mut world = World::new();
+    world.register::<Camera>();
+    world.register::<Transform>();
+    world.register::<Hidden>();
+    world.register::<HiddenPropagate>();
+    world.register::<Transparent>();
+    world.register::<EntityInfo>();
+    world.insert(SpriteVisibility::default());
+    let mut system = SpriteVisibilitySortingSystem::new(&mut world);
+    let ui_camera = world.create_entity().with(Transform::default()).with(Camera::standard_2d(100f32,100f32).with_order(1).with_culling_mask(1 << 5)).build();
+    let main_camera = world.create_entity().with(Transform::default()).with(Camera::standard_2d(100f32,100f32).with_culling_mask(1)).build();
+    let sprite = world.create_entity().with(Transform::default()).build();
+    let ui = world.create_entity().with(Transform::default()).with(Transparent)
+                                  .with(EntityInfo {layer:5,..Default::default() }).build();
+    let mut far = Transform::default();
+    far.set_position_x(1000f32);
+    far.global_matrix = far.matrix();
+    let far = world.create_entity().with(far).with(Rect2D::new(10f32,10f32,[0.5f32,0.5f32])).build();
+
+    system.run_now(&world);
+    let visibility = world.read_resource::<SpriteVisibility>();
+    assert_eq!(visibility.cameras.iter().map(|c| c.camera).collect::<Vec<_>>(),vec![Some(main_camera),Some(ui_camera)]);
+    assert!(visibility.cameras[0].visible_unordered.contains(sprite.id()));
+    assert!(visibility.cameras[0].visible_ordered.is_empty());
+    assert!(!visibility.cameras[1].visible_unordered.contains(sprite.id()));
+    assert_eq!(visibility.cameras[1].visible_ordered,vec![ui]);
+    assert!(!visibility.visible.contains(far.id()));
+    assert_eq!(visibility.cameras[0].stats,CullingStats {visible:3,culled:2 });
+}
+
+#[test]
+fn test_sorting_order() {
+    use specs::{Builder,RunNow};
+    let mut world = World::new();
+    world.insert(Tree::default());
+    let mut system = SpriteVisibilitySortingSystem::new(&mut world);
+    let create = |world:&mut World,z:f32| {
+        let mut t = Transform::default();
+        t.set_position_z(z);
+        t.global_matrix = t.matrix();
+        world.create_entity().with(t).with(Transparent).build()
+    };
+    let top = create(&mut world,0f32);
+    world.write_storage::<SortingOrder>().insert(top,SortingOrder::new(1,0)).unwrap();
+    let child = create(&mut world,5f32);
+    let parent = create(&mut world,1f32);
+    let near = create(&mut world,2f32);
+    let far = create(&mut world,8f32);
+    Tree::add(&mut world,parent,None);
+    Tree::add(&mut world,child,Some(parent));
+
+    system.run_now(&world);
+    let ordered = |world:&World| world.read_resource::<SpriteVisibility>().cameras[0].visible_ordered.clone();
+    assert_eq!(ordered(&world),vec![far,child,near,parent,top]);
+
+    world.write_resource::<SortingSettings>().tree_order = true;
+    system.run_now(&world);
+    assert_eq!(ordered(&world),vec![far,near,parent,child,top]);
+}
+
+#[test]
+fn test_mesh_change_updates_bounds() {
+    use specs::{Builder,RunNow};
+    use crate::render::components::{ImageRender};
+    let mut world = World::new();
+    let mut system = SpriteVisibilitySortingSystem::new(&mut world);
+    world.create_entity().with(Transform::default()).with(Camera::standard_2d(100f32,100f32)).build();
+    let mut t = Transform::default();
+    t.set_position_x(1000f32);
+    t.global_matrix = t.matrix();
+    let sprite = world.create_entity().with(t.clone()).with(Mesh2D::default()).build();
+    //还没有网格时不剔除
+    system.run_now(&world);
+    assert!(world.read_resource::<SpriteVisibility>().visible.contains(sprite.id()));
+
+    let rect = Rect2D::new(10f32,10f32,[0.5f32,0.5f32]);
+    ImageRender::new(None).process_mesh(&t,(10,10),&rect,world.write_storage::<Mesh2D>().get_mut(sprite).unwrap());
+    system.run_now(&world);
+    assert!(!world.read_resource::<SpriteVisibility>().visible.contains(sprite.id()));
+}
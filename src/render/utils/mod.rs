@@ -181,6 +181,10 @@ impl<I: Hash + Eq> LookupBuilder<I> {
             id_num
         }
     }
+
+    pub fn get(&self, id: I) -> Option<usize> {
+        self.forward.get(&id).copied()
+    }
 }
 
 
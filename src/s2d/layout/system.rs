@@ -1,6 +1,6 @@
 use crate::{common::{Rect2D, Transform, Tree, TreeEvent, TreeNode}, render::components::Mesh2D, window::ViewPortSize};
 use hibitset::BitSet;
-use specs::{Entities, Entity, ReadExpect, ReadStorage, System, SystemData, World, WriteStorage, prelude::ComponentEvent};
+use specs::{Entities, Entity, Join, ReadExpect, ReadStorage, System, SystemData, World, WriteStorage, prelude::ComponentEvent};
 use shrev::{ReaderId};
 use nalgebra::{Vector2,Vector3};
 
@@ -29,6 +29,7 @@ pub struct LayoutSystem {
    ev_tree:ReaderId<TreeEvent>,
    ev_view:ReaderId<ComponentEvent>,
    modified:BitSet,
+   view_size:Option<(f64,f64)>
 }
 
 impl LayoutSystem {
@@ -38,7 +39,8 @@ impl LayoutSystem {
         LayoutSystem {
            ev_tree:tree,
            ev_view,
-           modified:BitSet::new()
+           modified:BitSet::new(),
+           view_size:None
         }
     }
 
@@ -81,6 +83,15 @@ impl<'a> System<'a> for LayoutSystem {
     
     fn run(&mut self,mut ldata: Self::SystemData) {
        self.modified.clear();
+       //窗口大小变化后根节点的大小和原点都会变,重新布局所有根节点
+       let view_size = (ldata.4.width(),ldata.4.height());
+       if self.view_size.replace(view_size).is_some_and(|old| old != view_size) {
+           for (e,_) in (&ldata.0,&ldata.3).join() {
+               if ldata.2.get(e).and_then(|t| t.parent).is_none() {
+                   self.modified.add(e.id());
+               }
+           }
+       }
        for ev in ldata.3.channel().read(&mut self.ev_view) {
            match ev {
                ComponentEvent::Modified(e) => {
@@ -132,4 +143,28 @@ impl<'a> System<'a> for LayoutSystem {
 
        ldata.3.channel().read(&mut self.ev_view);
     }
-}
\ No newline at end of file
+}
+#[test]
+fn test_relayout_on_resize() {
+    use specs::{WorldExt,Builder,RunNow};
+    use super::View;
+    let mut world = World::new();
+    world.register::<TreeNode>();
+    world.register::<Rect2D>();
+    world.register::<Transform>();
+    world.register::<LayoutElement>();
+    world.register::<GridCell>();
+    world.insert(Tree::default());
+    world.insert(ViewPortSize::new(100f64,100f64));
+    let mut system = LayoutSystem::new(&mut world);
+    let root = world.create_entity().with(Transform::default()).with(Rect2D::new(0f32,0f32,[0.5f32,0.5f32]))
+                                    .with(LayoutElement::View(View::default())).build();
+    Tree::add(&mut world,root,None);
+    system.run_now(&world);
+    assert_eq!(world.read_storage::<Rect2D>().get(root).unwrap().width,100f32);
+
+    world.write_resource::<ViewPortSize>().set_size(200f64,80f64,2f64);
+    system.run_now(&world);
+    let rects = world.read_storage::<Rect2D>();
+    assert_eq!((rects.get(root).unwrap().width,rects.get(root).unwrap().height),(200f32,80f32));
+}
@@ -1,4 +1,4 @@
 pub mod layout;
 mod simple2d;
 pub mod ui;
-pub use simple2d::{Simple2d,S2DLoader,DefaultBackend};
\ No newline at end of file
+pub use simple2d::{Simple2d,S2DLoader,DefaultBackend,OffscreenTarget};
\ No newline at end of file
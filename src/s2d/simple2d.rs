@@ -2,19 +2,21 @@ use crate::core::{AppControlFlow, IModuleBundle,Time};
 use crate::window::{WindowModule,ViewPortSize};
 use rendy::factory::{Factory};
 use crate::render::{OutputOptions,ImageOptions,OutputColor,SpriteMeshSystem,
-                    RenderOrder,RenderSystem,RenderBuilder,GraphNodeBuilder,
-                    SpriteVisibilitySortingSystem,SpriteVisibility};
-use crate::render::groups::{Flat2DGroupDesc,Flat2DTransparentDesc};
+                    RenderOrder,RenderSystem,Camera,RenderBuilder,GraphNodeBuilder,
+                    SpriteVisibilitySortingSystem,SpriteVisibility,SpriteAnimationSystem,SpriteAnimationEvent,
+                    create_render_texture,CameraFilter,RenderFilters,types::{Texture}};
+use crate::render::groups::{Flat2DGroupDesc};
+use crate::render::components::{SpriteAnimation};
 use rendy::hal::image::{Kind};
 use rendy::graph::render::{RenderGroupDesc as _};
 use rendy::hal::command::{ClearValue,ClearDepthStencil};
 use rendy::hal::format::{Format};
-use specs::{DispatcherBuilder,World,WorldExt};
+use specs::{DispatcherBuilder,World,WorldExt,Join};
 use shrev::{EventChannel};
-use crate::common::{Tree,EntityInfo,transform::{build_transform_module},Rect2D,UpdateSystem,Update};
-use winit::{window::WindowBuilder};
-use crate::assets::{Loader,S2DAssetPack,StorageCenter};
-use crate::event::{GameEventHandle};
+use crate::common::{Tree,EntityInfo,transform::{build_transform_module},Rect2D,UpdateSystem,Update,TweenSystem,TweenCallbackSystem,TweenCompleted,Tweens};
+use winit::{window::WindowBuilder,dpi::{PhysicalSize}};
+use crate::assets::{Handle,Loader,S2DAssetPack,StorageCenter,AssetLoadSystem,HotReloadSystem};
+use crate::event::{GameEventHandle,EventReplayer};
 use crate::s2d::layout::{init_layout_system};
 
 use super::ui::{raw_input::RawInput, ui_system::UIUpdateSystem};
@@ -30,6 +32,22 @@ pub struct Simple2d  {
     bg_color:[f32;4],
     event_handle:GameEventHandle,
     update_system:UpdateSystem,
+    headless:Option<(f64,f64)>,
+    offscreen:Option<(u32,u32)>
+}
+
+//with_offscreen时场景额外渲染到的贴图,可以用ImageRender显示或者通过FrameCapture读回
+pub struct OffscreenTarget {
+    pub texture:Handle<Texture>
+}
+
+impl OffscreenTarget {
+    pub const NODE:&'static str = "offscreen2d";
+
+    //默认绘制整个场景,指定相机后只绘制这个相机(例如小地图),这个相机也不再绘制到窗口
+    pub fn set_filter(world:&mut World,filter:CameraFilter) {
+        world.entry::<RenderFilters>().or_insert_with(RenderFilters::default).set(OffscreenTarget::NODE,filter);
+    }
 }
 
 impl Simple2d  {
@@ -40,7 +58,9 @@ impl Simple2d  {
             win_builder:Some(WindowBuilder::new()),
             bg_color:[0.8f32,0.8f32,0.8f32,1.0],
             event_handle:GameEventHandle::new(),
-            update_system:UpdateSystem::default()
+            update_system:UpdateSystem::default(),
+            headless:None,
+            offscreen:None
         }
     }
 
@@ -51,6 +71,101 @@ impl Simple2d  {
     pub fn with_bg_color(&mut self,color:[f32;4]) {
         self.bg_color = color;
     }
+
+    //不创建窗口和渲染,只运行布局,变换,事件和Update系统,用于CI中的测试
+    //rendy的empty后端没有可用的Adapter,无法创建Factory,所以不提供在empty后端构建渲染图的选项,
+    //需要上传到GPU的资源(贴图,图集)加载时返回NoFactory,依赖这些资源的场景不能以headless运行
+    pub fn with_headless(&mut self,width:f64,height:f64) {
+        self.headless = Some((width,height));
+    }
+
+    pub fn with_offscreen(&mut self,width:u32,height:u32) {
+        self.offscreen = Some((width,height));
+    }
+
+    pub fn is_headless(&self) -> bool {
+        self.headless.is_some()
+    }
+
+    fn set_view_size(&mut self,world:&mut World,win_size:PhysicalSize<u32>,scale_factor:f64) {
+        let size = win_size.to_logical::<f64>(scale_factor);
+        world.insert(ViewPortSize::new(size.width,size.height).with_scale_factor(scale_factor));
+        self.event_handle.set_view_size((size.width,size.height));
+        self.event_handle.set_scale_factor(scale_factor);
+    }
+
+    //交换链和深度图都依赖窗口大小,窗口变化后需要重新构建渲染图,最小化时大小为0不重建
+    fn on_resize(&mut self,world:&mut World,win_size:PhysicalSize<u32>,scale_factor:f64) {
+        self.set_view_size(world,win_size,scale_factor);
+        if win_size.width == 0 || win_size.height == 0 {
+            return;
+        }
+        Simple2d::resize_cameras(world);
+        if self.render_system.is_none() {
+            return;
+        }
+        let render_builder = self.create_render_builder(world,win_size);
+        self.render_system.as_mut().unwrap().re_build(render_builder,world);
+    }
+
+    fn resize_cameras(world:&mut World) {
+        let (width,height) = {
+            let view_size = world.fetch::<ViewPortSize>();
+            (view_size.width() as f32,view_size.height() as f32)
+        };
+        for camera in (&mut world.write_storage::<Camera>()).join().filter(|c| c.follow_view_size) {
+            camera.resize_to_view(width,height);
+        }
+    }
+
+    fn create_render_builder(&self,world:&mut World,win_size:PhysicalSize<u32>) -> RenderBuilder<DefaultBackend> {
+        let win_surface = world.fetch_mut::<Factory<DefaultBackend>>().create_surface(self.window.get_window()).unwrap();
+        let kind = Kind::D2(win_size.width, win_size.height, 1, 1);
+        let depth = Some(ImageOptions {
+            kind,
+            levels: 1,
+            format: Format::D32Sfloat,
+            clear: Some(ClearValue { depth_stencil:ClearDepthStencil {
+                depth:1.0, stencil:0
+            } }),
+         });
+
+        let mut render_builder = RenderBuilder::new().with_render_size(win_size.width,win_size.height);
+        let mut node2d = GraphNodeBuilder::new().with_name("flat2d");
+        if let Some((w,h)) = self.offscreen {
+            //重建时继续使用原来的贴图,ImageRender等对它的引用仍然有效
+            let texture = world.try_fetch::<OffscreenTarget>().map(|t| t.texture.clone());
+            let texture = texture.unwrap_or_else(|| create_render_texture::<DefaultBackend>(world,w,h));
+            let clear = self.window.clear_color.map(|c| ClearValue {color:c});
+            let offscreen = GraphNodeBuilder::new().with_name(OffscreenTarget::NODE)
+                                                   .with_group(RenderOrder::Opaque,Flat2DGroupDesc::new().with_output(OffscreenTarget::NODE).builder())
+                                                   .build(OutputOptions {
+                                                       colors: vec![OutputColor::Texture(texture.clone(),clear)],
+                                                       depth: depth.map(|d| ImageOptions {kind:Kind::D2(w,h,1,1),..d })
+                                                   });
+            render_builder = render_builder.with_node(offscreen).with_capture(OffscreenTarget::NODE);
+            node2d = node2d.with_dep(OffscreenTarget::NODE);
+            world.insert(OffscreenTarget {texture });
+        }
+        let node2d = node2d
+                                           .with_group(RenderOrder::Opaque,Flat2DGroupDesc::new().builder())
+                                           .build(OutputOptions {
+                                                     colors: vec![OutputColor::Surface(win_surface,self.window.clear_color.map(|c|
+                                                        ClearValue {color:c}
+                                                     ))],
+                                                     depth: depth
+                                                  });
+        render_builder.with_root_node(node2d)
+    }
+
+    fn start_headless(&mut self,world:&mut World,(width,height):(f64,f64)) {
+        world.insert(ViewPortSize::new(width,height));
+        self.event_handle.set_view_size((width,height));
+        RenderSystem::<DefaultBackend>::register(world);
+        S2DAssetPack::register_all_storage(world);
+        world.insert(StorageCenter::default());
+        world.insert(Loader::<S2DAssetPack>::default());
+    }
 }
 
 impl IModuleBundle for Simple2d  {
@@ -60,67 +175,69 @@ impl IModuleBundle for Simple2d  {
         world.register::<Rect2D>();
         world.register::<RawInput>();
         world.register::<Update>();
+        world.register::<SpriteAnimation>();
+        world.register::<Tweens>();
         
         world.insert(Tree::default());
+        world.insert(TweenCompleted::default());
+        //tween与layout/transform_system写同一批storage,先加入则先运行
+        builder.add(TweenSystem, "tween_system", &[]);
         init_layout_system(world, builder);
         build_transform_module(world,builder);
         
         builder.add(UIUpdateSystem::default(), "UIUpdateSystem", &[]);
-        builder.add(SpriteVisibilitySortingSystem::new(), &"sprite_visibility_system", &[]);
-        builder.add(SpriteMeshSystem::<DefaultBackend>::new(),&"sprite_mesh",&[&"sprite_visibility_system"]);
+        builder.add(SpriteVisibilitySortingSystem::new(world), &"sprite_visibility_system", &["transform_system"]);
+        builder.add(SpriteAnimationSystem, "sprite_animation", &[]);
+        builder.add(SpriteMeshSystem::<DefaultBackend>::new(),&"sprite_mesh",&[&"sprite_visibility_system","sprite_animation"]);
+        builder.add_thread_local(TweenCallbackSystem);
+        builder.add_thread_local(AssetLoadSystem::<S2DAssetPack>::default());
+        builder.add_thread_local(HotReloadSystem::<S2DAssetPack>::default());
+        S2DAssetPack::register_all_system(builder);
        
         world.insert(SpriteVisibility::default());
+        world.insert(EventChannel::<SpriteAnimationEvent>::new());
        
         GameEventHandle::register(world);
     }
 
     fn start(&mut self,world:&mut World) {
+        if let Some(size) = self.headless {
+            self.start_headless(world,size);
+            return;
+        }
         self.window.start(self.win_builder.take().unwrap());
         self.window.set_clear_color(rendy::hal::command::ClearColor {
             float32:self.bg_color
         });
         let mut render_system = RenderSystem::new(world);
-        let win_surface = world.fetch_mut::<Factory<rendy::vulkan::Backend>>().create_surface(self.window.get_window()).unwrap();
-        let win_size = self.window.win_attr().inner_size.unwrap().to_logical(1f64);
-        world.insert(ViewPortSize::new(win_size.width ,win_size.height));
-        self.event_handle.set_view_size((win_size.width,win_size.height));
-        let kind = Kind::D2(win_size.width as u32, win_size.height as u32, 1, 1);
-        let depth = Some(ImageOptions {
-            kind,
-            levels: 1,
-            format: Format::D32Sfloat,
-            clear: Some(ClearValue { depth_stencil:ClearDepthStencil {
-                depth:1.0, stencil:0
-            } }),
-         });
-
-        let node2d = GraphNodeBuilder::new().with_name("flat2d")
-                                           .with_group(RenderOrder::Opaque,Flat2DGroupDesc::new().builder())
-                                           .with_group(RenderOrder::Transparent, Flat2DTransparentDesc::new().builder())
-                                           .build(OutputOptions {
-                                                     colors: vec![OutputColor::Surface(win_surface,self.window.clear_color.map(|c|
-                                                        ClearValue {color:c}
-                                                     ))],
-                                                     depth: depth
-                                                  });
-        render_system.build(RenderBuilder::new().with_root_node(node2d), world);
-
+        let (win_size,scale_factor) = self.window.size_and_scale();
+        self.set_view_size(world,win_size,scale_factor);
         S2DAssetPack::register_all_storage(world);
         world.insert(StorageCenter::default());
         world.insert(Loader::<S2DAssetPack>::default());
+
+        let render_builder = self.create_render_builder(world,win_size);
+        render_system.build(render_builder, world);
         
         self.render_system = Some(render_system);
     }
 
     fn update(&mut self,world:&mut World) {
-        let win_events = self.window.update();
-        self.event_handle.fire_event(&win_events,world);
+        let win_events = if self.headless.is_none() { self.window.update() } else { vec![] };
+        if let Some((win_size,scale_factor)) = self.window.take_resized() {
+            self.on_resize(world,win_size,scale_factor);
+        }
+        if !EventReplayer::replay_frame(world,&mut self.event_handle) {
+            self.event_handle.fire_event(&win_events,world);
+        }
         if WindowModule::has_close_event(&win_events) {
             world.write_resource::<EventChannel<AppControlFlow>>().single_write(AppControlFlow::Quit);
         };
         let dt = world.read_resource::<Time>().delta_seconds();
         self.update_system.update(dt, world);
-        self.render_system.as_mut().unwrap().update(world);
+        if let Some(render_system) = self.render_system.as_mut() {
+            render_system.update(world);
+        }
     }
 
     fn quit(&mut self,world:&mut World) {
@@ -131,3 +248,115 @@ impl IModuleBundle for Simple2d  {
         self.window.quit();
     }
 }
+
+#[test]
+fn test_headless_replay() {
+    use crate::app::{AppBuilder};
+    use crate::core::{IGame,LimitSetting};
+    use crate::common::{Transform};
+    use crate::event::{EventNode,GameEventType,GameEvent,PointerId};
+    use crate::event::cb_event::{CABEventRoot};
+    use specs::{Builder};
+    use std::sync::{Arc,Mutex};
+    struct ClickGame {
+        log:Arc<Mutex<Vec<String>>>
+    }
+    impl IGame for ClickGame {
+        fn start(&mut self,world:&mut World) {
+            let view_width = world.fetch::<ViewPortSize>().width();
+            self.log.lock().unwrap().push(format!("view {}",view_width));
+            let log = self.log.clone();
+            let mut ev_node = EventNode::default();
+            ev_node.register(false,GameEventType::Click,move |_,w| {
+                log.lock().unwrap().push(format!("click {}",w.read_resource::<Time>().frame_number()));
+            });
+            let root = world.create_entity().with(Transform::default()).with(Rect2D::new(320f32,240f32,[0.5f32,0.5f32]))
+                            .with(CABEventRoot::default()).with(ev_node).build();
+            Tree::add(world,root,None);
+            let events = vec![(1,GameEvent::TouchStart(PointerId::Mouse,(0f64,0f64))),(2,GameEvent::TouchEnd(PointerId::Mouse,(0f64,0f64)))];
+            world.insert(EventReplayer::new(events));
+        }
+
+        fn update(&mut self,world:&mut World) {
+            if world.read_resource::<Time>().frame_number() >= 4 {
+                world.write_resource::<EventChannel<AppControlFlow>>().single_write(AppControlFlow::Quit);
+            }
+        }
+
+        fn quit(&mut self,_:&mut World) {}
+    }
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut s2d = Simple2d::new();
+    s2d.with_headless(320f64,240f64);
+    let mut app = AppBuilder::new().with_update_limiter(LimitSetting::Unlimited).build(s2d,ClickGame {log:log.clone() });
+    app.run();
+    assert_eq!(*log.lock().unwrap(),vec!["view 320","click 2"]);
+}
+
+#[test]
+fn test_headless_loader() {
+    use crate::app::{AppBuilder};
+    use crate::core::{IGame,LimitSetting};
+    use crate::assets::{AssetErrorKind,MemorySource,TextuteLoaderInfo,InputBindingsLoaderInfo,LoadState,PNG_1X1};
+    use std::sync::{Arc,Mutex};
+    struct LoadGame {
+        log:Arc<Mutex<Vec<String>>>,
+        pending:Option<Handle<Texture>>
+    }
+    impl IGame for LoadGame {
+        fn start(&mut self,world:&mut World) {
+            let loader = world.fetch::<S2DLoader>();
+            loader.env().add_source("mem",Box::new(MemorySource::from_static(&[("keys.json",br#"{"actions":{"jump":["Space"]}}"#),("a.png",PNG_1X1)])));
+            let bindings = loader.load_sync::<_,DefaultBackend>(InputBindingsLoaderInfo::new("keys.json").with_source("mem"),world);
+            self.log.lock().unwrap().push(format!("bindings {}",bindings.is_ok()));
+            let err = loader.load_sync::<_,DefaultBackend>(TextuteLoaderInfo::new_only_path("a.png").with_source("mem"),world).err().map(|e| e.kind());
+            self.log.lock().unwrap().push(format!("texture {:?}",err));
+            self.pending = Some(loader.load_async::<_,DefaultBackend>(TextuteLoaderInfo::new_only_path("a.png").with_source("mem"),world));
+        }
+
+        fn update(&mut self,world:&mut World) {
+            let state = world.fetch::<S2DLoader>().load_state(self.pending.as_ref().unwrap());
+            let frame = world.read_resource::<Time>().frame_number();
+            if let Some(LoadState::Failed(err)) = state {
+                self.log.lock().unwrap().push(format!("async {:?}",err.kind()));
+                world.write_resource::<EventChannel<AppControlFlow>>().single_write(AppControlFlow::Quit);
+            } else if frame >= 1000 {
+                world.write_resource::<EventChannel<AppControlFlow>>().single_write(AppControlFlow::Quit);
+            }
+        }
+
+        fn quit(&mut self,_:&mut World) {}
+    }
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut s2d = Simple2d::new();
+    s2d.with_headless(320f64,240f64);
+    let mut app = AppBuilder::new().with_update_limiter(LimitSetting::Unlimited).build(s2d,LoadGame {log:log.clone(),pending:None });
+    app.run();
+    assert_eq!(*log.lock().unwrap(),vec![String::from("bindings true"),format!("texture {:?}",Some(AssetErrorKind::NoFactory)),format!("async {:?}",AssetErrorKind::NoFactory)]);
+}
+
+#[test]
+fn test_resize_camera_projection() {
+    use specs::{Builder};
+    let mut world = World::new();
+    world.register::<Camera>();
+    let mut s2d = Simple2d::new();
+    s2d.with_headless(320f64,240f64);
+    s2d.start(&mut world);
+    let main = world.create_entity().with(Camera::standard_2d(320f32,240f32).with_follow_view_size(true)).build();
+    let minimap = world.create_entity().with(Camera::standard_2d(320f32,240f32).with_viewport(0.75f32,0f32,0.25f32,0.5f32).with_follow_view_size(true)).build();
+    let fixed = world.create_entity().with(Camera::standard_2d(320f32,240f32)).build();
+
+    s2d.on_resize(&mut world,PhysicalSize::new(1280,960),2f64);
+    let cameras = world.read_storage::<Camera>();
+    assert_eq!(cameras.get(main).unwrap().projection(),Camera::standard_2d(640f32,480f32).projection());
+    assert_eq!(cameras.get(minimap).unwrap().projection(),Camera::standard_2d(160f32,240f32).projection());
+    assert_eq!(cameras.get(fixed).unwrap().projection(),Camera::standard_2d(320f32,240f32).projection());
+    drop(cameras);
+
+    //最小化时保持原来的投影
+    s2d.on_resize(&mut world,PhysicalSize::new(0,0),2f64);
+    assert_eq!(world.read_storage::<Camera>().get(main).unwrap().projection(),Camera::standard_2d(640f32,480f32).projection());
+}
@@ -1,4 +1,4 @@
-use crate::{assets::{Handle}, common::{AnchorAlign, Rect2D, Transform, Tree}, core::Time, event::{global::GlobalEventNode, EventNode, GameEvent, GameEventCallBack, GameEventType}, render::{
+use crate::{assets::{Handle}, common::{AnchorAlign, Rect2D, Transform, Tree}, event::{CurrentEvent, EventNode, GameEvent, GameEventType, KeyCode}, render::{
         components::{Mesh2D, TextRender},
         FontAsset, Transparent,
     }, s2d::layout::{ContentView, LayoutElement, View}};
@@ -18,76 +18,12 @@ pub struct RawInput {
     pub time:f32,
     pub cursor_idx:usize,
     pub show_cursor:bool,
-
-
-    pub click_frame:u64,
 }
 
 impl Component for RawInput {
     type Storage = DenseVecStorage<Self>;
 }
 
-pub struct RawInputCallBack {
-    pub entity:Entity,
-    pub label:Entity
-}
-
-impl GameEventCallBack for RawInputCallBack {
-    fn run(&self, ev:&GameEvent, world:&mut World) {
-        let mut raw_inputs:WriteStorage<RawInput> = world.write_storage::<RawInput>();
-        let mut texts:WriteStorage<TextRender> = world.write_storage::<TextRender>();
-        let raw_input = raw_inputs.get_mut(self.entity).unwrap();
-        if !raw_input.is_focus {
-            return;
-        }
-        match ev {
-            GameEvent::RecvChar(chr) => {
-                if *chr == '\u{8}' {
-                        let rm_idx = raw_input.cursor_idx as i32 - 1;
-                        if rm_idx >= 0 {
-                            raw_input.text_value = string_rm_idx(&raw_input.text_value,raw_input.cursor_idx);
-                            raw_input.cursor_idx -= 1;
-                        }
-                    
-                } else {
-                    raw_input.text_value = string_insert_idx(&raw_input.text_value, raw_input.cursor_idx, *chr);
-                    raw_input.cursor_idx += 1;
-                }
-
-                let text = texts.get_mut(self.label).unwrap();
-                let  set_s = string_insert_idx(&raw_input.text_value, raw_input.cursor_idx, '|');
-                text.set_text_string(set_s);
-            },
-            GameEvent::KeyBoard(code,b) => {
-                match code {
-                    70 if raw_input.cursor_idx > 0 && *b => {
-                        raw_input.cursor_idx -= 1;
-                        raw_input.time = 0f32;
-                        raw_input.update_show_cursor(true,&mut texts);
-                    },
-                    72 if raw_input.cursor_idx < raw_input.char_len() && *b => {
-                        raw_input.cursor_idx += 1;
-                        raw_input.time = 0f32;
-                        raw_input.update_show_cursor(true,&mut texts);
-                    },
-                    _ => {}
-                }
-            },
-            GameEvent::TouchStart(_) => {
-                let f = world.read_resource::<Time>().frame_number();
-                if f != raw_input.click_frame {
-                    raw_input.is_focus = false;
-                    raw_input.update_show_cursor(false,&mut texts);
-                }
-            },
-            _ => {}
-        }
-        
-        
-        
-    }
-}
-
 impl RawInput {
     pub fn new(label: Entity) -> RawInput {
         RawInput {
@@ -96,8 +32,7 @@ impl RawInput {
             is_focus: false,
             show_cursor: false,
             time:0f32,
-            cursor_idx:0,
-            click_frame:0
+            cursor_idx:0
         }
     }
 
@@ -128,16 +63,31 @@ impl RawInput {
                     .unwrap();
             }
             let mut ev_node = EventNode::default();
-            ev_node.register(true, crate::event::GameEventType::TouchStart, |e, w| {
+            ev_node.tab_index = Some(0);
+            ev_node.register(false, GameEventType::FocusIn, |e, w| {
                 let mut raw_inputs: WriteStorage<RawInput> = w.write_storage::<RawInput>();
-                let raw_input = raw_inputs.get_mut(e).unwrap();
-                raw_input.is_focus = true;
-                raw_input.click_frame = w.read_resource::<Time>().frame_number();
                 let mut texts:WriteStorage<TextRender> = w.write_storage::<TextRender>();
-                raw_input.update_show_cursor(true, &mut texts);
-                raw_input.show_cursor = true;
-                raw_input.time = 0f32;
+                if let Some(raw_input) = raw_inputs.get_mut(e) {
+                    raw_input.set_focus(true, &mut texts);
+                }
             });
+            ev_node.register(false, GameEventType::FocusOut, |e, w| {
+                let mut raw_inputs: WriteStorage<RawInput> = w.write_storage::<RawInput>();
+                let mut texts:WriteStorage<TextRender> = w.write_storage::<TextRender>();
+                if let Some(raw_input) = raw_inputs.get_mut(e) {
+                    raw_input.set_focus(false, &mut texts);
+                }
+            });
+            let on_key = |e:Entity, w:&World| {
+                let current = w.read_resource::<CurrentEvent>();
+                let mut raw_inputs: WriteStorage<RawInput> = w.write_storage::<RawInput>();
+                let mut texts:WriteStorage<TextRender> = w.write_storage::<TextRender>();
+                if let (Some(ev),Some(raw_input)) = (current.get(),raw_inputs.get_mut(e)) {
+                    raw_input.on_key_event(ev, &mut texts);
+                }
+            };
+            ev_node.register(false, GameEventType::KeyBoard, on_key);
+            ev_node.register(false, GameEventType::RecvChar, on_key);
             evnodes.insert(entity, ev_node).unwrap();
 
             
@@ -164,16 +114,54 @@ impl RawInput {
         let raw_input = RawInput::new(label_entity);
         world.write_storage::<RawInput>().insert(entity, raw_input).unwrap();
 
-        let mut global_events: WriteStorage<GlobalEventNode> = world.write_storage::<GlobalEventNode>();
-        let mut global_event = GlobalEventNode::default();
-        global_event.insert(GameEventType::RecvChar, Box::new(RawInputCallBack {entity,label:label_entity}));
-        global_event.insert(GameEventType::KeyBoard, Box::new(RawInputCallBack {entity,label:label_entity}));
-        global_event.insert(GameEventType::TouchStart, Box::new(RawInputCallBack {entity,label:label_entity}));
-        global_events.insert(entity, global_event).unwrap();
-
         true
     }
 
+    pub fn set_focus(&mut self,b:bool, texts: &mut WriteStorage<TextRender>) {
+        self.is_focus = b;
+        self.show_cursor = b;
+        self.time = 0f32;
+        self.update_show_cursor(b, texts);
+    }
+
+    pub fn on_key_event(&mut self,ev:&GameEvent, texts: &mut WriteStorage<TextRender>) {
+        match ev {
+            GameEvent::RecvChar(chr) => {
+                if *chr == '\u{8}' {
+                    if self.cursor_idx > 0 {
+                        self.text_value = string_rm_idx(&self.text_value,self.cursor_idx);
+                        self.cursor_idx -= 1;
+                    }
+                } else if chr.is_control() {
+                    //Tab,回车等控制字符不写入文本
+                    return;
+                } else {
+                    self.text_value = string_insert_idx(&self.text_value, self.cursor_idx, *chr);
+                    self.cursor_idx += 1;
+                }
+                self.time = 0f32;
+                self.show_cursor = true;
+                self.update_show_cursor(true, texts);
+            },
+            GameEvent::KeyBoard(code,b,_) => {
+                match code {
+                    KeyCode::Left if self.cursor_idx > 0 && *b => {
+                        self.cursor_idx -= 1;
+                        self.time = 0f32;
+                        self.update_show_cursor(true,texts);
+                    },
+                    KeyCode::Right if self.cursor_idx < self.char_len() && *b => {
+                        self.cursor_idx += 1;
+                        self.time = 0f32;
+                        self.update_show_cursor(true,texts);
+                    },
+                    _ => {}
+                }
+            },
+            _ => {}
+        }
+    }
+
     pub fn update(&mut self, texts: &mut WriteStorage<TextRender>,dt:f32) {
         self.time += dt;
         if self.time >= 0.5f32 {
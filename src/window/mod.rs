@@ -1,19 +1,27 @@
 mod window;
 pub use window::{WindowModule};
 
+//逻辑像素大小,布局和事件坐标使用,乘以scale_factor得到渲染用的物理像素
 pub struct ViewPortSize {
     width:f64,
-    height:f64
+    height:f64,
+    scale_factor:f64
 }
 
 impl ViewPortSize {
     pub fn new(w:f64,h:f64) -> Self {
         ViewPortSize {
             width:w,
-            height:h
+            height:h,
+            scale_factor:1f64
         }
     }
 
+    pub fn with_scale_factor(mut self,scale_factor:f64) -> Self {
+        self.scale_factor = scale_factor;
+        self
+    }
+
     pub fn width(&self) -> f64 {
         self.width
     }
@@ -21,4 +29,18 @@ impl ViewPortSize {
     pub fn height(&self) -> f64 {
         self.height
     }
-}
\ No newline at end of file
+
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    pub fn physical_size(&self) -> (u32,u32) {
+        ((self.width * self.scale_factor).round() as u32,(self.height * self.scale_factor).round() as u32)
+    }
+
+    pub fn set_size(&mut self,w:f64,h:f64,scale_factor:f64) {
+        self.width = w;
+        self.height = h;
+        self.scale_factor = scale_factor;
+    }
+}
@@ -1,10 +1,11 @@
-use winit::{window::{WindowBuilder,WindowAttributes,Window},event::{Event,WindowEvent},event_loop::{EventLoop,ControlFlow}};
+use winit::{window::{WindowBuilder,WindowAttributes,Window},event::{Event,WindowEvent},event_loop::{EventLoop,ControlFlow},dpi::{PhysicalSize}};
 use rendy::hal::command::{ClearColor};
 use winit::platform::run_return::EventLoopExtRunReturn;
 pub struct WindowModule {
   window:Option<Window>,
   win_attr:Option<WindowAttributes>,
   event_loop:Option<EventLoop<()>>,
+  resized:bool,
   pub clear_color:Option<ClearColor>
 }
 
@@ -21,17 +22,21 @@ impl WindowModule {
         self.event_loop = Some(event_loop);
     }
 
-    pub fn update(&mut self) -> Vec<Event<()>> {
+    pub fn update(&mut self) -> Vec<Event<'static,()>> {
        let event_ref:&mut EventLoop<()> = self.event_loop.as_mut().unwrap();
        let mut events = Vec::new(); 
-       
+       let mut resized = false;
        event_ref.run_return(|ev:Event<'_,()>,_,control_flow| {
+           //ScaleFactorChanged带有引用,to_static后会丢失,需要在这里记录
+           if let Event::WindowEvent {event:WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged {..},..} = ev {
+               resized = true;
+           }
            if let Some(s_ev) = ev.to_static() {
             events.push(s_ev);
            }
            *control_flow = ControlFlow::Exit;
        });
-      
+       self.resized |= resized;
        events
     }
 
@@ -40,6 +45,21 @@ impl WindowModule {
         self.window.as_ref().unwrap()
     }
 
+    //窗口的物理像素大小和缩放比例
+    pub fn size_and_scale(&self) -> (PhysicalSize<u32>,f64) {
+        let win = self.get_window();
+        (win.inner_size(),win.scale_factor())
+    }
+
+    //上次调用后窗口大小或缩放比例是否变化过
+    pub fn take_resized(&mut self) -> Option<(PhysicalSize<u32>,f64)> {
+        if std::mem::replace(&mut self.resized,false) && self.window.is_some() {
+            Some(self.size_and_scale())
+        } else {
+            None
+        }
+    }
+
     pub fn win_attr(&self) -> &WindowAttributes {
         self.win_attr.as_ref().unwrap()
     }
@@ -76,6 +96,7 @@ impl Default for WindowModule {
             window:None,
             event_loop:None,
             win_attr:None,
+            resized:false,
             clear_color:None
         }
     }